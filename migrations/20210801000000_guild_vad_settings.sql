@@ -0,0 +1,3 @@
+-- per-guild voice activity detection thresholds
+ALTER TABLE guilds ADD COLUMN IF NOT EXISTS vad_threshold REAL NOT NULL DEFAULT -45;
+ALTER TABLE guilds ADD COLUMN IF NOT EXISTS vad_silence_ms INTEGER NOT NULL DEFAULT 800;
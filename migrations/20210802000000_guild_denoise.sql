@@ -0,0 +1,2 @@
+-- per-guild background noise suppression toggle
+ALTER TABLE guilds ADD COLUMN IF NOT EXISTS denoise BOOLEAN NOT NULL DEFAULT false;
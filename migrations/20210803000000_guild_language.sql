@@ -0,0 +1,2 @@
+-- per-guild transcription language, NULL means the bot's default
+ALTER TABLE guilds ADD COLUMN IF NOT EXISTS language TEXT;
@@ -0,0 +1,7 @@
+-- per-guild words to bias speech-to-text towards
+CREATE TABLE IF NOT EXISTS hot_words (
+    guild_id BIGINT NOT NULL,
+    word TEXT NOT NULL,
+    boost REAL NOT NULL,
+    PRIMARY KEY (guild_id, word)
+);
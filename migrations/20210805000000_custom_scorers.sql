@@ -0,0 +1,8 @@
+-- per-guild KenLM scorers. language/scorer_path are set once one is built,
+-- pending_language while a uploaded corpus waits for a bot owner to approve it
+CREATE TABLE IF NOT EXISTS custom_scorers (
+    guild_id BIGINT PRIMARY KEY,
+    language TEXT,
+    scorer_path TEXT,
+    pending_language TEXT
+);
@@ -0,0 +1,3 @@
+-- per-guild confidence threshold, NULL means the bot's default
+ALTER TABLE guilds ADD COLUMN IF NOT EXISTS min_confidence REAL;
+ALTER TABLE guilds ADD COLUMN IF NOT EXISTS mark_low_confidence BOOLEAN NOT NULL DEFAULT false;
@@ -0,0 +1,7 @@
+-- per-guild redaction rules, by name, and extra words to mask
+ALTER TABLE guilds ADD COLUMN IF NOT EXISTS redact TEXT[] NOT NULL DEFAULT '{}';
+CREATE TABLE IF NOT EXISTS redacted_words (
+    guild_id BIGINT NOT NULL,
+    word TEXT NOT NULL,
+    PRIMARY KEY (guild_id, word)
+);
@@ -0,0 +1,6 @@
+-- users who agreed to be recorded in a guild's recording sessions
+CREATE TABLE IF NOT EXISTS recording_consent (
+    guild_id BIGINT NOT NULL,
+    user_id BIGINT NOT NULL,
+    PRIMARY KEY (guild_id, user_id)
+);
@@ -0,0 +1,2 @@
+-- format to export session transcripts as when everyone leaves, or NULL to not export them
+ALTER TABLE guilds ADD COLUMN IF NOT EXISTS transcript_format TEXT;
@@ -0,0 +1,12 @@
+-- alternatives speakers picked over the transcript that was sent, as training data for models
+CREATE TABLE IF NOT EXISTS transcript_corrections (
+    id BIGSERIAL PRIMARY KEY,
+    guild_id BIGINT NOT NULL,
+    user_id BIGINT NOT NULL,
+    language TEXT NOT NULL,
+    original TEXT NOT NULL,
+    corrected TEXT NOT NULL,
+    -- where the picked candidate ranked: 1 is the model's second guess
+    candidate_rank SMALLINT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
@@ -0,0 +1,21 @@
+-- guilds where nobody is transcribed until they agree to it
+ALTER TABLE guilds ADD COLUMN IF NOT EXISTS strict_consent BOOLEAN NOT NULL DEFAULT false;
+
+-- guild_id 0 holds a user's settings for every guild, other rows override it for one guild
+CREATE TABLE IF NOT EXISTS user_settings (
+    user_id BIGINT NOT NULL,
+    guild_id BIGINT NOT NULL,
+    opted_out BOOLEAN NOT NULL DEFAULT false,
+    PRIMARY KEY (user_id, guild_id)
+);
+
+-- every time someone agreed to something, and which version of the privacy policy it was
+CREATE TABLE IF NOT EXISTS consent_log (
+    id BIGSERIAL PRIMARY KEY,
+    user_id BIGINT NOT NULL,
+    guild_id BIGINT NOT NULL,
+    -- `setup` for the terms agreed to while setting the bot up, `transcription` for being transcribed
+    kind TEXT NOT NULL,
+    policy_version TEXT NOT NULL,
+    accepted_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
@@ -0,0 +1,7 @@
+-- roles whose members are the only ones transcribed (allowed) or never are (not allowed)
+CREATE TABLE IF NOT EXISTS speaker_roles (
+    guild_id BIGINT NOT NULL,
+    role_id BIGINT NOT NULL,
+    allowed BOOLEAN NOT NULL,
+    PRIMARY KEY (guild_id, role_id)
+);
@@ -0,0 +1,6 @@
+-- roles whose members go ahead of everyone else in the queue for a transcription seat
+CREATE TABLE IF NOT EXISTS priority_roles (
+    guild_id BIGINT NOT NULL,
+    role_id BIGINT NOT NULL,
+    PRIMARY KEY (guild_id, role_id)
+);
@@ -2,13 +2,27 @@
  * Licensed under the EUPL: see LICENSE.md.
  */
 
+use crate::live::LiveTranscript;
+use crate::transcript_message::TranscriptMessage;
+use crate::{
+    add_pending_correction, forget_consent, guild_settings, is_recording, load_consent,
+    log_utterance, may_transcribe, record_packet, schedule_stt, send_consent_prompt,
+    send_recording, send_session_transcript, should_prompt, stop_recording,
+    take_session_transcript, with_guild_policy, with_guild_settings, Admission, RegisteredSeats,
+    SttJobError,
+};
 use ahash::RandomState;
-use scripty_audio_utils::{load_model, run_stt, Model};
+use scripty_audio_utils::{
+    loaded_model, AudioFormat, ModelKey, SttBackend, VadEvent, VoiceActivityDetector,
+};
+use scripty_config::BotConfig;
 use scripty_metrics::METRICS;
 use serenity::builder::ExecuteWebhook;
-use serenity::model::prelude::Embed;
-use serenity::{async_trait, model::webhook::Webhook, prelude::Context};
-use smallvec::SmallVec;
+use serenity::{
+    async_trait,
+    model::{id::GuildId, webhook::Webhook},
+    prelude::Context,
+};
 use songbird::{
     model::{
         id::UserId,
@@ -17,15 +31,39 @@ use songbird::{
     Event, EventContext, EventHandler as VoiceEventHandler,
 };
 use std::{
-    collections::{HashMap, HashSet},
+    collections::HashMap,
     sync::{Arc, RwLock},
+    time::{Duration, Instant},
 };
 use tokio::task;
 use tracing::{debug, error, trace, warn};
 
+/// Get the model for the language (and custom scorer) a guild picked.
+///
+/// This is looked up for every utterance, that way reloaded models and language changes get
+/// picked up between them. `bind` loads the model beforehand: if it somehow isn't loaded,
+/// this falls back to the language without the custom scorer, then the default language.
+fn guild_model(guild_id: GuildId) -> Option<Arc<dyn SttBackend>> {
+    let default_language = BotConfig::get()?.default_language();
+    let key = with_guild_settings(guild_id, |s| s.model_key(default_language));
+    let model = loaded_model(&key)
+        .or_else(|| loaded_model(&ModelKey::language(key.language)))
+        .or_else(|| loaded_model(&ModelKey::language(default_language)));
+    if model.is_none() {
+        error!("No model is loaded for {}!", guild_id);
+    }
+    model
+}
+
+/// How many candidates to ask the model for. The ones after the first are offered to the
+/// speaker to pick from, in case the first was wrong.
+pub const MAX_CANDIDATES: u32 = 5;
+
 macro_rules! do_check {
-    ($active_users:expr, $user_id:expr) => {
-        if !$active_users.read().ok()?.contains($user_id) {
+    ($self:expr, $user_id:expr) => {
+        if !$self.seats.read().ok()?.is_seated($user_id.0)
+            || !may_transcribe($self.guild_id, $user_id.0)
+        {
             return None;
         }
     };
@@ -35,26 +73,25 @@ macro_rules! do_check {
 pub struct Receiver {
     ssrc_map: Arc<RwLock<HashMap<u32, UserId, RandomState>>>,
     audio_buffer: Arc<RwLock<HashMap<u32, Vec<i16>, RandomState>>>,
-    active_users: Arc<RwLock<HashSet<UserId, RandomState>>>,
-    next_users: Arc<RwLock<SmallVec<[UserId; 10]>>>, // 10 should be fine
+    live: Arc<RwLock<HashMap<u32, LiveTranscript, RandomState>>>,
+    vad: Arc<RwLock<HashMap<u32, VoiceActivityDetector, RandomState>>>,
+    seats: Arc<RegisteredSeats>,
     webhook: Arc<Webhook>,
     context: Arc<Context>,
+    guild_id: GuildId,
     premium_level: u8,
-    max_users: u16, // seriously if it hits 65535 users in a VC wtf
-    ds_model: Arc<RwLock<Model>>,
+    // in raw samples: 48KHz stereo
+    max_utterance_len: usize,
+    // whether the model supports streaming: if so, audio goes into `live` instead of `audio_buffer`
+    streaming: bool,
     verbose: bool,
 }
 
-// next two both forcibly implement the required types for async code
-// we need these because `deepspeech::Model` doesn't impl Send + Sync
-// because it's got a FFI type inside of it
-unsafe impl Send for Receiver {}
-unsafe impl Sync for Receiver {}
-
 impl Receiver {
     pub async fn new(
         webhook: Webhook,
         context: Arc<Context>,
+        guild_id: GuildId,
         premium_level: u8,
         verbose: bool,
     ) -> Self {
@@ -64,34 +101,303 @@ impl Receiver {
             2 => 50,
             3 => 100,
             4 => 250,
-            _ => u16::MAX,
+            _ => usize::MAX,
         };
-
-        if let Some(id) = webhook.guild_id {
-            trace!("constructing new receiver for {}", id);
-        } else {
-            trace!("constructing new receiver for unknown guild");
+        // in seconds: once someone talks for longer than this without pausing,
+        // what they've said so far gets transcribed and a new utterance starts
+        let max_utterance_secs = match premium_level {
+            0 => 30,
+            1 => 45,
+            2 => 60,
+            3 => 90,
+            4 => 120,
+            _ => 300,
         };
+        let max_utterance_len = max_utterance_secs * AudioFormat::DISCORD.samples_per_sec();
+
+        trace!("constructing new receiver for {}", guild_id);
 
         let ssrc_map = Arc::new(RwLock::new(HashMap::with_hasher(ahash::RandomState::new())));
         let audio_buffer = Arc::new(RwLock::new(HashMap::with_hasher(ahash::RandomState::new())));
         let webhook = Arc::new(webhook);
-        let active_users = Arc::new(RwLock::new(HashSet::with_hasher(ahash::RandomState::new())));
-        let next_users = Arc::new(RwLock::new(SmallVec::new()));
-        let ds_model = Arc::new(RwLock::new(load_model()));
+        let seats = Arc::new(RegisteredSeats::new(guild_id, max_users));
+        let live = Arc::new(RwLock::new(HashMap::with_hasher(ahash::RandomState::new())));
+        let vad = Arc::new(RwLock::new(HashMap::with_hasher(ahash::RandomState::new())));
+        let streaming = guild_model(guild_id).map_or(false, |m| m.supports_streaming());
         Self {
             ssrc_map,
             audio_buffer,
-            active_users,
-            next_users,
+            live,
+            vad,
+            seats,
             webhook,
             context,
+            guild_id,
             premium_level,
-            max_users,
-            ds_model,
+            max_utterance_len,
+            streaming,
             verbose,
         }
     }
+
+    /// The user behind `ssrc` stopped talking, or paused for long enough:
+    /// transcribe everything they said since the last time this was called.
+    async fn end_utterance(&self, ssrc: u32) -> Option<()> {
+        let uid = {
+            let ssrc_map = self.ssrc_map.read().ok()?;
+            *(ssrc_map.get(&ssrc)?)
+        };
+        if !may_transcribe(self.guild_id, uid.0) {
+            // they opted out while talking: what they said so far can't be kept for later
+            if let Some(a) = self.audio_buffer.write().ok()?.get_mut(&ssrc) {
+                a.clear();
+            }
+            let live = self.live.write().ok()?.remove(&ssrc);
+            if let Some(live) = live {
+                live.abort();
+            }
+            return None;
+        }
+        do_check!(self, &uid);
+
+        if self.streaming {
+            if let Some(live) = self.live.write().ok()?.remove(&ssrc) {
+                live.finish();
+            }
+            return None;
+        }
+
+        let audio = match self.audio_buffer.write().ok()?.get_mut(&ssrc) {
+            Some(a) => {
+                let res = a.clone();
+                a.clear();
+                res
+            }
+            None => return None,
+        };
+
+        let u = self.context.cache.user(uid.0).await?;
+        if u.bot {
+            return None;
+        }
+        let duration_ms = (audio.len() * 1000 / AudioFormat::DISCORD.samples_per_sec()) as u32;
+        // when the buffer started, which is what the transcript's timings are counted from,
+        // silence trimmed off the front and all
+        let start = Instant::now() - Duration::from_millis(duration_ms as u64);
+
+        // these might seem weird, but these are required that way we can spawn the
+        // task below and move these variables into it without getting lifetime
+        // errors
+        let webhook = Arc::clone(&self.webhook);
+        let context = Arc::clone(&self.context);
+        let model = guild_model(self.guild_id)?;
+        let settings = guild_settings(self.guild_id);
+        let verbose = self.verbose;
+        let guild_id = self.guild_id;
+        let premium_level = self.premium_level;
+
+        task::spawn(async move {
+            match schedule_stt(
+                guild_id,
+                premium_level,
+                audio,
+                AudioFormat::DISCORD,
+                Arc::clone(&model),
+                settings.clone(),
+                MAX_CANDIDATES,
+            )
+            .await
+            {
+                Ok(r) => {
+                    if let Some(msg) =
+                        TranscriptMessage::new(&r, &*model, &settings, verbose, &u, duration_ms)
+                    {
+                        log_utterance(guild_id, webhook.channel_id, start, msg.utterance().clone());
+                        let correction =
+                            msg.pending_correction(guild_id, Arc::clone(&webhook), &settings);
+                        let mut webhook_execute = ExecuteWebhook::default();
+                        msg.execute(&mut webhook_execute);
+                        webhook_execute.avatar_url(u.face()).username(u.name);
+
+                        // the message's ID is only needed to correct it later
+                        let res = webhook
+                            .execute(&context, correction.is_some(), |m| {
+                                *m = webhook_execute;
+                                m
+                            })
+                            .await;
+                        if let (Ok(Some(m)), Some(correction)) = (res, correction) {
+                            add_pending_correction(m.id, correction);
+                        }
+                    }
+                }
+                Err(SttJobError::Stt(e)) => {
+                    error!("Failed to run speech-to-text! {}", e);
+                }
+                Err(e) => {
+                    warn!("Dropped a utterance in {}: {}", guild_id, e);
+                }
+            };
+        });
+
+        Some(())
+    }
+
+    /// Everyone left: stop listing who's seated, stop recording, and send the transcript of
+    /// the whole session if the guild wants one.
+    fn end_session(&self) {
+        self.seats.unregister();
+        self.finish_recording();
+        let format = match with_guild_policy(self.guild_id, |p| p.transcript_format) {
+            Some(f) => f,
+            None => return,
+        };
+        let transcript = match take_session_transcript(self.guild_id) {
+            Some(t) => t,
+            None => return,
+        };
+        let context = Arc::clone(&self.context);
+        task::spawn(async move {
+            if let Err(e) =
+                send_session_transcript(&*context, transcript.channel_id, &transcript, format).await
+            {
+                warn!("Failed to send session transcript: {}", e);
+            }
+        });
+    }
+
+    /// Stop the guild's recording, if it has one, and send it where it was started from.
+    fn finish_recording(&self) {
+        let context = Arc::clone(&self.context);
+        let guild_id = self.guild_id;
+        task::spawn(async move {
+            if let Some(recording) = stop_recording(guild_id).await {
+                send_recording(&*context, guild_id, recording).await;
+            }
+        });
+    }
+
+    /// Give `user_id` a seat if the guild's speaker roles let them be transcribed, or queue
+    /// them for one if every seat's taken, letting them know. Bots never get one.
+    async fn admit(&self, user_id: UserId) -> Option<()> {
+        if self
+            .context
+            .cache
+            .user(user_id.0)
+            .await
+            .map_or(false, |u| u.bot)
+        {
+            return None;
+        }
+        let roles = with_guild_policy(self.guild_id, |p| p.speaker_roles.clone());
+        let member_roles: Vec<u64> = if roles.is_empty() {
+            Vec::new()
+        } else {
+            // this checks the cache first
+            match self.guild_id.member(&*self.context, user_id.0).await {
+                Ok(m) => m.roles.iter().map(|r| r.0).collect(),
+                Err(e) => {
+                    warn!("Couldn't fetch a member to check their roles: {}", e);
+                    return None;
+                }
+            }
+        };
+        if !roles.permits(&member_roles) {
+            return None;
+        }
+
+        self.seats.register();
+        let admission = self
+            .seats
+            .write()
+            .ok()?
+            .admit(user_id.0, roles.has_priority(&member_roles));
+        if let Admission::Queued(place) = admission {
+            self.notify(format!(
+                "<@{}>, every seat is taken, so I'm not transcribing you yet. You're number {} \
+                in line for the next one.",
+                user_id.0, place
+            ));
+        }
+        Some(())
+    }
+
+    /// Send a notice to the channel transcripts go to.
+    fn notify(&self, content: String) {
+        let webhook = Arc::clone(&self.webhook);
+        let context = Arc::clone(&self.context);
+        task::spawn(async move {
+            if let Err(e) = webhook
+                .execute(&*context, false, |m| m.content(content))
+                .await
+            {
+                warn!("Failed to send a notice: {}", e);
+            }
+        });
+    }
+
+    /// Ask `user_id` to agree to be transcribed, if the guild wants that and they weren't
+    /// asked since they joined.
+    async fn prompt_consent(&self, user_id: u64) {
+        if !should_prompt(self.guild_id, user_id) {
+            return;
+        }
+        if self
+            .context
+            .cache
+            .user(user_id)
+            .await
+            .map_or(false, |u| u.bot)
+        {
+            return;
+        }
+        let webhook = Arc::clone(&self.webhook);
+        let context = Arc::clone(&self.context);
+        task::spawn(async move {
+            send_consent_prompt(&*context, &webhook, user_id).await;
+        });
+    }
+
+    /// Push a packet of audio into the live transcript for `ssrc`,
+    /// starting one if they just started talking.
+    ///
+    /// Returns the total length of the live transcript, in samples.
+    async fn push_live(&self, ssrc: u32, audio: &[i16]) -> Option<usize> {
+        let uid = *self.ssrc_map.read().ok()?.get(&ssrc)?;
+        if !may_transcribe(self.guild_id, uid.0) {
+            return None;
+        }
+        if let Some(live) = self.live.write().ok()?.get_mut(&ssrc) {
+            live.push(audio.to_vec());
+            return Some(live.len());
+        }
+
+        let u = self.context.cache.user(uid.0).await?;
+        if u.bot {
+            return None;
+        }
+
+        let mut live = match LiveTranscript::start(
+            guild_model(self.guild_id)?,
+            self.guild_id,
+            self.premium_level,
+            Arc::clone(&self.webhook),
+            Arc::clone(&self.context),
+            u,
+            self.verbose,
+            &guild_settings(self.guild_id),
+        ) {
+            Ok(l) => l,
+            Err(e) => {
+                error!("Failed to start live transcript! {}", e);
+                return None;
+            }
+        };
+        live.push(audio.to_vec());
+        let len = live.len();
+        self.live.write().ok()?.insert(ssrc, live);
+        Some(len)
+    }
 }
 
 #[async_trait]
@@ -108,7 +414,13 @@ impl VoiceEventHandler for Receiver {
                 user_id: Some(user_id),
                 ..
             }) => {
-                do_check!(&self.active_users, user_id);
+                load_consent(self.guild_id, user_id.0).await;
+                self.prompt_consent(user_id.0).await;
+                // people who were here before the bot joined never connected as far as it knows
+                if !self.seats.read().ok()?.is_seated(user_id.0) {
+                    self.admit(*user_id).await;
+                }
+                do_check!(self, user_id);
 
                 {
                     let mut ssrc_map = self.ssrc_map.write().ok()?;
@@ -121,119 +433,10 @@ impl VoiceEventHandler for Receiver {
             }
             EventContext::SpeakingUpdate { ssrc, speaking } => {
                 if !*speaking {
-                    let uid = {
-                        let ssrc_map = self.ssrc_map.read().ok()?;
-                        *(ssrc_map.get(ssrc)?)
-                    };
-                    do_check!(&self.active_users, &uid);
-
-                    let audio = match self.audio_buffer.write().ok()?.get_mut(ssrc) {
-                        Some(a) => {
-                            let res = a.clone();
-                            a.clear();
-                            res
-                        }
-                        None => return None,
-                    };
-
-                    let u = self.context.cache.user(uid.0).await?;
-                    if u.bot {
-                        return None;
+                    if let Some(vad) = self.vad.write().ok()?.get_mut(ssrc) {
+                        vad.reset();
                     }
-
-                    // these might seem weird, but these are required that way we can spawn the
-                    // task below and move these variables into it without getting lifetime
-                    // errors
-                    let webhook = Arc::clone(&self.webhook);
-                    let context = Arc::clone(&self.context);
-                    let model = Arc::clone(&self.ds_model);
-                    let verbose = self.verbose;
-
-                    task::spawn(async move {
-                        match run_stt(audio, model).await {
-                            Ok(r) => {
-                                let mut has_result = false;
-                                let mut webhook_execute = ExecuteWebhook::default();
-                                if let Some(t) = r.transcripts().first() {
-                                    let mut transcription = String::new();
-                                    let mut err = false;
-                                    let mut audio_length = 0;
-                                    let mut audio_start = 0;
-                                    let tokens = t.tokens();
-                                    let total_tokens = tokens.len() - 1;
-                                    for (i, token) in tokens.iter().enumerate() {
-                                        match token.text() {
-                                            Ok(text) => transcription.push_str(text),
-                                            Err(e) => {
-                                                warn!(
-                                                    "transcription contained invalid UTF-8? {}",
-                                                    e
-                                                );
-                                                if verbose {
-                                                    err = true;
-                                                } else {
-                                                    return;
-                                                }
-                                            }
-                                        };
-                                        if verbose {
-                                            if i == 0 {
-                                                audio_start = token.timestep() * 20
-                                            } else if i == total_tokens {
-                                                audio_length = token.timestep() * 20
-                                            }
-                                        }
-                                    }
-
-                                    if verbose {
-                                        let embed = Embed::fake(|x| {
-                                            x.description(format!(
-                                                "**Transcription**\n{}\n\n\
-                                                    **Confidence %**\n{}\n\n\
-                                                    **Start Offset (ms)**\n{}\n\n\
-                                                    **Length (ms)**\n{}\n\n\
-                                                    **Total Possiblities**\n{}",
-                                                transcription,
-                                                t.confidence() * 100.0,
-                                                audio_start,
-                                                audio_length,
-                                                r.transcripts().len()
-                                            ));
-                                            if err {
-                                                x.field(
-                                                    "Note",
-                                                    "UTF-8 decoding error was detected",
-                                                    false,
-                                                );
-                                            }
-                                            x
-                                        });
-                                        webhook_execute.embeds(vec![embed]);
-                                    } else {
-                                        webhook_execute.content(transcription);
-                                    }
-                                    has_result = true;
-                                } else if verbose {
-                                    webhook_execute.content("No transcriptions found");
-                                    has_result = true;
-                                }
-
-                                if has_result {
-                                    webhook_execute.avatar_url(u.face()).username(u.name);
-
-                                    let _ = webhook
-                                        .execute(&context, false, |m| {
-                                            *m = webhook_execute;
-                                            m
-                                        })
-                                        .await;
-                                }
-                            }
-                            Err(e) => {
-                                error!("Failed to run speech-to-text! {}", e);
-                            }
-                        };
-                    });
+                    self.end_utterance(*ssrc).await;
                 }
             }
             EventContext::VoicePacket {
@@ -246,15 +449,64 @@ impl VoiceEventHandler for Receiver {
                 // so we're trying to do stuff with as little overhead as possible
                 let st = std::time::Instant::now();
 
-                do_check!(
-                    &self.active_users,
-                    self.ssrc_map.read().ok()?.get(&packet.ssrc)?
-                );
+                let uid = *self.ssrc_map.read().ok()?.get(&packet.ssrc)?;
+                do_check!(self, &uid);
+
+                // recordings keep the original Opus packets, so they don't need decoding again
+                if is_recording(self.guild_id) {
+                    let opus =
+                        &packet.payload[*payload_offset..packet.payload.len() - *payload_end_pad];
+                    let out_of_time = record_packet(
+                        self.guild_id,
+                        uid.0,
+                        packet.ssrc,
+                        u32::from(packet.timestamp),
+                        opus,
+                    );
+                    if out_of_time {
+                        self.finish_recording();
+                    }
+                }
 
                 if let Some(audio) = audio {
-                    if let Some(b) = self.audio_buffer.write().ok()?.get_mut(&packet.ssrc) {
-                        b.extend(audio)
+                    let (vad_event, in_utterance) = {
+                        let settings = with_guild_settings(self.guild_id, |s| s.vad);
+                        let mut vad = self.vad.write().ok()?;
+                        let detector = vad.entry(packet.ssrc).or_insert_with(|| {
+                            VoiceActivityDetector::new(
+                                settings,
+                                AudioFormat::DISCORD.sample_rate,
+                                AudioFormat::DISCORD.layout.channels(),
+                            )
+                        });
+                        detector.set_settings(settings);
+                        let event = detector.push(audio);
+                        (event, detector.heard_speech())
+                    };
+
+                    // don't bother keeping silence from before they started talking
+                    let utterance_len = if vad_event != VadEvent::Silence || in_utterance {
+                        if self.streaming {
+                            self.push_live(packet.ssrc, audio).await
+                        } else {
+                            // people who agreed to be transcribed after they started talking
+                            // don't have a buffer yet
+                            let mut audio_buffer = self.audio_buffer.write().ok()?;
+                            let b = audio_buffer.entry(packet.ssrc).or_default();
+                            b.extend(audio);
+                            Some(b.len())
+                        }
+                    } else {
+                        None
                     };
+
+                    // they're still "speaking" according to Discord, but actually paused,
+                    // or they've been talking for so long it needs to be cut off somewhere
+                    if vad_event == VadEvent::Pause
+                        || utterance_len.map_or(false, |l| l >= self.max_utterance_len)
+                    {
+                        self.end_utterance(packet.ssrc).await;
+                    }
                 }
 
                 let et = std::time::Instant::now();
@@ -281,44 +533,53 @@ impl VoiceEventHandler for Receiver {
                     let mut ssrc_map = self.ssrc_map.write().ok()?;
                     ssrc_map.insert(*audio_ssrc, *user_id);
                 }
-                {
-                    let mut active_users = self.active_users.write().ok()?;
-                    if active_users.len() >= self.max_users as usize {
-                        let mut next_users = self.next_users.write().ok()?;
-                        next_users.push(*user_id);
-                    } else {
-                        active_users.insert(*user_id);
-                    };
-                }
+                load_consent(self.guild_id, user_id.0).await;
+                self.prompt_consent(user_id.0).await;
+                self.admit(*user_id).await;
             }
             EventContext::ClientDisconnect(ClientDisconnect { user_id, .. }) => {
-                let ssrc_map = self.ssrc_map.read().ok()?;
-                if let Some(u) =
-                    ssrc_map.iter().find_map(
-                        |(ssrc, uid)| {
-                            if uid == user_id {
-                                Some(*ssrc)
-                            } else {
-                                None
-                            }
-                        },
-                    )
-                {
+                // this has to be checked before their consent is forgotten
+                let allowed = may_transcribe(self.guild_id, user_id.0);
+                forget_consent(self.guild_id, user_id.0);
+                let promoted = self.seats.write().ok()?.leave(user_id.0);
+                if let Some(u) = promoted {
+                    self.notify(format!(
+                        "<@{}>, a seat opened up, so I'm transcribing you now.",
+                        u
+                    ));
+                }
+                // this has to be let go of before the map is written to below
+                let ssrc = self.ssrc_map.read().ok()?.iter().find_map(|(ssrc, uid)| {
+                    if uid == user_id {
+                        Some(*ssrc)
+                    } else {
+                        None
+                    }
+                });
+                if let Some(u) = ssrc {
                     {
                         let mut audio_buffer = self.audio_buffer.write().ok()?;
                         audio_buffer.remove(&u);
                     }
+                    {
+                        let live = self.live.write().ok()?.remove(&u);
+                        match live {
+                            Some(live) if allowed => live.finish(),
+                            Some(live) => live.abort(),
+                            None => {}
+                        }
+                    }
+                    {
+                        let mut vad = self.vad.write().ok()?;
+                        vad.remove(&u);
+                    }
                     {
                         let mut ssrc_map = self.ssrc_map.write().ok()?;
                         ssrc_map.remove(&u);
                     }
-                    {
-                        let mut active_users = self.active_users.write().ok()?;
-                        active_users.remove(user_id);
-                        let mut next_users = self.next_users.write().ok()?;
-                        if let Some(user) = next_users.pop() {
-                            active_users.insert(user);
-                        };
+                    // the last one left, so the session's over
+                    if self.ssrc_map.read().ok()?.is_empty() {
+                        self.end_session();
                     }
                 };
             }
@@ -1,4 +1,6 @@
-use super::audio_handler::Receiver;
+use super::{audio_handler::Receiver, load_guild_settings};
+use scripty_audio_utils::{load_model, ModelKey};
+use scripty_config::BotConfig;
 use scripty_db::PgPoolKey;
 use serenity::{
     http::CacheHttp,
@@ -8,7 +10,7 @@ use serenity::{
 use songbird::CoreEvent;
 use sqlx::query;
 use std::{convert::TryInto, sync::Arc};
-use tracing::debug;
+use tracing::{debug, warn};
 
 pub async fn bind(
     ctx: &Context,
@@ -60,6 +62,27 @@ pub async fn bind(
         Err(e) => return Err(format!("DB returned a error: {:?}", e)),
     };
 
+    debug!(guild_id = guild_id.0, "loading guild settings");
+    let settings = load_guild_settings(guild_id).await?;
+
+    debug!(guild_id = guild_id.0, "loading model");
+    let key = settings.model_key(
+        BotConfig::get()
+            .ok_or_else(|| "Config isn't loaded.".to_string())?
+            .default_language(),
+    );
+    if let Err(e) = load_model(&key).await {
+        if key.scorer.is_none() {
+            return Err(format!("Couldn't load the model for {}: {}", key, e));
+        }
+        // a broken custom scorer shouldn't keep the bot out of voice chats
+        warn!(guild_id = guild_id.0, "couldn't load custom scorer: {}", e);
+        let key = ModelKey::language(key.language);
+        if let Err(e) = load_model(&key).await {
+            return Err(format!("Couldn't load the model for {}: {}", key, e));
+        }
+    }
+
     debug!(
         transcription_id = transcription_channel.0,
         "fetching webhook token/id"
@@ -120,8 +143,14 @@ pub async fn bind(
             let ctx1 = Arc::new(ctx.clone());
 
             debug!(guild_id = guild_id.0, "creating receiver");
-            let receiver =
-                Receiver::new(webhook, ctx1, premium_level, guild_id == 675390855716274216).await;
+            let receiver = Receiver::new(
+                webhook,
+                ctx1,
+                guild_id,
+                premium_level,
+                guild_id == 675390855716274216,
+            )
+            .await;
 
             debug!(guild_id = guild_id.0, "muting self");
             let _ = handler.mute(true).await;
@@ -0,0 +1,331 @@
+use crate::with_guild_policy;
+use ahash::RandomState;
+use dashmap::DashMap;
+use scripty_db::PG_POOL;
+use serenity::{
+    builder::CreateComponents,
+    client::Context,
+    http::Http,
+    model::{
+        id::{GuildId, UserId},
+        interactions::{InteractionApplicationCommandCallbackDataFlags, InteractionResponseType},
+        prelude::message_component::{ButtonStyle, MessageComponentInteraction},
+        webhook::Webhook,
+    },
+};
+use sqlx::query;
+use std::{
+    lazy::SyncOnceCell as OnceCell,
+    time::{Duration, Instant},
+};
+use tracing::{error, info, warn};
+
+/// Where the privacy policy people agree to can be read.
+pub const PRIVACY_POLICY_URL: &str = "https://scripty.imaskeleton.me/privacy_policy";
+/// Version of the privacy policy at `PRIVACY_POLICY_URL`, logged with every agreement.
+/// Change this whenever the policy does: guilds with strict consent then ask everyone again.
+pub const PRIVACY_POLICY_VERSION: &str = "2021-08-11";
+
+/// How long to wait before trying to load someone's consent again after it failed to load.
+const CONSENT_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// Custom ID of the button on consent prompts that agrees to be transcribed.
+pub const CONSENT_AGREE_ID: &str = "transcription_consent_agree";
+/// Custom ID of the button on consent prompts that opts out of being transcribed.
+pub const CONSENT_DECLINE_ID: &str = "transcription_consent_decline";
+
+/// What someone agreed to, as logged in `consent_log`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConsentKind {
+    /// The privacy policy, while setting the bot up in a guild.
+    Setup,
+    /// Being transcribed in a guild.
+    Transcription,
+}
+
+impl ConsentKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            ConsentKind::Setup => "setup",
+            ConsentKind::Transcription => "transcription",
+        }
+    }
+}
+
+/// Whether someone in voice chat may be transcribed, as far as they're concerned.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct UserConsent {
+    /// Whether they opted out everywhere.
+    pub global_opt_out: bool,
+    /// Whether they opted out (`Some(true)`) or back in (`Some(false)`) in this guild,
+    /// which overrides `global_opt_out`.
+    pub guild_opt_out: Option<bool>,
+    /// Whether they agreed to the current privacy policy in this guild.
+    pub agreed: bool,
+    /// Whether they've been asked to agree since they joined voice chat.
+    pub prompted: bool,
+}
+
+impl UserConsent {
+    pub fn opted_out(&self) -> bool {
+        self.guild_opt_out.unwrap_or(self.global_opt_out)
+    }
+
+    /// Whether they may be transcribed in a guild that does (`strict`) or doesn't ask everyone
+    /// to agree first.
+    pub fn permits(&self, strict: bool) -> bool {
+        !self.opted_out() && (self.agreed || !strict)
+    }
+}
+
+/// Consent of everyone in a voice chat the bot is in, by guild and user. It's loaded when they
+/// join or start talking, and forgotten when they leave.
+static CONSENT: OnceCell<DashMap<(GuildId, u64), UserConsent, RandomState>> = OnceCell::new();
+
+fn consent_map() -> &'static DashMap<(GuildId, u64), UserConsent, RandomState> {
+    CONSENT.get_or_init(|| DashMap::with_hasher(RandomState::new()))
+}
+
+/// When to try loading the consent of people whose consent failed to load again, so the DB
+/// isn't queried every time they start talking while it's down.
+static CONSENT_RETRY: OnceCell<DashMap<(GuildId, u64), Instant, RandomState>> = OnceCell::new();
+
+fn retry_map() -> &'static DashMap<(GuildId, u64), Instant, RandomState> {
+    CONSENT_RETRY.get_or_init(|| DashMap::with_hasher(RandomState::new()))
+}
+
+/// Load whether `user_id` opted out of, or agreed to, being transcribed in the guild, unless
+/// it already is. If it fails to load, they aren't transcribed until it's tried again after
+/// `CONSENT_RETRY_DELAY`.
+pub(crate) async fn load_consent(guild_id: GuildId, user_id: u64) {
+    if consent_map().contains_key(&(guild_id, user_id)) {
+        return;
+    }
+    if retry_map()
+        .get(&(guild_id, user_id))
+        .map_or(false, |r| Instant::now() < *r)
+    {
+        return;
+    }
+    match fetch_consent(guild_id, user_id).await {
+        Ok(consent) => {
+            retry_map().remove(&(guild_id, user_id));
+            consent_map().entry((guild_id, user_id)).or_insert(consent);
+        }
+        // without this there's no telling if they opted out, so they aren't transcribed
+        Err(e) => {
+            error!(
+                guild_id = guild_id.0,
+                user_id,
+                "Couldn't load consent, not transcribing them for {}s: {}",
+                CONSENT_RETRY_DELAY.as_secs(),
+                e
+            );
+            retry_map().insert((guild_id, user_id), Instant::now() + CONSENT_RETRY_DELAY);
+        }
+    }
+}
+
+async fn fetch_consent(guild_id: GuildId, user_id: u64) -> Result<UserConsent, sqlx::Error> {
+    let pool = unsafe { PG_POOL.get().unwrap_unchecked() };
+
+    let mut consent = UserConsent::default();
+    for r in query!(
+        "SELECT guild_id, opted_out FROM user_settings \
+        WHERE user_id = $1 AND guild_id IN (0, $2)",
+        user_id as i64,
+        i64::from(guild_id)
+    )
+    .fetch_all(pool)
+    .await?
+    {
+        if r.guild_id == 0 {
+            consent.global_opt_out = r.opted_out;
+        } else {
+            consent.guild_opt_out = Some(r.opted_out);
+        }
+    }
+    consent.agreed = query!(
+        "SELECT id FROM consent_log WHERE user_id = $1 AND guild_id = $2 AND kind = $3 \
+        AND policy_version = $4 LIMIT 1",
+        user_id as i64,
+        i64::from(guild_id),
+        ConsentKind::Transcription.name(),
+        PRIVACY_POLICY_VERSION
+    )
+    .fetch_optional(pool)
+    .await?
+    .is_some();
+    Ok(consent)
+}
+
+/// Forget `user_id`'s consent in the guild, after they left voice chat.
+pub(crate) fn forget_consent(guild_id: GuildId, user_id: u64) {
+    consent_map().remove(&(guild_id, user_id));
+    retry_map().remove(&(guild_id, user_id));
+}
+
+/// Whether `user_id` may be transcribed in the guild: they haven't opted out, and agreed to it
+/// if the guild has strict consent. Nobody may be until their consent is loaded.
+pub(crate) fn may_transcribe(guild_id: GuildId, user_id: u64) -> bool {
+    let strict = with_guild_policy(guild_id, |p| p.strict_consent);
+    consent_map()
+        .get(&(guild_id, user_id))
+        .map_or(false, |c| c.permits(strict))
+}
+
+/// Whether `user_id` should be asked to agree to be transcribed in the guild.
+/// This is only true once each time they join.
+pub(crate) fn should_prompt(guild_id: GuildId, user_id: u64) -> bool {
+    if !with_guild_policy(guild_id, |p| p.strict_consent) {
+        return false;
+    }
+    match consent_map().get_mut(&(guild_id, user_id)) {
+        Some(mut c) if !c.opted_out() && !c.agreed && !c.prompted => {
+            c.prompted = true;
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Update whether `user_id` opted out in the guild, or everywhere if `guild_id` is `None`.
+/// This doesn't touch the DB, that's up to the caller.
+pub fn set_opted_out(guild_id: Option<GuildId>, user_id: UserId, opted_out: bool) {
+    for mut c in consent_map().iter_mut() {
+        let (guild, user) = *c.key();
+        if user != user_id.0 {
+            continue;
+        }
+        match guild_id {
+            Some(g) if g == guild => c.guild_opt_out = Some(opted_out),
+            Some(_) => {}
+            None => c.global_opt_out = opted_out,
+        }
+    }
+}
+
+/// Log that `user_id` agreed to the current privacy policy in the guild.
+pub async fn log_consent(
+    guild_id: GuildId,
+    user_id: UserId,
+    kind: ConsentKind,
+) -> Result<(), sqlx::Error> {
+    let pool = unsafe { PG_POOL.get().unwrap_unchecked() };
+    query!(
+        "INSERT INTO consent_log (user_id, guild_id, kind, policy_version) VALUES ($1, $2, $3, $4)",
+        i64::from(user_id),
+        i64::from(guild_id),
+        kind.name(),
+        PRIVACY_POLICY_VERSION
+    )
+    .execute(pool)
+    .await?;
+    info!(guild_id = guild_id.0, "consent logged: {}", kind.name());
+
+    if kind == ConsentKind::Transcription {
+        if let Some(mut c) = consent_map().get_mut(&(guild_id, user_id.0)) {
+            c.agreed = true;
+        }
+    }
+    Ok(())
+}
+
+/// Add the buttons to agree to being transcribed, or opt out of it.
+pub(crate) fn consent_buttons(c: &mut CreateComponents) -> &mut CreateComponents {
+    c.create_action_row(|r| {
+        r.create_button(|b| {
+            b.style(ButtonStyle::Primary)
+                .custom_id(CONSENT_AGREE_ID)
+                .label("I Agree")
+        })
+        .create_button(|b| {
+            b.style(ButtonStyle::Secondary)
+                .custom_id(CONSENT_DECLINE_ID)
+                .label("Don't transcribe me")
+        })
+    })
+}
+
+/// Ask `user_id` to agree to being transcribed, in the channel transcripts are sent to.
+pub(crate) async fn send_consent_prompt(http: impl AsRef<Http>, webhook: &Webhook, user_id: u64) {
+    if let Err(e) = webhook
+        .execute(http, false, |m| {
+            m.content(format!(
+                "<@{}>, this server asks everyone to agree before I transcribe what they say in \
+                voice chat. By agreeing, you accept the privacy policy: {}",
+                user_id, PRIVACY_POLICY_URL
+            ))
+            .components(consent_buttons)
+        })
+        .await
+    {
+        warn!("Failed to send consent prompt: {}", e);
+    }
+}
+
+/// Handle someone clicking a button on a consent prompt. Whoever clicks it answers for
+/// themselves, not for who the prompt was for.
+///
+/// Returns `false` without responding if the interaction isn't one of those buttons.
+pub async fn handle_consent_interaction(
+    ctx: &Context,
+    interaction: &MessageComponentInteraction,
+) -> bool {
+    let agree = match interaction.data.custom_id.as_str() {
+        CONSENT_AGREE_ID => true,
+        CONSENT_DECLINE_ID => false,
+        _ => return false,
+    };
+    let guild_id = match interaction.guild_id {
+        Some(g) => g,
+        None => return true,
+    };
+    let user_id = interaction.user.id;
+
+    // agreeing also undoes opting out here, otherwise they still wouldn't be transcribed
+    let pool = unsafe { PG_POOL.get().unwrap_unchecked() };
+    let content = match query!(
+        "INSERT INTO user_settings (user_id, guild_id, opted_out) VALUES ($1, $2, $3) \
+        ON CONFLICT (user_id, guild_id) DO UPDATE SET opted_out = $3",
+        i64::from(user_id),
+        i64::from(guild_id),
+        !agree
+    )
+    .execute(pool)
+    .await
+    {
+        Ok(_) => {
+            set_opted_out(Some(guild_id), user_id, !agree);
+            if !agree {
+                "Got it, I won't transcribe you here. Use `transcription on` if you change \
+                your mind."
+            } else if let Err(e) = log_consent(guild_id, user_id, ConsentKind::Transcription).await
+            {
+                error!("Couldn't log consent: {}", e);
+                "I couldn't write that down, try again in a bit."
+            } else {
+                "Thanks! I'll transcribe you here from now on. Use `transcription off` if \
+                you change your mind."
+            }
+        }
+        Err(e) => {
+            error!("Couldn't update user settings: {}", e);
+            "I couldn't write that down, try again in a bit."
+        }
+    };
+
+    if let Err(e) = interaction
+        .create_interaction_response(ctx, |r| {
+            r.kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|d| {
+                    d.flags(InteractionApplicationCommandCallbackDataFlags::EPHEMERAL)
+                        .content(content)
+                })
+        })
+        .await
+    {
+        warn!("Failed to respond to a consent prompt: {}", e);
+    }
+    true
+}
@@ -0,0 +1,234 @@
+use crate::correct_utterance;
+use ahash::RandomState;
+use dashmap::DashMap;
+use scripty_db::PG_POOL;
+use serenity::{
+    builder::CreateComponents,
+    client::Context,
+    model::{
+        id::{GuildId, MessageId, UserId},
+        interactions::{InteractionApplicationCommandCallbackDataFlags, InteractionResponseType},
+        prelude::message_component::{
+            ButtonStyle, InteractionMessage, MessageComponentInteraction,
+        },
+        webhook::Webhook,
+    },
+};
+use sqlx::query;
+use std::{
+    lazy::SyncOnceCell as OnceCell,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tracing::{error, info, warn};
+
+/// Custom ID of the button on transcripts that have alternatives.
+pub const ALTERNATIVES_BUTTON_ID: &str = "transcript_alternatives";
+/// Custom ID of the menu a speaker picks a alternative from, followed by `:` and the ID of
+/// the transcript's message.
+pub const ALTERNATIVE_PICKER_ID: &str = "transcript_alternative_picker";
+
+/// Transcripts can be corrected for this long after they're sent.
+const CORRECTION_WINDOW: Duration = Duration::from_secs(15 * 60);
+// Discord's limit for the label of a menu option
+const MAX_LABEL_CHARS: usize = 100;
+
+/// Transcripts with alternatives the speaker can still pick from, by message.
+static PENDING: OnceCell<DashMap<MessageId, PendingCorrection, RandomState>> = OnceCell::new();
+
+fn pending() -> &'static DashMap<MessageId, PendingCorrection, RandomState> {
+    PENDING.get_or_init(|| DashMap::with_hasher(RandomState::new()))
+}
+
+/// A transcript that was sent with alternatives, waiting for the speaker to pick one.
+pub(crate) struct PendingCorrection {
+    pub(crate) guild_id: GuildId,
+    pub(crate) speaker_id: UserId,
+    pub(crate) language: String,
+    /// The text that was sent, followed by every alternative, all redacted.
+    pub(crate) candidates: Vec<String>,
+    /// The webhook the transcript was sent with, which is the only thing that can edit it.
+    pub(crate) webhook: Arc<Webhook>,
+    pub(crate) sent: Instant,
+}
+
+/// Remember the alternatives of a transcript that was just sent as `message_id`.
+pub(crate) fn add_pending_correction(message_id: MessageId, correction: PendingCorrection) {
+    let pending = pending();
+    pending.retain(|_, c| c.sent.elapsed() < CORRECTION_WINDOW);
+    pending.insert(message_id, correction);
+}
+
+/// Add the button that shows a transcript's alternatives.
+pub(crate) fn alternatives_button(c: &mut CreateComponents) -> &mut CreateComponents {
+    c.create_action_row(|r| {
+        r.create_button(|b| {
+            b.style(ButtonStyle::Secondary)
+                .custom_id(ALTERNATIVES_BUTTON_ID)
+                .label("Not what you said?")
+        })
+    })
+}
+
+/// Handle someone clicking a transcript's alternatives button, or picking one from the menu.
+///
+/// Returns `false` without responding if the interaction isn't either of those.
+pub async fn handle_correction_interaction(
+    ctx: &Context,
+    interaction: &MessageComponentInteraction,
+) -> bool {
+    let custom_id = interaction.data.custom_id.as_str();
+    if custom_id == ALTERNATIVES_BUTTON_ID {
+        show_alternatives(ctx, interaction).await;
+    } else if let Some(message_id) = custom_id
+        .strip_prefix(ALTERNATIVE_PICKER_ID)
+        .and_then(|id| id.strip_prefix(':'))
+    {
+        match message_id.parse() {
+            Ok(id) => pick_alternative(ctx, interaction, MessageId(id)).await,
+            Err(_) => reply(ctx, interaction, "Discord sent me a broken menu.").await,
+        }
+    } else {
+        return false;
+    }
+    true
+}
+
+/// Answer the speaker privately with a menu of the transcript's alternatives.
+async fn show_alternatives(ctx: &Context, interaction: &MessageComponentInteraction) {
+    let message_id = match &interaction.message {
+        InteractionMessage::Regular(m) => m.id,
+        _ => return,
+    };
+    // the map can't stay locked while waiting on Discord
+    let options: Result<Vec<(usize, String)>, &str> = match pending().get(&message_id) {
+        Some(c) if c.sent.elapsed() < CORRECTION_WINDOW => {
+            if c.speaker_id == interaction.user.id {
+                Ok(c.candidates
+                    .iter()
+                    .enumerate()
+                    .skip(1)
+                    .map(|(i, text)| (i, text.chars().take(MAX_LABEL_CHARS).collect()))
+                    .collect())
+            } else {
+                Err("Only the person who said this can correct it.")
+            }
+        }
+        _ => Err("It's too late to correct this one."),
+    };
+    let options = match options {
+        Ok(o) => o,
+        Err(e) => return reply(ctx, interaction, e).await,
+    };
+
+    if let Err(e) = interaction
+        .create_interaction_response(ctx, |r| {
+            r.kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|d| {
+                    d.flags(InteractionApplicationCommandCallbackDataFlags::EPHEMERAL)
+                        .content("Which of these did you say?")
+                        .components(|c| {
+                            c.create_action_row(|r| {
+                                r.create_select_menu(|m| {
+                                    m.custom_id(format!("{}:{}", ALTERNATIVE_PICKER_ID, message_id))
+                                        .placeholder("Pick what you said")
+                                        .options(|o| {
+                                            for (i, label) in options {
+                                                o.create_option(|opt| {
+                                                    opt.label(label).value(i.to_string())
+                                                });
+                                            }
+                                            o
+                                        })
+                                })
+                            })
+                        })
+                })
+        })
+        .await
+    {
+        warn!("Failed to show transcript alternatives: {}", e);
+    }
+}
+
+/// Swap the transcript for the alternative the speaker picked, and log it as a correction.
+async fn pick_alternative(
+    ctx: &Context,
+    interaction: &MessageComponentInteraction,
+    message_id: MessageId,
+) {
+    let index: usize = match interaction.data.values.get(0).and_then(|v| v.parse().ok()) {
+        Some(i) if i > 0 => i,
+        _ => return reply(ctx, interaction, "Discord sent me a broken menu.").await,
+    };
+    // only the first pick counts, so the same correction can't be logged twice
+    let correction = match pending().remove_if(&message_id, |_, c| {
+        c.speaker_id == interaction.user.id && index < c.candidates.len()
+    }) {
+        Some((_, c)) if c.sent.elapsed() < CORRECTION_WINDOW => c,
+        _ => return reply(ctx, interaction, "It's too late to correct this one.").await,
+    };
+    let original = &correction.candidates[0];
+    let corrected = &correction.candidates[index];
+
+    if let Err(e) = correction
+        .webhook
+        .edit_message(ctx, message_id, |m| m.content(corrected).components(|c| c))
+        .await
+    {
+        warn!("Failed to edit corrected transcript: {}", e);
+        return reply(ctx, interaction, "I couldn't edit the transcript, sorry!").await;
+    }
+    if let Err(e) = interaction
+        .create_interaction_response(ctx, |r| {
+            r.kind(InteractionResponseType::UpdateMessage)
+                .interaction_response_data(|d| d.content("Fixed it, thanks!").components(|c| c))
+        })
+        .await
+    {
+        warn!("Failed to respond to a correction: {}", e);
+    }
+
+    correct_utterance(
+        correction.guild_id,
+        correction.speaker_id.0,
+        original,
+        corrected.clone(),
+    );
+    info!(
+        guild_id = correction.guild_id.0,
+        "transcript corrected to candidate {}", index
+    );
+    let pool = unsafe { PG_POOL.get().unwrap_unchecked() };
+    if let Err(e) = query!(
+        "INSERT INTO transcript_corrections (guild_id, user_id, language, original, corrected, \
+        candidate_rank) VALUES ($1, $2, $3, $4, $5, $6)",
+        i64::from(correction.guild_id),
+        i64::from(correction.speaker_id),
+        correction.language,
+        original,
+        corrected,
+        index as i16
+    )
+    .execute(pool)
+    .await
+    {
+        error!("Couldn't log a transcript correction: {}", e);
+    }
+}
+
+/// Answer a interaction with a message only its user can see.
+async fn reply(ctx: &Context, interaction: &MessageComponentInteraction, content: &str) {
+    if let Err(e) = interaction
+        .create_interaction_response(ctx, |r| {
+            r.kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|d| {
+                    d.flags(InteractionApplicationCommandCallbackDataFlags::EPHEMERAL)
+                        .content(content)
+                })
+        })
+        .await
+    {
+        warn!("Failed to respond to a interaction: {}", e);
+    }
+}
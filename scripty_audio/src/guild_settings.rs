@@ -0,0 +1,249 @@
+use ahash::RandomState;
+use dashmap::DashMap;
+use scripty_audio_utils::{
+    CustomScorer, ExportFormat, HotWord, RedactionRules, SttSettings, VadSettings,
+};
+use scripty_db::PG_POOL;
+use serenity::model::id::GuildId;
+use sqlx::query;
+use std::lazy::SyncOnceCell as OnceCell;
+
+/// Per-guild speech-to-text settings, cached that way they can be read for every packet
+/// without hitting the DB. Commands that change settings update this as well as the DB, and
+/// since receivers read from here for every utterance, changes apply without rejoining.
+static GUILD_SETTINGS: OnceCell<DashMap<GuildId, SttSettings, RandomState>> = OnceCell::new();
+
+fn settings_map() -> &'static DashMap<GuildId, SttSettings, RandomState> {
+    GUILD_SETTINGS.get_or_init(|| DashMap::with_hasher(RandomState::new()))
+}
+
+/// Get a copy of the cached settings for a guild, or the defaults if they were never loaded.
+pub fn guild_settings(guild_id: GuildId) -> SttSettings {
+    settings_map()
+        .get(&guild_id)
+        .map_or_else(SttSettings::default, |s| s.value().clone())
+}
+
+/// Read part of a guild's cached settings without copying all of them.
+pub fn with_guild_settings<F, T>(guild_id: GuildId, f: F) -> T
+where
+    F: FnOnce(&SttSettings) -> T,
+{
+    match settings_map().get(&guild_id) {
+        Some(s) => f(s.value()),
+        None => f(&SttSettings::default()),
+    }
+}
+
+/// Change the cached settings for a guild. This doesn't touch the DB, that's up to the caller.
+///
+/// Guilds whose settings aren't cached are left alone: they're read from the DB when they
+/// are loaded, changes and all.
+pub fn update_guild_settings<F>(guild_id: GuildId, f: F)
+where
+    F: FnOnce(&mut SttSettings),
+{
+    if let Some(mut s) = settings_map().get_mut(&guild_id) {
+        f(s.value_mut())
+    }
+}
+
+/// Who in a guild gets transcribed, and what's done with their transcripts.
+///
+/// Unlike `SttSettings`, none of this changes how audio is decoded.
+#[derive(Clone, Debug, Default)]
+pub struct GuildPolicy {
+    /// What to export the session's transcript as when everyone leaves voice chat.
+    /// `None` means it isn't exported automatically.
+    pub transcript_format: Option<ExportFormat>,
+    /// Whether speakers have to agree to be transcribed before anything they say is.
+    pub strict_consent: bool,
+    /// Which roles' members are transcribed.
+    pub speaker_roles: SpeakerRoles,
+}
+
+/// Roles that decide who gets transcribed, by role ID.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SpeakerRoles {
+    /// If there are any, only members with at least one of these are transcribed.
+    pub allowed: Vec<u64>,
+    /// Members with any of these aren't transcribed, even if they have a allowed one.
+    pub denied: Vec<u64>,
+    /// Members with any of these go ahead of everyone else in the queue for a seat.
+    pub priority: Vec<u64>,
+}
+
+impl SpeakerRoles {
+    /// Whether there are no roles, and so members' roles don't matter.
+    pub fn is_empty(&self) -> bool {
+        self.allowed.is_empty() && self.denied.is_empty() && self.priority.is_empty()
+    }
+
+    /// Whether a member with `roles` may be transcribed.
+    pub fn permits(&self, roles: &[u64]) -> bool {
+        if roles.iter().any(|r| self.denied.contains(r)) {
+            return false;
+        }
+        self.allowed.is_empty() || roles.iter().any(|r| self.allowed.contains(r))
+    }
+
+    /// Whether a member with `roles` goes ahead in the queue for a seat.
+    pub fn has_priority(&self, roles: &[u64]) -> bool {
+        roles.iter().any(|r| self.priority.contains(r))
+    }
+}
+
+/// Per-guild policies, cached and kept up to date the same way as `GUILD_SETTINGS`.
+static GUILD_POLICIES: OnceCell<DashMap<GuildId, GuildPolicy, RandomState>> = OnceCell::new();
+
+fn policy_map() -> &'static DashMap<GuildId, GuildPolicy, RandomState> {
+    GUILD_POLICIES.get_or_init(|| DashMap::with_hasher(RandomState::new()))
+}
+
+/// Get a copy of the cached policy for a guild, or the defaults if it was never loaded.
+pub fn guild_policy(guild_id: GuildId) -> GuildPolicy {
+    policy_map()
+        .get(&guild_id)
+        .map_or_else(GuildPolicy::default, |p| p.value().clone())
+}
+
+/// Read part of a guild's cached policy without copying all of it.
+pub fn with_guild_policy<F, T>(guild_id: GuildId, f: F) -> T
+where
+    F: FnOnce(&GuildPolicy) -> T,
+{
+    match policy_map().get(&guild_id) {
+        Some(p) => f(p.value()),
+        None => f(&GuildPolicy::default()),
+    }
+}
+
+/// Change the cached policy for a guild, the same way as `update_guild_settings`.
+pub fn update_guild_policy<F>(guild_id: GuildId, f: F)
+where
+    F: FnOnce(&mut GuildPolicy),
+{
+    if let Some(mut p) = policy_map().get_mut(&guild_id) {
+        f(p.value_mut())
+    }
+}
+
+/// Load a guild's settings and policy from the DB into the cache, and return the settings.
+pub async fn load_guild_settings(guild_id: GuildId) -> Result<SttSettings, String> {
+    let pool = unsafe { PG_POOL.get().unwrap_unchecked() };
+
+    let row = match query!(
+        "SELECT vad_threshold, vad_silence_ms, denoise, language, min_confidence, mark_low_confidence, \
+        redact, transcript_format, strict_consent FROM guilds WHERE guild_id = $1",
+        i64::from(guild_id)
+    )
+    .fetch_optional(pool)
+    .await
+    {
+        Ok(Some(r)) => r,
+        Ok(None) => return Err("Guild not found in DB.".to_string()),
+        Err(e) => return Err(format!("DB returned a error: {:?}", e)),
+    };
+
+    let hot_words = match query!(
+        "SELECT word, boost FROM hot_words WHERE guild_id = $1",
+        i64::from(guild_id)
+    )
+    .fetch_all(pool)
+    .await
+    {
+        Ok(rows) => rows
+            .into_iter()
+            .map(|r| HotWord {
+                word: r.word,
+                boost: r.boost,
+            })
+            .collect(),
+        Err(e) => return Err(format!("DB returned a error: {:?}", e)),
+    };
+
+    let scorer = match query!(
+        "SELECT language, scorer_path FROM custom_scorers \
+        WHERE guild_id = $1 AND scorer_path IS NOT NULL",
+        i64::from(guild_id)
+    )
+    .fetch_optional(pool)
+    .await
+    {
+        Ok(r) => r.and_then(|r| {
+            Some(CustomScorer {
+                language: r.language?,
+                path: r.scorer_path?.into(),
+            })
+        }),
+        Err(e) => return Err(format!("DB returned a error: {:?}", e)),
+    };
+
+    let redacted_words = match query!(
+        "SELECT word FROM redacted_words WHERE guild_id = $1",
+        i64::from(guild_id)
+    )
+    .fetch_all(pool)
+    .await
+    {
+        Ok(rows) => rows.into_iter().map(|r| r.word).collect(),
+        Err(e) => return Err(format!("DB returned a error: {:?}", e)),
+    };
+
+    let mut speaker_roles = match query!(
+        "SELECT role_id, allowed FROM speaker_roles WHERE guild_id = $1",
+        i64::from(guild_id)
+    )
+    .fetch_all(pool)
+    .await
+    {
+        Ok(rows) => {
+            let mut roles = SpeakerRoles::default();
+            for r in rows {
+                if r.allowed {
+                    roles.allowed.push(r.role_id as u64);
+                } else {
+                    roles.denied.push(r.role_id as u64);
+                }
+            }
+            roles
+        }
+        Err(e) => return Err(format!("DB returned a error: {:?}", e)),
+    };
+
+    match query!(
+        "SELECT role_id FROM priority_roles WHERE guild_id = $1",
+        i64::from(guild_id)
+    )
+    .fetch_all(pool)
+    .await
+    {
+        Ok(rows) => speaker_roles.priority = rows.into_iter().map(|r| r.role_id as u64).collect(),
+        Err(e) => return Err(format!("DB returned a error: {:?}", e)),
+    };
+
+    let settings = SttSettings {
+        vad: VadSettings {
+            threshold_db: row.vad_threshold,
+            min_silence_ms: row.vad_silence_ms as u32,
+            ..Default::default()
+        },
+        denoise: row.denoise,
+        language: row.language,
+        hot_words,
+        scorer,
+        min_confidence: row.min_confidence,
+        mark_low_confidence: row.mark_low_confidence,
+        redaction: RedactionRules::from_enabled(&row.redact, redacted_words),
+    };
+    let policy = GuildPolicy {
+        transcript_format: row
+            .transcript_format
+            .and_then(|f| f.parse::<ExportFormat>().ok()),
+        strict_consent: row.strict_consent,
+        speaker_roles,
+    };
+    settings_map().insert(guild_id, settings.clone());
+    policy_map().insert(guild_id, policy);
+    Ok(settings)
+}
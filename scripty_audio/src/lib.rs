@@ -5,7 +5,24 @@
 mod audio_handler;
 mod auto_join;
 mod bind;
+mod consent;
+mod corrections;
+mod guild_settings;
+mod live;
+mod recording;
+mod scheduler;
+mod seats;
+mod session_transcript;
+mod transcript_message;
 
 pub use audio_handler::*;
 pub use auto_join::*;
 pub use bind::*;
+pub use consent::*;
+pub use corrections::*;
+pub use guild_settings::*;
+pub use live::*;
+pub use recording::*;
+pub use scheduler::*;
+pub use seats::*;
+pub use session_transcript::*;
@@ -0,0 +1,329 @@
+use crate::transcript_message::TranscriptMessage;
+use crate::{add_pending_correction, log_utterance, schedule_decode, SttJobError, MAX_CANDIDATES};
+use scripty_audio_utils::{
+    downmix_to_mono, redact, AudioFormat, Denoiser, Resampler, SttBackend, SttError, SttSettings,
+    SttStream, Transcript,
+};
+use serenity::{
+    builder::ExecuteWebhook,
+    model::{
+        id::{GuildId, MessageId},
+        user::User,
+        webhook::Webhook,
+    },
+    prelude::Context,
+};
+use std::{
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+use tokio::{
+    sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
+    task,
+};
+use tracing::{error, warn};
+
+/// How much new audio to collect before decoding a intermediate result and editing the message.
+/// This is 2 seconds of 48KHz stereo audio, which keeps us well under the webhook rate limits.
+const PARTIAL_INTERVAL: usize = AudioFormat::DISCORD.samples_per_sec() * 2;
+
+enum LiveCommand {
+    Audio(Vec<i16>),
+    Finish,
+    Abort,
+}
+
+/// A live transcript of one speaker's current utterance.
+///
+/// Audio is fed into a streaming decode as it arrives, in jobs run by the same scheduler as
+/// every other decode. A message is posted as soon as there's
+/// a intermediate result, edited as more comes in, and finalized once the speaker stops.
+/// Dropping this without calling `finish` also finalizes the transcript, only `abort` doesn't.
+pub struct LiveTranscript {
+    tx: UnboundedSender<LiveCommand>,
+    // total samples pushed so far
+    len: usize,
+}
+
+impl LiveTranscript {
+    pub fn start(
+        model: Arc<dyn SttBackend>,
+        guild_id: GuildId,
+        premium_level: u8,
+        webhook: Arc<Webhook>,
+        context: Arc<Context>,
+        user: User,
+        verbose: bool,
+        settings: &SttSettings,
+    ) -> Result<Self, SttError> {
+        let stream = model.create_stream(&settings.hot_words, MAX_CANDIDATES)?;
+        let (tx, rx) = unbounded_channel();
+        let decoder = SharedDecoder {
+            unfed: Mutex::new(Vec::with_capacity(PARTIAL_INTERVAL)),
+            decoder: Mutex::new(LiveDecoder {
+                stream: Some(stream),
+                denoiser: if settings.denoise {
+                    Some(Denoiser::new())
+                } else {
+                    None
+                },
+                resampler: Resampler::new(AudioFormat::DISCORD.sample_rate, model.sample_rate()),
+            }),
+        };
+        task::spawn(run_live_transcript(
+            rx,
+            Arc::new(decoder),
+            model,
+            guild_id,
+            premium_level,
+            webhook,
+            context,
+            user,
+            verbose,
+            settings.clone(),
+        ));
+        Ok(Self { tx, len: 0 })
+    }
+
+    /// Add raw 48KHz stereo audio from Discord to this transcript.
+    pub fn push(&mut self, audio: Vec<i16>) {
+        self.len += audio.len();
+        // if the worker is gone it already logged why
+        let _ = self.tx.send(LiveCommand::Audio(audio));
+    }
+
+    /// How many samples of raw audio have been pushed into this transcript.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The speaker stopped talking: decode the rest of the audio and finalize the message.
+    pub fn finish(self) {
+        let _ = self.tx.send(LiveCommand::Finish);
+    }
+
+    /// The speaker may not be transcribed after all: throw away the audio without decoding it,
+    /// and delete the message if one was posted already.
+    pub fn abort(self) {
+        let _ = self.tx.send(LiveCommand::Abort);
+    }
+}
+
+/// A streaming decode, along with the denoiser and resampler feeding it.
+///
+/// Both keep their state between chunks of audio,
+/// so there are no seams in the audio where one chunk ends and the next begins.
+struct LiveDecoder {
+    // taken once the stream is finished
+    stream: Option<Box<dyn SttStream>>,
+    // Discord's audio is already at the denoiser's sample rate, so this goes first
+    denoiser: Option<Denoiser>,
+    resampler: Resampler,
+}
+
+impl LiveDecoder {
+    fn feed(&mut self, audio: Vec<i16>, flush: bool) -> Result<(), SttError> {
+        let mut mono = downmix_to_mono(&audio, AudioFormat::DISCORD);
+        if let Some(denoiser) = &mut self.denoiser {
+            let mut denoised = Vec::with_capacity(mono.len());
+            denoiser.process(&mono, &mut denoised);
+            if flush {
+                denoiser.flush(&mut denoised);
+            }
+            mono = denoised;
+        }
+
+        let mut resampled = Vec::new();
+        self.resampler.process(&mono, &mut resampled);
+        if flush {
+            self.resampler.flush(&mut resampled);
+        }
+        self.stream()?.feed_audio(&resampled)
+    }
+
+    fn stream(&mut self) -> Result<&mut Box<dyn SttStream>, SttError> {
+        self.stream
+            .as_mut()
+            .ok_or_else(|| SttError::Decode("the stream was already finished".to_string()))
+    }
+}
+
+/// A live transcript's decoder, shared with the scheduler's jobs that run it.
+///
+/// Audio waits in `unfed` until the next job feeds it into the stream, so none of it is lost
+/// when a job gets dropped from the queue.
+struct SharedDecoder {
+    unfed: Mutex<Vec<i16>>,
+    // only ever locked by jobs, on blocking threads
+    decoder: Mutex<LiveDecoder>,
+}
+
+impl SharedDecoder {
+    fn push(&self, audio: Vec<i16>) {
+        self.unfed
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .extend(audio);
+    }
+
+    fn take_unfed(&self) -> Vec<i16> {
+        std::mem::take(&mut *self.unfed.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    /// Feed the audio pushed so far into the stream and decode a intermediate result.
+    fn intermediate(&self) -> Result<Transcript, SttError> {
+        let mut decoder = self.decoder.lock().unwrap_or_else(|e| e.into_inner());
+        decoder.feed(self.take_unfed(), false)?;
+        decoder.stream()?.intermediate()
+    }
+
+    /// Feed the last of the audio into the stream and finish it.
+    fn finish(&self) -> Result<Transcript, SttError> {
+        let mut decoder = self.decoder.lock().unwrap_or_else(|e| e.into_inner());
+        decoder.feed(self.take_unfed(), true)?;
+        decoder.stream()?;
+        decoder.stream.take().expect("checked above").finish()
+    }
+}
+
+async fn run_live_transcript(
+    mut rx: UnboundedReceiver<LiveCommand>,
+    decoder: Arc<SharedDecoder>,
+    // the model the stream runs on, which is needed to judge how confident it is in the result
+    model: Arc<dyn SttBackend>,
+    guild_id: GuildId,
+    premium_level: u8,
+    webhook: Arc<Webhook>,
+    context: Arc<Context>,
+    user: User,
+    verbose: bool,
+    settings: SttSettings,
+) {
+    // the first audio is pushed right as this starts
+    let start = Instant::now();
+    // samples pushed since the last intermediate result
+    let mut pending = 0;
+    let mut message_id: Option<MessageId> = None;
+    let mut last_text = String::new();
+
+    while let Some(command) = rx.recv().await {
+        let audio = match command {
+            LiveCommand::Audio(audio) => audio,
+            LiveCommand::Finish => break,
+            LiveCommand::Abort => {
+                if let Some(id) = message_id {
+                    let _ = webhook.delete_message(&context, id).await;
+                }
+                return;
+            }
+        };
+        pending += audio.len();
+        decoder.push(audio);
+        if pending < PARTIAL_INTERVAL {
+            continue;
+        }
+        pending = 0;
+
+        let d = Arc::clone(&decoder);
+        let res = schedule_decode(guild_id, premium_level, move || d.intermediate()).await;
+        let text = match res {
+            Ok(r) => match r.best() {
+                Some(t) if !t.text.is_empty() => redact(&t.text, &settings.redaction),
+                _ => continue,
+            },
+            Err(e) => {
+                warn!("Failed to decode intermediate transcript: {}", e);
+                continue;
+            }
+        };
+        if text == last_text {
+            continue;
+        }
+        // the ellipsis marks the message as still in progress
+        let content = format!("{} …", text);
+        last_text = text;
+
+        match message_id {
+            Some(id) => {
+                let _ = webhook
+                    .edit_message(&context, id, |m| m.content(content))
+                    .await;
+            }
+            None => {
+                match webhook
+                    .execute(&context, true, |m| {
+                        m.content(content)
+                            .avatar_url(user.face())
+                            .username(&user.name)
+                    })
+                    .await
+                {
+                    Ok(Some(m)) => message_id = Some(m.id),
+                    Ok(None) => {}
+                    Err(e) => warn!("Failed to send intermediate transcript: {}", e),
+                }
+            }
+        }
+    }
+
+    let end = Instant::now();
+    let r = match schedule_decode(guild_id, premium_level, move || decoder.finish()).await {
+        Ok(r) => r,
+        Err(SttJobError::Stt(e)) => {
+            error!("Failed to run speech-to-text! {}", e);
+            return;
+        }
+        Err(e) => {
+            warn!("Dropped a utterance in {}: {}", guild_id, e);
+            return;
+        }
+    };
+
+    let duration_ms = end.duration_since(start).as_millis() as u32;
+    let msg = TranscriptMessage::new(&r, &*model, &settings, verbose, &user, duration_ms);
+    let mut correction = None;
+    if let Some(msg) = &msg {
+        log_utterance(guild_id, webhook.channel_id, start, msg.utterance().clone());
+        correction = msg.pending_correction(guild_id, Arc::clone(&webhook), &settings);
+    }
+    match (msg, message_id) {
+        (Some(msg), Some(id)) => {
+            if webhook
+                .edit_message(&context, id, |m| {
+                    msg.edit(m);
+                    m
+                })
+                .await
+                .is_ok()
+            {
+                if let Some(correction) = correction {
+                    add_pending_correction(id, correction);
+                }
+            }
+        }
+        (Some(msg), None) => {
+            let mut webhook_execute = ExecuteWebhook::default();
+            msg.execute(&mut webhook_execute);
+            webhook_execute.avatar_url(user.face()).username(user.name);
+            // the message's ID is only needed to correct it later
+            let res = webhook
+                .execute(&context, correction.is_some(), |m| {
+                    *m = webhook_execute;
+                    m
+                })
+                .await;
+            if let (Ok(Some(m)), Some(correction)) = (res, correction) {
+                add_pending_correction(m.id, correction);
+            }
+        }
+        (None, Some(id)) => {
+            // the intermediate results turned out to be nothing
+            let _ = webhook.delete_message(&context, id).await;
+        }
+        (None, None) => {}
+    }
+}
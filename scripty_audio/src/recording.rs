@@ -0,0 +1,406 @@
+use ahash::RandomState;
+use dashmap::DashMap;
+use rand::Rng;
+use scripty_audio_utils::{opus_packet_samples, OggOpusWriter, OPUS_SAMPLE_RATE};
+use scripty_config::BotConfig;
+use scripty_db::PG_POOL;
+use serenity::{
+    builder::CreateEmbed,
+    http::Http,
+    model::id::{ChannelId, GuildId, UserId},
+    prelude::Mentionable,
+};
+use sqlx::query;
+use std::{
+    collections::{hash_map::Entry, HashMap, HashSet},
+    fs::{self, File},
+    io::{self, BufWriter},
+    lazy::SyncOnceCell as OnceCell,
+    path::PathBuf,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use tracing::{error, info, warn};
+
+/// Recording sessions in progress, by guild. Receivers look their guild up in here for every
+/// packet, so sessions can be started and stopped without rejoining.
+static RECORDINGS: OnceCell<DashMap<GuildId, Mutex<RecordingSession>, RandomState>> =
+    OnceCell::new();
+
+/// Sessions stop recording after this long, in case someone forgets to stop one.
+pub const MAX_RECORDING_SECS: u64 = 6 * 60 * 60;
+/// Files bigger than this can't be uploaded to Discord, in bytes.
+const UPLOAD_LIMIT: u64 = 8 * 1024 * 1024;
+/// Discord only allows this many attachments per message.
+const MAX_ATTACHMENTS: usize = 10;
+// Discord always sends stereo Opus, even for mono mics
+const CHANNELS: u8 = 2;
+
+fn recordings() -> &'static DashMap<GuildId, Mutex<RecordingSession>, RandomState> {
+    RECORDINGS.get_or_init(|| DashMap::with_hasher(RandomState::new()))
+}
+
+/// A recording in progress, with a separate file for each speaker who agreed to be recorded.
+struct RecordingSession {
+    id: String,
+    dir: PathBuf,
+    started: Instant,
+    channel_id: ChannelId,
+    consenting: HashSet<u64, RandomState>,
+    tracks: HashMap<u64, Track, RandomState>,
+}
+
+/// One speaker's file in a recording session.
+struct Track {
+    writer: OggOpusWriter<BufWriter<File>>,
+    path: PathBuf,
+    /// Where the end of the last packet should be, in samples since the session started.
+    position: u64,
+    /// The SSRC of the last packet, and the RTP timestamp the next one has if there's no gap.
+    next_timestamp: Option<(u32, u32)>,
+    last_packet: Instant,
+}
+
+/// A recording session that was stopped, and the files it left behind.
+pub struct FinishedRecording {
+    /// Random ID of the session, which is also the name of its directory.
+    pub id: String,
+    pub dir: PathBuf,
+    /// The channel the session was started from.
+    pub channel_id: ChannelId,
+    pub length: Duration,
+    /// Every speaker who said something, and their file.
+    pub files: Vec<(UserId, PathBuf)>,
+}
+
+/// Whether a guild has a recording session running.
+pub fn is_recording(guild_id: GuildId) -> bool {
+    recordings().contains_key(&guild_id)
+}
+
+/// How long a guild's recording session has been running, if it has one.
+pub fn recording_duration(guild_id: GuildId) -> Option<Duration> {
+    let session = recordings().get(&guild_id)?;
+    let session = session.lock().unwrap_or_else(|e| e.into_inner());
+    Some(session.started.elapsed())
+}
+
+/// Start a recording session for a guild, with `channel_id` being where it was started from.
+///
+/// Only users who opted in with `record optin` are recorded.
+pub async fn start_recording(guild_id: GuildId, channel_id: ChannelId) -> Result<(), String> {
+    if is_recording(guild_id) {
+        return Err("This server is already being recorded.".to_string());
+    }
+    let pool = unsafe { PG_POOL.get().unwrap_unchecked() };
+    let consenting = match query!(
+        "SELECT user_id FROM recording_consent WHERE guild_id = $1",
+        i64::from(guild_id)
+    )
+    .fetch_all(pool)
+    .await
+    {
+        Ok(rows) => rows.into_iter().map(|r| r.user_id as u64).collect(),
+        Err(e) => return Err(format!("DB returned a error: {:?}", e)),
+    };
+
+    let config = BotConfig::get().ok_or_else(|| "Config isn't loaded.".to_string())?;
+    // the ID ends up in download links, so it has to be impossible to guess
+    let id = format!("{:032x}", rand::thread_rng().gen::<u128>());
+    let dir = PathBuf::from(config.recording_dir())
+        .join(guild_id.0.to_string())
+        .join(&id);
+    if let Err(e) = tokio::fs::create_dir_all(&dir).await {
+        return Err(format!("Couldn't create the recording directory: {}", e));
+    }
+
+    info!(guild_id = guild_id.0, "started recording {}", id);
+    recordings().insert(
+        guild_id,
+        Mutex::new(RecordingSession {
+            id,
+            dir,
+            started: Instant::now(),
+            channel_id,
+            consenting,
+            tracks: HashMap::with_hasher(RandomState::new()),
+        }),
+    );
+    Ok(())
+}
+
+/// Stop a guild's recording session and finish all its files, if it has one running.
+pub async fn stop_recording(guild_id: GuildId) -> Option<FinishedRecording> {
+    let (_, session) = recordings().remove(&guild_id)?;
+    let session = session.into_inner().unwrap_or_else(|e| e.into_inner());
+    let length = session.started.elapsed();
+
+    // finishing the files flushes what's left of them to disk
+    let tracks = session.tracks;
+    let files = tokio::task::spawn_blocking(move || {
+        let mut files = Vec::with_capacity(tracks.len());
+        for (user_id, track) in tracks {
+            match track.writer.finish() {
+                Ok(_) => files.push((UserId(user_id), track.path)),
+                Err(e) => error!(
+                    guild_id = guild_id.0,
+                    "couldn't finish recording {}: {}",
+                    track.path.display(),
+                    e
+                ),
+            }
+        }
+        files
+    })
+    .await
+    .expect("Failed to spawn blocking!");
+    info!(guild_id = guild_id.0, "stopped recording {}", session.id);
+    Some(FinishedRecording {
+        id: session.id,
+        dir: session.dir,
+        channel_id: session.channel_id,
+        length,
+        files,
+    })
+}
+
+/// Upload every file of a finished recording that fits to the channel it was started from,
+/// and link or list the rest.
+pub async fn send_recording(
+    http: impl AsRef<Http>,
+    guild_id: GuildId,
+    recording: FinishedRecording,
+) {
+    let http = http.as_ref();
+    let mut uploads: Vec<Vec<PathBuf>> = Vec::new();
+    let mut upload_size = 0;
+    let mut too_big = Vec::new();
+    let mut speakers = Vec::with_capacity(recording.files.len());
+    for (user_id, path) in recording.files {
+        let name = path
+            .file_name()
+            .map_or_else(String::new, |n| n.to_string_lossy().into_owned());
+        speakers.push(format!("{}: `{}`", user_id.mention(), name));
+
+        let size = match tokio::fs::metadata(&path).await {
+            Ok(m) => m.len(),
+            Err(e) => {
+                error!("Couldn't read {}: {}", path.display(), e);
+                continue;
+            }
+        };
+        if size > UPLOAD_LIMIT {
+            too_big.push(name);
+            continue;
+        }
+        match uploads.last_mut() {
+            Some(batch) if batch.len() < MAX_ATTACHMENTS && upload_size + size <= UPLOAD_LIMIT => {
+                upload_size += size;
+                batch.push(path);
+            }
+            _ => {
+                upload_size = size;
+                uploads.push(vec![path]);
+            }
+        }
+    }
+
+    let mut embed = CreateEmbed::default();
+    embed.title("Recording finished").field(
+        "Length",
+        format!("{} minutes", recording.length.as_secs() / 60),
+        false,
+    );
+    if speakers.is_empty() {
+        embed.description("Nobody who agreed to be recorded said anything.");
+    } else {
+        embed.field("Speakers", speakers.join("\n"), false);
+    }
+    if !too_big.is_empty() {
+        let url = BotConfig::get().and_then(|c| c.recording_url());
+        embed.field(
+            "Too big to upload",
+            match url {
+                Some(url) => too_big
+                    .iter()
+                    .map(|name| format!("{}/{}/{}/{}", url, guild_id, recording.id, name))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+                None => format!(
+                    "{}\nAsk the bot owner for these, they're kept in `{}`.",
+                    too_big.join(", "),
+                    recording.dir.display()
+                ),
+            },
+            false,
+        );
+    }
+    if let Err(e) = recording
+        .channel_id
+        .send_message(http, |m| {
+            m.embed(|e| {
+                *e = embed;
+                e
+            })
+        })
+        .await
+    {
+        warn!(guild_id = guild_id.0, "Failed to send recording: {}", e);
+    }
+
+    for batch in uploads {
+        match recording
+            .channel_id
+            .send_files(http, batch.iter(), |m| m)
+            .await
+        {
+            // once it's on Discord there's no need to keep a copy around
+            Ok(_) => {
+                for path in &batch {
+                    let _ = tokio::fs::remove_file(path).await;
+                }
+            }
+            Err(e) => warn!(guild_id = guild_id.0, "Failed to upload recording: {}", e),
+        }
+    }
+    if too_big.is_empty() {
+        let _ = tokio::fs::remove_dir(&recording.dir).await;
+    }
+}
+
+/// Update whether a user agreed to be recorded in a guild's running session, if there is one.
+/// This doesn't touch the DB, that's up to the caller.
+///
+/// Users who opt out mid-session have everything recorded of them so far deleted.
+pub fn set_recording_consent(guild_id: GuildId, user_id: UserId, consent: bool) {
+    let recordings = recordings();
+    let session = match recordings.get(&guild_id) {
+        Some(s) => s,
+        None => return,
+    };
+    let mut session = session.lock().unwrap_or_else(|e| e.into_inner());
+    if consent {
+        session.consenting.insert(user_id.0);
+    } else {
+        session.consenting.remove(&user_id.0);
+        if let Some(track) = session.tracks.remove(&user_id.0) {
+            drop(track.writer);
+            let _ = fs::remove_file(&track.path);
+        }
+    }
+}
+
+/// Add a packet of Opus audio from `user_id` to the guild's recording, if it has one running
+/// and they agreed to be recorded.
+///
+/// `ssrc` and `timestamp` come from the packet's RTP header, and are used to keep the silence
+/// between packets.
+///
+/// Returns `true` once the session has run for `MAX_RECORDING_SECS`, and should be stopped.
+pub fn record_packet(
+    guild_id: GuildId,
+    user_id: u64,
+    ssrc: u32,
+    timestamp: u32,
+    opus: &[u8],
+) -> bool {
+    let recordings = recordings();
+    let session = match recordings.get(&guild_id) {
+        Some(s) => s,
+        None => return false,
+    };
+    let mut session = session.lock().unwrap_or_else(|e| e.into_inner());
+    if session.started.elapsed().as_secs() >= MAX_RECORDING_SECS {
+        return true;
+    }
+    if !session.consenting.contains(&user_id) {
+        return false;
+    }
+
+    let RecordingSession {
+        dir,
+        started,
+        tracks,
+        ..
+    } = &mut *session;
+    let track = match tracks.entry(user_id) {
+        Entry::Occupied(e) => e.into_mut(),
+        Entry::Vacant(e) => {
+            let path = dir.join(format!("{}.opus", user_id));
+            let writer = File::create(&path)
+                .and_then(|f| OggOpusWriter::new(BufWriter::new(f), rand::random(), CHANNELS));
+            match writer {
+                Ok(writer) => e.insert(Track {
+                    writer,
+                    path,
+                    position: 0,
+                    next_timestamp: None,
+                    last_packet: Instant::now(),
+                }),
+                Err(e) => {
+                    error!(
+                        guild_id = guild_id.0,
+                        "couldn't create {}: {}",
+                        path.display(),
+                        e
+                    );
+                    return false;
+                }
+            }
+        }
+    };
+    if let Err(e) = track.write(*started, ssrc, timestamp, opus) {
+        error!(
+            guild_id = guild_id.0,
+            "couldn't write to {}: {}",
+            track.path.display(),
+            e
+        );
+    }
+    false
+}
+
+impl Track {
+    fn write(
+        &mut self,
+        started: Instant,
+        ssrc: u32,
+        timestamp: u32,
+        opus: &[u8],
+    ) -> io::Result<()> {
+        // not worth failing the whole file over a corrupt packet
+        let samples = match opus_packet_samples(opus) {
+            Some(s) => s,
+            None => return Ok(()),
+        };
+        let now = Instant::now();
+        let gap = match self.next_timestamp {
+            Some((last_ssrc, next)) if last_ssrc == ssrc => {
+                let gap = timestamp.wrapping_sub(next) as i32;
+                // late or duplicate packet: there's no going back to insert it
+                if gap < 0 {
+                    return Ok(());
+                }
+                // RTP timestamps are exact, but don't trust one that jumps further ahead
+                // than the time that actually passed
+                let elapsed = samples_between(self.last_packet, now) + OPUS_SAMPLE_RATE as u64;
+                (gap as u64).min(elapsed)
+            }
+            // first packet, or they reconnected with a new SSRC: all there is to go by is
+            // the clock
+            _ => samples_between(started, now).saturating_sub(self.position),
+        };
+
+        self.position += gap;
+        self.writer.write_silence_until(self.position)?;
+        self.writer.write_packet(opus)?;
+        self.position += samples as u64;
+        self.next_timestamp = Some((ssrc, timestamp.wrapping_add(samples)));
+        self.last_packet = now;
+        Ok(())
+    }
+}
+
+fn samples_between(start: Instant, end: Instant) -> u64 {
+    (end.duration_since(start).as_micros() * OPUS_SAMPLE_RATE as u128 / 1_000_000) as u64
+}
@@ -0,0 +1,342 @@
+use ahash::RandomState;
+use scripty_audio_utils::{
+    transcribe_audio, AudioFormat, SttBackend, SttError, SttSettings, Transcript,
+};
+use scripty_config::BotConfig;
+use scripty_metrics::METRICS;
+use serenity::model::id::GuildId;
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, HashMap},
+    fmt,
+    lazy::SyncOnceCell as OnceCell,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::{sync::oneshot, task, time::timeout};
+
+/// How many utterances can wait to be transcribed at once, across every guild.
+const MAX_QUEUE_LEN: usize = 512;
+
+/// Utterances that waited longer than this get dropped:
+/// by the time they'd be sent, the conversation has moved on.
+const MAX_QUEUE_WAIT: Duration = Duration::from_secs(30);
+
+/// How long transcribing a utterance can take before we give up waiting on it.
+const JOB_TIMEOUT: Duration = Duration::from_secs(60);
+
+static SCHEDULER: OnceCell<SttScheduler> = OnceCell::new();
+
+/// Why a utterance didn't get transcribed.
+#[derive(Debug)]
+pub enum SttJobError {
+    /// The backend failed to transcribe it.
+    Stt(SttError),
+    /// The queue was full of jobs with the same or higher priority.
+    QueueFull,
+    /// It waited in the queue for too long.
+    Expired,
+    /// Transcribing it took too long.
+    TimedOut,
+}
+
+impl fmt::Display for SttJobError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SttJobError::Stt(e) => write!(f, "{}", e),
+            SttJobError::QueueFull => write!(f, "the speech-to-text queue is full"),
+            SttJobError::Expired => write!(f, "waited too long in the speech-to-text queue"),
+            SttJobError::TimedOut => write!(f, "speech-to-text took too long"),
+        }
+    }
+}
+
+impl std::error::Error for SttJobError {}
+
+/// A decode waiting for a worker: a whole utterance, or part of a live transcript.
+struct SttJob {
+    enqueued: Instant,
+    /// The decode itself, run on a blocking thread.
+    work: Box<dyn FnOnce() -> Result<Transcript, SttError> + Send>,
+    tx: oneshot::Sender<Result<Transcript, SttJobError>>,
+}
+
+impl SttJob {
+    fn reject(self, error: SttJobError) {
+        let metrics = unsafe { METRICS.get().unwrap_unchecked() };
+        match error {
+            SttJobError::QueueFull => metrics.stt_jobs_dropped.queue_full.inc(),
+            SttJobError::Expired => metrics.stt_jobs_dropped.expired.inc(),
+            SttJobError::TimedOut => metrics.stt_jobs_dropped.timed_out.inc(),
+            SttJobError::Stt(_) => {}
+        }
+        // if nobody's waiting on it anymore there's nobody to tell
+        let _ = self.tx.send(Err(error));
+    }
+}
+
+/// A job in a `JobQueue`, along with what's needed to schedule it.
+struct Queued<J> {
+    guild_id: GuildId,
+    // how many jobs from this guild can run at once
+    guild_cap: usize,
+    enqueued: Instant,
+    job: J,
+}
+
+/// Jobs waiting for a worker, and how many are running, for every guild.
+///
+/// Jobs are ordered by priority, then by when they were queued. This only keeps track of
+/// them: running them is up to the caller.
+pub struct JobQueue<J> {
+    max_len: usize,
+    max_wait: Duration,
+    // ordered by priority (highest first), then by when they were queued (oldest first)
+    queue: BTreeMap<(Reverse<u8>, u64), Queued<J>>,
+    next_seq: u64,
+    running: usize,
+    running_per_guild: HashMap<GuildId, usize, RandomState>,
+}
+
+impl<J> JobQueue<J> {
+    /// Create a queue holding up to `max_len` jobs, which expire after waiting for `max_wait`.
+    pub fn new(max_len: usize, max_wait: Duration) -> Self {
+        Self {
+            max_len,
+            max_wait,
+            queue: BTreeMap::new(),
+            next_seq: 0,
+            running: 0,
+            running_per_guild: HashMap::with_hasher(RandomState::new()),
+        }
+    }
+
+    /// Queue a job from a guild that can run `guild_cap` jobs at once.
+    ///
+    /// If the queue is full, the newest of the lowest priority jobs makes room, but only if
+    /// this one outranks it. Returns whichever job didn't make it, if either.
+    pub fn push(
+        &mut self,
+        guild_id: GuildId,
+        guild_cap: usize,
+        priority: u8,
+        enqueued: Instant,
+        job: J,
+    ) -> Option<J> {
+        let mut dropped = None;
+        if self.queue.len() >= self.max_len {
+            let lowest = self.queue.last_key_value().map(|(&(Reverse(p), _), _)| p);
+            match lowest {
+                Some(lowest) if lowest < priority => {
+                    dropped = self.queue.pop_last().map(|(_, q)| q.job);
+                }
+                _ => return Some(job),
+            }
+        }
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.queue.insert(
+            (Reverse(priority), seq),
+            Queued {
+                guild_id,
+                guild_cap,
+                enqueued,
+                job,
+            },
+        );
+        dropped
+    }
+
+    /// Take every job that's been waiting for too long as of `now` out of the queue.
+    pub fn expire(&mut self, now: Instant) -> Vec<J> {
+        let max_wait = self.max_wait;
+        let expired: Vec<_> = self
+            .queue
+            .iter()
+            .filter(|(_, q)| now.duration_since(q.enqueued) > max_wait)
+            .map(|(key, _)| *key)
+            .collect();
+        expired
+            .into_iter()
+            .filter_map(|key| self.queue.remove(&key))
+            .map(|q| q.job)
+            .collect()
+    }
+
+    /// Take the next job that can run with `workers` workers without going over its guild's
+    /// cap, counting it as running until `finish` is called.
+    pub fn start_next(&mut self, workers: usize) -> Option<(GuildId, J)> {
+        if self.running >= workers {
+            return None;
+        }
+        let running_per_guild = &self.running_per_guild;
+        let key = *self
+            .queue
+            .iter()
+            .find(|(_, q)| running_per_guild.get(&q.guild_id).copied().unwrap_or(0) < q.guild_cap)?
+            .0;
+        let q = self.queue.remove(&key).expect("key was just found");
+
+        self.running += 1;
+        *self.running_per_guild.entry(q.guild_id).or_insert(0) += 1;
+        Some((q.guild_id, q.job))
+    }
+
+    /// A job from the guild finished running.
+    pub fn finish(&mut self, guild_id: GuildId) {
+        self.running = self.running.saturating_sub(1);
+        if let Some(n) = self.running_per_guild.get_mut(&guild_id) {
+            *n -= 1;
+            if *n == 0 {
+                self.running_per_guild.remove(&guild_id);
+            }
+        }
+    }
+
+    /// How many jobs are waiting.
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// How many jobs are running.
+    pub fn running(&self) -> usize {
+        self.running
+    }
+}
+
+/// Runs speech-to-text, a limited number of decodes at a time.
+///
+/// Utterances from guilds with a higher premium level go first, and each guild can only have
+/// so many decodes running at once, so one busy guild can't hold up everyone else.
+struct SttScheduler {
+    state: Mutex<JobQueue<SttJob>>,
+    workers: usize,
+}
+
+fn scheduler() -> &'static SttScheduler {
+    SCHEDULER.get_or_init(|| SttScheduler {
+        state: Mutex::new(JobQueue::new(MAX_QUEUE_LEN, MAX_QUEUE_WAIT)),
+        workers: BotConfig::get()
+            .expect("Failed to load config!")
+            .stt_workers()
+            .map_or_else(num_cpus::get, |w| w.max(1) as usize),
+    })
+}
+
+/// How many decodes from one guild can be running at once.
+fn guild_cap(premium_level: u8) -> usize {
+    match premium_level {
+        0 => 1,
+        1 | 2 => 2,
+        3 => 3,
+        4 => 4,
+        _ => 8,
+    }
+}
+
+impl SttScheduler {
+    fn submit(&'static self, guild_id: GuildId, premium_level: u8, job: SttJob) {
+        let dropped = self.state.lock().expect("poisoned").push(
+            guild_id,
+            guild_cap(premium_level),
+            premium_level,
+            job.enqueued,
+            job,
+        );
+        if let Some(dropped) = dropped {
+            dropped.reject(SttJobError::QueueFull);
+        }
+        self.dispatch();
+    }
+
+    /// Start as many queued jobs as there are free workers for.
+    fn dispatch(&'static self) {
+        let metrics = unsafe { METRICS.get().unwrap_unchecked() };
+        let mut state = self.state.lock().expect("poisoned");
+
+        for job in state.expire(Instant::now()) {
+            job.reject(SttJobError::Expired);
+        }
+        while let Some((guild_id, job)) = state.start_next(self.workers) {
+            task::spawn(self.run(guild_id, job));
+        }
+
+        metrics.stt_queue_depth.set(state.len() as i64);
+        metrics.stt_jobs_running.set(state.running() as i64);
+    }
+
+    async fn run(&'static self, guild_id: GuildId, job: SttJob) {
+        let metrics = unsafe { METRICS.get().unwrap_unchecked() };
+        metrics
+            .stt_queue_wait
+            .observe(job.enqueued.elapsed().as_secs_f64());
+
+        let SttJob { work, tx, .. } = job;
+        let mut handle = task::spawn_blocking(work);
+        match timeout(JOB_TIMEOUT, &mut handle).await {
+            Ok(res) => {
+                let res = res.unwrap_or_else(|e| Err(SttError::Decode(e.to_string())));
+                let _ = tx.send(res.map_err(SttJobError::Stt));
+            }
+            Err(_) => {
+                metrics.stt_jobs_dropped.timed_out.inc();
+                let _ = tx.send(Err(SttJobError::TimedOut));
+                // the decode can't be cancelled once it started, so its worker stays busy
+                // until it actually finishes
+                let _ = handle.await;
+            }
+        }
+
+        self.state.lock().expect("poisoned").finish(guild_id);
+        self.dispatch();
+    }
+}
+
+/// Queue `work` to run on a blocking thread once a worker is free, and wait for what it
+/// returns.
+///
+/// `premium_level` decides both how soon it runs and how many of this guild's decodes
+/// can run at once. Every speech-to-text decode should go through this or `schedule_stt`,
+/// that way there's a limit on how many run at once.
+pub async fn schedule_decode(
+    guild_id: GuildId,
+    premium_level: u8,
+    work: impl FnOnce() -> Result<Transcript, SttError> + Send + 'static,
+) -> Result<Transcript, SttJobError> {
+    let (tx, rx) = oneshot::channel();
+    scheduler().submit(
+        guild_id,
+        premium_level,
+        SttJob {
+            enqueued: Instant::now(),
+            work: Box::new(work),
+            tx,
+        },
+    );
+    // the scheduler always answers, unless it panicked
+    rx.await.unwrap_or(Err(SttJobError::TimedOut))
+}
+
+/// Queue a utterance to be transcribed, and wait for the transcript with up to
+/// `max_candidates` candidates.
+///
+/// `premium_level` decides both how soon it runs and how many of this guild's utterances
+/// can run at once.
+pub async fn schedule_stt(
+    guild_id: GuildId,
+    premium_level: u8,
+    audio: Vec<i16>,
+    format: AudioFormat,
+    model: Arc<dyn SttBackend>,
+    settings: SttSettings,
+    max_candidates: u32,
+) -> Result<Transcript, SttJobError> {
+    schedule_decode(guild_id, premium_level, move || {
+        transcribe_audio(&audio, format, &*model, &settings, max_candidates)
+    })
+    .await
+}
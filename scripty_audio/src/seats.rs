@@ -0,0 +1,145 @@
+use ahash::RandomState;
+use dashmap::DashMap;
+use serenity::model::id::{GuildId, UserId};
+use std::{
+    collections::{HashSet, VecDeque},
+    lazy::SyncOnceCell as OnceCell,
+    ops::Deref,
+    sync::{Arc, RwLock},
+};
+
+/// Seats of every voice chat the bot is transcribing, by guild. `Receiver`s register theirs while
+/// a session is going, that way commands can see who's being transcribed.
+static SEATS: OnceCell<DashMap<GuildId, Arc<RwLock<Seats>>, RandomState>> = OnceCell::new();
+
+fn seats_map() -> &'static DashMap<GuildId, Arc<RwLock<Seats>>, RandomState> {
+    SEATS.get_or_init(|| DashMap::with_hasher(RandomState::new()))
+}
+
+/// Who's being transcribed in a voice chat, and who's waiting for a seat to open up.
+pub struct Seats {
+    max: usize,
+    seated: HashSet<u64, RandomState>,
+    /// People with a priority role first, then everyone else, each in the order they joined.
+    /// The flag is whether they have a priority role.
+    queue: VecDeque<(u64, bool)>,
+}
+
+/// What happened to someone who wanted a seat.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Admission {
+    /// They were already seated or queued.
+    Unchanged,
+    Seated,
+    /// Every seat's taken: they're this far back in the queue, starting from 1.
+    Queued(usize),
+}
+
+impl Seats {
+    /// Empty seats for `max` people.
+    pub fn new(max: usize) -> Self {
+        Self {
+            max,
+            seated: HashSet::with_hasher(RandomState::new()),
+            queue: VecDeque::new(),
+        }
+    }
+
+    pub fn is_seated(&self, user_id: u64) -> bool {
+        self.seated.contains(&user_id)
+    }
+
+    /// Seat `user_id`, or queue them if every seat's taken.
+    pub fn admit(&mut self, user_id: u64, priority: bool) -> Admission {
+        if self.seated.contains(&user_id) || self.queue.iter().any(|(u, _)| *u == user_id) {
+            return Admission::Unchanged;
+        }
+        if self.seated.len() < self.max {
+            self.seated.insert(user_id);
+            return Admission::Seated;
+        }
+        let i = if priority {
+            self.queue
+                .iter()
+                .position(|(_, p)| !p)
+                .unwrap_or(self.queue.len())
+        } else {
+            self.queue.len()
+        };
+        self.queue.insert(i, (user_id, priority));
+        Admission::Queued(i + 1)
+    }
+
+    /// Give up `user_id`'s seat or place in the queue, returning who got their seat, if anyone.
+    pub fn leave(&mut self, user_id: u64) -> Option<u64> {
+        if !self.seated.remove(&user_id) {
+            self.queue.retain(|(u, _)| *u != user_id);
+            return None;
+        }
+        let (next, _) = self.queue.pop_front()?;
+        self.seated.insert(next);
+        Some(next)
+    }
+}
+
+/// A voice chat's seats, listed in `SEATS` while its session is going. They're taken off the
+/// list when the session ends, and for good once the last `Receiver` holding them is dropped.
+pub(crate) struct RegisteredSeats {
+    guild_id: GuildId,
+    seats: Arc<RwLock<Seats>>,
+}
+
+impl RegisteredSeats {
+    /// Make empty seats for a new voice chat in the guild, replacing any from the last one.
+    pub(crate) fn new(guild_id: GuildId, max: usize) -> Self {
+        let seats = Arc::new(RwLock::new(Seats::new(max)));
+        seats_map().insert(guild_id, Arc::clone(&seats));
+        Self { guild_id, seats }
+    }
+
+    /// List these seats again, once someone joins after the session ended.
+    pub(crate) fn register(&self) {
+        seats_map()
+            .entry(self.guild_id)
+            .or_insert_with(|| Arc::clone(&self.seats));
+    }
+
+    /// Take these seats off the list, unless a newer voice chat's replaced them already.
+    pub(crate) fn unregister(&self) {
+        seats_map().remove_if(&self.guild_id, |_, s| Arc::ptr_eq(s, &self.seats));
+    }
+}
+
+impl Deref for RegisteredSeats {
+    type Target = RwLock<Seats>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.seats
+    }
+}
+
+impl Drop for RegisteredSeats {
+    fn drop(&mut self) {
+        self.unregister();
+    }
+}
+
+/// Who's being transcribed in a guild's voice chat and who's waiting to be, as `seat_list`
+/// returns them.
+pub struct SeatList {
+    pub max: usize,
+    pub seated: Vec<UserId>,
+    /// In the order they'll get a seat.
+    pub queued: Vec<UserId>,
+}
+
+/// Who's being transcribed in the guild's voice chat, if there's a session going.
+pub fn seat_list(guild_id: GuildId) -> Option<SeatList> {
+    let seats = Arc::clone(seats_map().get(&guild_id)?.value());
+    let seats = seats.read().ok()?;
+    Some(SeatList {
+        max: seats.max,
+        seated: seats.seated.iter().map(|u| UserId(*u)).collect(),
+        queued: seats.queue.iter().map(|(u, _)| UserId(*u)).collect(),
+    })
+}
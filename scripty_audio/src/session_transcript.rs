@@ -0,0 +1,170 @@
+use ahash::RandomState;
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use scripty_audio_utils::{export_transcript, ExportFormat, Utterance};
+use serenity::{
+    http::{AttachmentType, Http},
+    model::{
+        channel::Message,
+        id::{ChannelId, GuildId},
+    },
+};
+use std::{
+    borrow::Cow,
+    lazy::SyncOnceCell as OnceCell,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Everything said in each guild since its voice session started, by guild.
+static SESSIONS: OnceCell<DashMap<GuildId, Mutex<SessionTranscript>, RandomState>> =
+    OnceCell::new();
+
+/// Sessions stop logging after this many utterances, so one that never ends can't eat up all
+/// the memory.
+const MAX_ENTRIES: usize = 20_000;
+
+fn sessions() -> &'static DashMap<GuildId, Mutex<SessionTranscript>, RandomState> {
+    SESSIONS.get_or_init(|| DashMap::with_hasher(RandomState::new()))
+}
+
+/// Every utterance transcribed in a guild's voice session so far.
+#[derive(Clone)]
+pub struct SessionTranscript {
+    started: Instant,
+    pub started_at: DateTime<Utc>,
+    /// The channel transcripts were sent to.
+    pub channel_id: ChannelId,
+    /// In order, with timings relative to the start of the session.
+    pub entries: Vec<Utterance>,
+}
+
+impl SessionTranscript {
+    /// Format the whole session as one document, returning it and its file name.
+    pub fn export(&self, format: ExportFormat) -> (String, String) {
+        let title = format!(
+            "Voice chat transcript, started {} UTC",
+            self.started_at.format("%Y-%m-%d %H:%M")
+        );
+        (
+            export_transcript(&self.entries, format, &title),
+            format!(
+                "transcript-{}.{}",
+                self.started_at.format("%Y%m%d-%H%M"),
+                format.name()
+            ),
+        )
+    }
+}
+
+/// Add a utterance to the guild's session, starting a new one if there isn't one.
+///
+/// `start` is when the utterance was said, with its timings relative to then, and
+/// `channel_id` is where its transcript was sent.
+pub fn log_utterance(
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    start: Instant,
+    mut utterance: Utterance,
+) {
+    if utterance.text.is_empty() {
+        return;
+    }
+    let sessions = sessions();
+    let session = sessions.entry(guild_id).or_insert_with(|| {
+        Mutex::new(SessionTranscript {
+            // the utterance started before it was logged: back the session's start up to it
+            started: start,
+            started_at: Utc::now()
+                - chrono::Duration::from_std(start.elapsed())
+                    .unwrap_or_else(|_| chrono::Duration::zero()),
+            channel_id,
+            entries: Vec::new(),
+        })
+    });
+    let mut session = session.lock().unwrap_or_else(|e| e.into_inner());
+    if session.entries.len() >= MAX_ENTRIES {
+        return;
+    }
+    utterance.shift(
+        start
+            .checked_duration_since(session.started)
+            .unwrap_or_else(|| Duration::from_secs(0))
+            .as_millis() as u32,
+    );
+    // utterances are transcribed in parallel, so they can finish out of order
+    let i = session
+        .entries
+        .iter()
+        .rposition(|e| e.start_ms <= utterance.start_ms)
+        .map_or(0, |i| i + 1);
+    session.entries.insert(i, utterance);
+}
+
+/// Swap the text of `speaker_id`'s last utterance that was `original` for `corrected`, after
+/// they picked a alternative.
+pub fn correct_utterance(guild_id: GuildId, speaker_id: u64, original: &str, corrected: String) {
+    let session = match sessions().get(&guild_id) {
+        Some(s) => s,
+        None => return,
+    };
+    let mut session = session.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(u) = session
+        .entries
+        .iter_mut()
+        .rev()
+        .find(|u| u.speaker_id == speaker_id && u.text == original)
+    {
+        u.text = corrected;
+        // the timings were for the words of the old text
+        u.words.clear();
+    }
+}
+
+/// Everything said in the guild's session so far, if anything was.
+pub fn session_transcript(guild_id: GuildId) -> Option<SessionTranscript> {
+    let session = sessions().get(&guild_id)?;
+    let session = session.lock().unwrap_or_else(|e| e.into_inner());
+    Some(session.clone())
+}
+
+/// End the guild's session, returning everything said in it.
+pub fn take_session_transcript(guild_id: GuildId) -> Option<SessionTranscript> {
+    let (_, session) = sessions().remove(&guild_id)?;
+    Some(session.into_inner().unwrap_or_else(|e| e.into_inner()))
+}
+
+/// End every guild's session, returning everything said in them.
+pub fn take_all_session_transcripts() -> Vec<(GuildId, SessionTranscript)> {
+    let sessions = sessions();
+    let guilds: Vec<GuildId> = sessions.iter().map(|s| *s.key()).collect();
+    guilds
+        .into_iter()
+        .filter_map(|g| take_session_transcript(g).map(|t| (g, t)))
+        .collect()
+}
+
+/// Attach a session's transcript to a message in `channel_id`.
+pub async fn send_session_transcript(
+    http: impl AsRef<Http>,
+    channel_id: ChannelId,
+    transcript: &SessionTranscript,
+    format: ExportFormat,
+) -> serenity::Result<Message> {
+    let (data, filename) = transcript.export(format);
+    channel_id
+        .send_files(
+            http,
+            vec![AttachmentType::Bytes {
+                data: Cow::Owned(data.into_bytes()),
+                filename,
+            }],
+            |m| {
+                m.content(format!(
+                    "Here's everything said since {} UTC.",
+                    transcript.started_at.format("%Y-%m-%d %H:%M")
+                ))
+            },
+        )
+        .await
+}
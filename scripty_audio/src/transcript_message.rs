@@ -0,0 +1,184 @@
+use crate::corrections::{alternatives_button, PendingCorrection};
+use scripty_audio_utils::{redact, SttBackend, SttSettings, Transcript, Utterance};
+use scripty_config::BotConfig;
+use scripty_metrics::METRICS;
+use serenity::builder::{EditWebhookMessage, ExecuteWebhook};
+use serenity::model::prelude::{Embed, GuildId, User, UserId, Webhook};
+use std::{sync::Arc, time::Instant};
+
+/// The body of a message containing a transcript, ready to be sent or edited in with a webhook.
+pub(crate) struct TranscriptMessage {
+    /// What was said, with the guild's redaction rules applied.
+    /// Its timings are relative to the start of the audio.
+    utterance: Utterance,
+    /// Whether the model was less confident in it than the guild's threshold.
+    unsure: bool,
+    /// The model's other guesses at what was said, redacted, which the speaker can pick
+    /// instead. Always empty in verbose mode.
+    alternatives: Vec<String>,
+    /// A embed with extra info about the transcript, used in verbose mode.
+    verbose: Option<String>,
+}
+
+impl TranscriptMessage {
+    /// Build the message for a transcript. Returns `None` if there's nothing worth sending.
+    ///
+    /// Transcripts `model` is less confident in than the guild's threshold are dropped or
+    /// marked as unsure, depending on `settings`, and counted in metrics either way.
+    /// Everything the guild's redaction rules ask for is masked.
+    ///
+    /// `speaker` said it, and `duration_ms` is how long the transcribed audio was.
+    pub(crate) fn new(
+        r: &Transcript,
+        model: &dyn SttBackend,
+        settings: &SttSettings,
+        verbose: bool,
+        speaker: &User,
+        duration_ms: u32,
+    ) -> Option<Self> {
+        let t = match r.best() {
+            Some(t) => t,
+            None if verbose => {
+                return Some(TranscriptMessage {
+                    utterance: Utterance::default(),
+                    unsure: false,
+                    alternatives: Vec::new(),
+                    verbose: Some("No transcriptions found".to_string()),
+                })
+            }
+            None => return None,
+        };
+
+        let metrics = unsafe { METRICS.get().unwrap_unchecked() };
+        let confidence = model.normalized_confidence(t);
+        metrics.stt_confidence.observe(confidence);
+        let unsure = confidence < settings.confidence_threshold() as f64;
+        if unsure {
+            if settings.mark_low_confidence {
+                metrics.stt_low_confidence.marked.inc();
+            } else {
+                metrics.stt_low_confidence.dropped.inc();
+                // verbose mode is for debugging, so it shows what would have been dropped too
+                if !verbose {
+                    return None;
+                }
+            }
+        }
+
+        let utterance = Utterance::new(
+            speaker.id.0,
+            speaker.name.clone(),
+            t,
+            confidence,
+            duration_ms,
+            &settings.redaction,
+        );
+        let mut alternatives = Vec::new();
+        if !verbose {
+            for c in r.alternatives() {
+                let text = redact(&c.text, &settings.redaction);
+                if !text.is_empty() && text != utterance.text && !alternatives.contains(&text) {
+                    alternatives.push(text);
+                }
+            }
+        }
+        let verbose = if verbose {
+            Some(format!(
+                "**Transcription**\n{}\n\n\
+                    **Confidence %**\n{:.1}{}\n\n\
+                    **Start Offset (ms)**\n{}\n\n\
+                    **Length (ms)**\n{}\n\n\
+                    **Words**\n{}\n\n\
+                    **Total Possiblities**\n{}",
+                utterance.text,
+                confidence * 100.0,
+                if unsure { " (below threshold)" } else { "" },
+                t.start_ms(),
+                t.end_ms() - t.start_ms(),
+                utterance.words.len(),
+                r.candidates().len()
+            ))
+        } else {
+            None
+        };
+        Some(TranscriptMessage {
+            utterance,
+            unsure,
+            alternatives,
+            verbose,
+        })
+    }
+
+    /// What was said, for the session's transcript. Its text is empty if there's nothing to
+    /// log.
+    pub(crate) fn utterance(&self) -> &Utterance {
+        &self.utterance
+    }
+
+    /// What's needed to swap the message for one of its alternatives once it's sent with
+    /// `webhook`, if it has any.
+    pub(crate) fn pending_correction(
+        &self,
+        guild_id: GuildId,
+        webhook: Arc<Webhook>,
+        settings: &SttSettings,
+    ) -> Option<PendingCorrection> {
+        if self.alternatives.is_empty() {
+            return None;
+        }
+        let mut candidates = Vec::with_capacity(self.alternatives.len() + 1);
+        candidates.push(self.utterance.text.clone());
+        candidates.extend(self.alternatives.iter().cloned());
+        Some(PendingCorrection {
+            guild_id,
+            speaker_id: UserId(self.utterance.speaker_id),
+            language: settings.language.clone().unwrap_or_else(|| {
+                BotConfig::get()
+                    .map_or("en", |c| c.default_language())
+                    .to_string()
+            }),
+            candidates,
+            webhook,
+            sent: Instant::now(),
+        })
+    }
+
+    /// The content of the message, if it isn't a embed.
+    fn content(&self) -> String {
+        if self.unsure {
+            format!("_{}_ (?)", self.utterance.text)
+        } else {
+            self.utterance.text.clone()
+        }
+    }
+
+    pub(crate) fn execute(self, webhook_execute: &mut ExecuteWebhook) {
+        match self.verbose {
+            Some(d) => {
+                webhook_execute.embeds(vec![Embed::fake(|x| x.description(d))]);
+            }
+            None => {
+                webhook_execute.content(self.content());
+                if !self.alternatives.is_empty() {
+                    webhook_execute.components(alternatives_button);
+                }
+            }
+        }
+    }
+
+    pub(crate) fn edit(self, webhook_edit: &mut EditWebhookMessage) {
+        match self.verbose {
+            Some(d) => {
+                webhook_edit
+                    .content("")
+                    .embeds(vec![Embed::fake(|x| x.description(d))]);
+            }
+            None => {
+                webhook_edit.content(self.content()).embeds(vec![]);
+                if !self.alternatives.is_empty() {
+                    webhook_edit.components(alternatives_button);
+                }
+            }
+        }
+    }
+}
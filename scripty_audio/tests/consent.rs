@@ -0,0 +1,59 @@
+use scripty_audio::UserConsent;
+
+#[test]
+fn nobody_opted_out_by_default() {
+    let c = UserConsent::default();
+    assert!(!c.opted_out());
+    assert!(c.permits(false));
+    // but strict guilds still need them to agree
+    assert!(!c.permits(true));
+}
+
+#[test]
+fn opted_out_is_never_transcribed() {
+    for opt_out in [
+        UserConsent {
+            global_opt_out: true,
+            ..Default::default()
+        },
+        UserConsent {
+            guild_opt_out: Some(true),
+            ..Default::default()
+        },
+    ] {
+        // not even if they agreed to the privacy policy before opting out
+        let c = UserConsent {
+            agreed: true,
+            ..opt_out
+        };
+        assert!(c.opted_out());
+        assert!(!c.permits(false));
+        assert!(!c.permits(true));
+    }
+}
+
+#[test]
+fn guild_choice_overrides_global() {
+    let back_in = UserConsent {
+        global_opt_out: true,
+        guild_opt_out: Some(false),
+        ..Default::default()
+    };
+    assert!(back_in.permits(false));
+
+    let out_here = UserConsent {
+        global_opt_out: false,
+        guild_opt_out: Some(true),
+        ..Default::default()
+    };
+    assert!(!out_here.permits(false));
+}
+
+#[test]
+fn agreeing_is_enough_for_strict_guilds() {
+    let c = UserConsent {
+        agreed: true,
+        ..Default::default()
+    };
+    assert!(c.permits(true));
+}
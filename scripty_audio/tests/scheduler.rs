@@ -0,0 +1,92 @@
+use scripty_audio::JobQueue;
+use serenity::model::id::GuildId;
+use std::time::{Duration, Instant};
+
+const WAIT: Duration = Duration::from_secs(30);
+
+fn drain(queue: &mut JobQueue<u32>, workers: usize) -> Vec<u32> {
+    std::iter::from_fn(|| queue.start_next(workers).map(|(_, job)| job)).collect()
+}
+
+#[test]
+fn higher_priority_goes_first_then_oldest_first() {
+    let mut queue = JobQueue::new(16, WAIT);
+    let now = Instant::now();
+    queue.push(GuildId(1), 8, 0, now, 1);
+    queue.push(GuildId(2), 8, 2, now, 2);
+    queue.push(GuildId(3), 8, 0, now, 3);
+    queue.push(GuildId(4), 8, 2, now, 4);
+    assert_eq!(drain(&mut queue, 16), vec![2, 4, 1, 3]);
+}
+
+#[test]
+fn full_queue_evicts_the_newest_lowest_priority_job() {
+    let mut queue = JobQueue::new(3, WAIT);
+    let now = Instant::now();
+    assert_eq!(queue.push(GuildId(1), 8, 1, now, 1), None);
+    assert_eq!(queue.push(GuildId(1), 8, 0, now, 2), None);
+    assert_eq!(queue.push(GuildId(1), 8, 0, now, 3), None);
+
+    assert_eq!(queue.push(GuildId(2), 8, 1, now, 4), Some(3));
+    assert_eq!(queue.len(), 3);
+    assert_eq!(drain(&mut queue, 16), vec![1, 4, 2]);
+}
+
+#[test]
+fn full_queue_rejects_jobs_that_dont_outrank_anything() {
+    let mut queue = JobQueue::new(2, WAIT);
+    let now = Instant::now();
+    queue.push(GuildId(1), 8, 1, now, 1);
+    queue.push(GuildId(1), 8, 1, now, 2);
+
+    assert_eq!(queue.push(GuildId(2), 8, 1, now, 3), Some(3));
+    assert_eq!(queue.push(GuildId(2), 8, 0, now, 4), Some(4));
+    assert_eq!(drain(&mut queue, 16), vec![1, 2]);
+}
+
+#[test]
+fn guild_cap_lets_other_guilds_through() {
+    let mut queue = JobQueue::new(16, WAIT);
+    let now = Instant::now();
+    queue.push(GuildId(1), 1, 0, now, 1);
+    queue.push(GuildId(1), 1, 0, now, 2);
+    queue.push(GuildId(2), 1, 0, now, 3);
+
+    assert_eq!(queue.start_next(16), Some((GuildId(1), 1)));
+    // guild 1 is at its cap, so its second job waits for the first to finish
+    assert_eq!(queue.start_next(16), Some((GuildId(2), 3)));
+    assert_eq!(queue.start_next(16), None);
+    assert_eq!(queue.len(), 1);
+
+    queue.finish(GuildId(1));
+    assert_eq!(queue.start_next(16), Some((GuildId(1), 2)));
+    assert!(queue.is_empty());
+}
+
+#[test]
+fn workers_limit_jobs_running_at_once() {
+    let mut queue = JobQueue::new(16, WAIT);
+    let now = Instant::now();
+    for job in 0..4 {
+        queue.push(GuildId(1), 8, 0, now, job);
+    }
+
+    assert_eq!(drain(&mut queue, 2), vec![0, 1]);
+    assert_eq!(queue.running(), 2);
+    queue.finish(GuildId(1));
+    assert_eq!(queue.running(), 1);
+    assert_eq!(drain(&mut queue, 2), vec![2]);
+}
+
+#[test]
+fn jobs_expire_after_waiting_too_long() {
+    let mut queue = JobQueue::new(16, WAIT);
+    let now = Instant::now();
+    queue.push(GuildId(1), 8, 4, now, 1);
+    queue.push(GuildId(2), 8, 0, now + Duration::from_secs(20), 2);
+
+    assert!(queue.expire(now + WAIT).is_empty());
+    assert_eq!(queue.expire(now + WAIT + Duration::from_secs(1)), vec![1]);
+    assert_eq!(queue.len(), 1);
+    assert_eq!(drain(&mut queue, 16), vec![2]);
+}
@@ -0,0 +1,71 @@
+use scripty_audio::{Admission, Seats};
+
+#[test]
+fn seats_fill_in_join_order() {
+    let mut seats = Seats::new(2);
+    assert_eq!(seats.admit(1, false), Admission::Seated);
+    assert_eq!(seats.admit(2, false), Admission::Seated);
+    assert_eq!(seats.admit(3, false), Admission::Queued(1));
+    assert_eq!(seats.admit(4, false), Admission::Queued(2));
+    assert!(seats.is_seated(1) && seats.is_seated(2));
+    assert!(!seats.is_seated(3) && !seats.is_seated(4));
+}
+
+#[test]
+fn admitting_twice_changes_nothing() {
+    let mut seats = Seats::new(1);
+    seats.admit(1, false);
+    seats.admit(2, false);
+    assert_eq!(seats.admit(1, false), Admission::Unchanged);
+    assert_eq!(seats.admit(2, true), Admission::Unchanged);
+    assert_eq!(seats.admit(3, false), Admission::Queued(2));
+}
+
+#[test]
+fn priority_goes_ahead_of_everyone_else() {
+    let mut seats = Seats::new(1);
+    seats.admit(1, false);
+    assert_eq!(seats.admit(2, false), Admission::Queued(1));
+    assert_eq!(seats.admit(3, false), Admission::Queued(2));
+    assert_eq!(seats.admit(4, true), Admission::Queued(1));
+    // but behind whoever had priority first
+    assert_eq!(seats.admit(5, true), Admission::Queued(2));
+    assert_eq!(seats.admit(6, false), Admission::Queued(5));
+}
+
+#[test]
+fn leaving_promotes_the_front_of_the_queue() {
+    let mut seats = Seats::new(1);
+    seats.admit(1, false);
+    seats.admit(2, false);
+    seats.admit(3, true);
+
+    assert_eq!(seats.leave(1), Some(3));
+    assert!(seats.is_seated(3));
+    assert_eq!(seats.leave(3), Some(2));
+    assert_eq!(seats.leave(2), None);
+    // the seat's free for whoever's next
+    assert_eq!(seats.admit(4, false), Admission::Seated);
+}
+
+#[test]
+fn leaving_the_queue_gives_up_the_place() {
+    let mut seats = Seats::new(1);
+    seats.admit(1, false);
+    seats.admit(2, false);
+    seats.admit(3, false);
+
+    // nobody gets a seat when someone who didn't have one leaves
+    assert_eq!(seats.leave(2), None);
+    assert_eq!(seats.admit(4, false), Admission::Queued(2));
+    assert_eq!(seats.leave(1), Some(3));
+    assert_eq!(seats.leave(99), None);
+}
+
+#[test]
+fn unlimited_seats_never_queue() {
+    let mut seats = Seats::new(usize::MAX);
+    for u in 0..1_000 {
+        assert_eq!(seats.admit(u, false), Admission::Seated);
+    }
+}
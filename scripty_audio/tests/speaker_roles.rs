@@ -0,0 +1,53 @@
+use scripty_audio::SpeakerRoles;
+
+#[test]
+fn everyone_without_roles() {
+    let roles = SpeakerRoles::default();
+    assert!(roles.is_empty());
+    assert!(roles.permits(&[]));
+    assert!(roles.permits(&[1, 2]));
+}
+
+#[test]
+fn allowlist_needs_one_role() {
+    let roles = SpeakerRoles {
+        allowed: vec![1, 2],
+        ..Default::default()
+    };
+    assert!(roles.permits(&[2]));
+    assert!(roles.permits(&[3, 1]));
+    assert!(!roles.permits(&[3]));
+    assert!(!roles.permits(&[]));
+}
+
+#[test]
+fn denylist_beats_allowlist() {
+    let roles = SpeakerRoles {
+        allowed: vec![1],
+        denied: vec![2],
+        ..Default::default()
+    };
+    assert!(roles.permits(&[1]));
+    assert!(!roles.permits(&[1, 2]));
+
+    let roles = SpeakerRoles {
+        denied: vec![2],
+        ..Default::default()
+    };
+    assert!(roles.permits(&[]));
+    assert!(roles.permits(&[1]));
+    assert!(!roles.permits(&[2]));
+}
+
+#[test]
+fn priority_doesnt_change_who_is_transcribed() {
+    let roles = SpeakerRoles {
+        priority: vec![3],
+        ..Default::default()
+    };
+    assert!(!roles.is_empty());
+    assert!(roles.permits(&[]));
+    assert!(roles.permits(&[3]));
+    assert!(roles.has_priority(&[1, 3]));
+    assert!(!roles.has_priority(&[1]));
+}
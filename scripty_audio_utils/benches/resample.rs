@@ -0,0 +1,56 @@
+//! How much faster than real time resampling Discord's audio for the models is.
+//!
+//! Run with `cargo bench -p scripty_audio_utils --bench resample`.
+use scripty_audio_utils::{hz_to_hz, Resampler};
+use std::f64::consts::PI;
+use std::time::Instant;
+
+const SECONDS: f64 = 10.0;
+const RUNS: u32 = 20;
+
+fn sine(freq: f64, sample_rate: u32, seconds: f64, amplitude: f64) -> Vec<i16> {
+    (0..(sample_rate as f64 * seconds) as usize)
+        .map(|n| (amplitude * (2.0 * PI * freq * n as f64 / sample_rate as f64).sin()) as i16)
+        .collect()
+}
+
+/// Run `f` `RUNS` times, and print the best run as a multiple of real time.
+fn bench(name: &str, mut f: impl FnMut()) {
+    let best = (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            f();
+            start.elapsed()
+        })
+        .min()
+        .expect("no runs");
+    println!(
+        "{:<32} {:>8.1}x real time ({:?} for {}s of audio)",
+        name,
+        SECONDS / best.as_secs_f64(),
+        best,
+        SECONDS
+    );
+}
+
+fn main() {
+    let input = sine(440.0, 48_000, SECONDS, 10_000.0);
+    let input_44k = sine(440.0, 44_100, SECONDS, 10_000.0);
+
+    bench("whole buffer, 48kHz -> 16kHz", || {
+        assert_eq!(hz_to_hz(&input, 48_000, 16_000).len(), 160_000);
+    });
+    bench("whole buffer, 44.1kHz -> 16kHz", || {
+        assert_eq!(hz_to_hz(&input_44k, 44_100, 16_000).len(), 160_000);
+    });
+    // 20ms at a time, like Discord sends it
+    bench("streamed, 48kHz -> 16kHz", || {
+        let mut resampler = Resampler::new(48_000, 16_000);
+        let mut output = Vec::with_capacity(input.len() / 3 + 1);
+        for chunk in input.chunks(960) {
+            resampler.process(chunk, &mut output);
+        }
+        resampler.flush(&mut output);
+        assert!(output.len() >= 160_000);
+    });
+}
@@ -0,0 +1,266 @@
+use crate::{
+    downmix_to_mono, hz_to_hz, speech_range, transcribe_chunked, AudioFormat, Candidate, HotWord,
+    ModelKey, SttSettings, Transcript, DENOISE_SAMPLE_RATE,
+};
+use scripty_config::{BotConfig, SttBackendKind};
+use std::{fmt, path::Path, sync::Arc};
+
+/// A speech-to-text engine.
+///
+/// Implementations must be safe to share between every voice connection: `transcribe` only
+/// takes `&self`, so any state a engine needs to mutate while decoding has to live behind
+/// its own lock.
+pub trait SttBackend: Send + Sync {
+    /// A short human readable name for this backend, used in logs.
+    fn name(&self) -> &'static str;
+
+    /// The sample rate, in Hz, this backend expects its mono input audio to be in.
+    fn sample_rate(&self) -> u32;
+
+    /// Run speech-to-text on a complete utterance of mono audio at `self.sample_rate()`.
+    ///
+    /// `hot_words` only apply to this decode. Backends that can't be biased ignore them.
+    /// Up to `max_candidates` candidates are returned: backends that only ever come up with
+    /// one return just that.
+    fn transcribe(
+        &self,
+        audio: &[i16],
+        hot_words: &[HotWord],
+        max_candidates: u32,
+    ) -> Result<Transcript, SttError>;
+
+    /// How confident this backend is in `candidate`, from 0 to 1.
+    ///
+    /// Unlike `Candidate::confidence`, this can be compared between backends. The default is
+    /// for backends that already report a probability.
+    fn normalized_confidence(&self, candidate: &Candidate) -> f64 {
+        candidate.confidence.max(0.0).min(1.0)
+    }
+
+    /// The opposite of `normalized_confidence`: the confidence `candidate` needs for it to be
+    /// normalized to `normalized`.
+    ///
+    /// This puts transcripts stitched together from several windows back on this backend's
+    /// own scale.
+    fn denormalize_confidence(&self, normalized: f64, _candidate: &Candidate) -> f64 {
+        normalized
+    }
+
+    /// Whether `create_stream` is supported by this backend.
+    fn supports_streaming(&self) -> bool {
+        false
+    }
+
+    /// Start a streaming decode, where audio is fed in as it arrives.
+    /// `max_candidates` is how many candidates the final transcript can have, the same as
+    /// for `transcribe`.
+    ///
+    /// Backends without a streaming API return `SttError::StreamingUnsupported`.
+    fn create_stream(
+        &self,
+        _hot_words: &[HotWord],
+        _max_candidates: u32,
+    ) -> Result<Box<dyn SttStream>, SttError> {
+        Err(SttError::StreamingUnsupported(self.name()))
+    }
+}
+
+/// A in-progress streaming decode, created by `SttBackend::create_stream`.
+pub trait SttStream: Send {
+    /// Feed more mono audio at the backend's sample rate into the decoder.
+    fn feed_audio(&mut self, audio: &[i16]) -> Result<(), SttError>;
+
+    /// Decode everything fed in so far without ending the stream.
+    fn intermediate(&mut self) -> Result<Transcript, SttError>;
+
+    /// End the stream and return the final transcript.
+    fn finish(self: Box<Self>) -> Result<Transcript, SttError>;
+}
+
+/// A error returned from a speech-to-text backend.
+#[derive(Debug)]
+pub enum SttError {
+    /// The model could not be loaded from disk.
+    Load(String),
+    /// The backend failed while decoding audio.
+    Decode(String),
+    /// The named backend can't decode audio as a stream.
+    StreamingUnsupported(&'static str),
+    /// A custom scorer could not be built.
+    Scorer(String),
+}
+
+impl fmt::Display for SttError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SttError::Load(e) => write!(f, "failed to load model: {}", e),
+            SttError::Decode(e) => write!(f, "failed to decode audio: {}", e),
+            SttError::StreamingUnsupported(name) => {
+                write!(f, "the {} backend doesn't support streaming", name)
+            }
+            SttError::Scorer(e) => write!(f, "failed to build scorer: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for SttError {}
+
+/// Get a shared handle to the model for `key`, loading it on a blocking thread if needed.
+///
+/// # Panics
+/// This function panics if the config isn't loaded yet.
+pub async fn load_model(key: &ModelKey) -> Result<Arc<dyn SttBackend>, SttError> {
+    if let Some(model) = loaded_model(key) {
+        return Ok(model);
+    }
+    let key = key.clone();
+    tokio::task::spawn_blocking(move || {
+        let config = BotConfig::get().expect("Failed to load config!");
+        crate::ModelRegistry::global()
+            .get_or_load(config, &key)
+            .map(|m| m as Arc<dyn SttBackend>)
+    })
+    .await
+    .expect("Failed to spawn blocking!")
+}
+
+/// Get a shared handle to the model for `key` if it's loaded already.
+///
+/// This never touches the disk, so it's fine to call for every utterance.
+pub fn loaded_model(key: &ModelKey) -> Option<Arc<dyn SttBackend>> {
+    crate::ModelRegistry::global()
+        .get(key)
+        .map(|m| m as Arc<dyn SttBackend>)
+}
+
+/// Stop keeping the model for `key` loaded, like when a custom scorer was replaced.
+///
+/// Voice connections using it keep it until their next utterance.
+pub fn unload_model(key: &ModelKey) -> bool {
+    crate::ModelRegistry::global().remove(key)
+}
+
+/// Reload every model loaded so far from disk, for when the files in `model_path` changed.
+///
+/// Voice connections pick up the new models with their next utterance.
+pub async fn reload_models() -> Result<usize, SttError> {
+    tokio::task::spawn_blocking(|| {
+        let config = BotConfig::get().expect("Failed to load config!");
+        crate::ModelRegistry::global().reload(config)
+    })
+    .await
+    .expect("Failed to spawn blocking!")
+}
+
+/// Load the backend set in `config` from the model directory at `model_dir`.
+///
+/// `language` is only used by backends with multilingual models, to pick what to transcribe as.
+/// `scorer` replaces the scorer in `model_dir`, which only DeepSpeech has.
+pub fn load_backend(
+    config: &BotConfig,
+    model_dir: &Path,
+    language: &str,
+    scorer: Option<&Path>,
+) -> Result<Arc<dyn SttBackend>, SttError> {
+    if scorer.is_some() && config.stt_backend() != SttBackendKind::DeepSpeech {
+        return Err(SttError::Load(
+            "custom scorers only work with the deepspeech backend".to_string(),
+        ));
+    }
+    Ok(match config.stt_backend() {
+        SttBackendKind::DeepSpeech => Arc::new(crate::DeepSpeechBackend::load(
+            model_dir,
+            scorer,
+            config.max_boosted_models() as usize,
+        )?),
+        #[cfg(feature = "whisper")]
+        SttBackendKind::Whisper => Arc::new(crate::WhisperBackend::load(
+            model_dir,
+            config
+                .whisper_threads()
+                .unwrap_or_else(|| num_cpus::get() as u16),
+            language,
+        )?),
+        #[cfg(not(feature = "whisper"))]
+        SttBackendKind::Whisper => {
+            return Err(SttError::Load(
+                "the whisper backend was selected, but scripty was built without the `whisper` \
+                feature"
+                    .to_string(),
+            ))
+        }
+        #[cfg(feature = "vosk")]
+        SttBackendKind::Vosk => Arc::new(crate::VoskBackend::load(model_dir)?),
+        #[cfg(not(feature = "vosk"))]
+        SttBackendKind::Vosk => {
+            return Err(SttError::Load(
+                "the vosk backend was selected, but scripty was built without the `vosk` feature"
+                    .to_string(),
+            ))
+        }
+    })
+}
+
+/// Convert raw audio in `format` into mono audio at `sample_rate`, optionally denoising it.
+pub fn prepare_audio(
+    input_data: &[i16],
+    format: AudioFormat,
+    sample_rate: u32,
+    denoise: bool,
+) -> Vec<i16> {
+    // Start off by mixing all the channels down to one.
+    let mut input_data = downmix_to_mono(input_data, format);
+    let mut source_hz = format.sample_rate;
+
+    // The denoiser only works at 48KHz, which is what Discord sends anyway.
+    if denoise {
+        input_data = crate::denoise(&hz_to_hz(&input_data, source_hz, DENOISE_SAMPLE_RATE));
+        source_hz = DENOISE_SAMPLE_RATE;
+    }
+
+    // Then convert to whatever the backend was trained on (usually 16KHz)
+    hz_to_hz(&input_data, source_hz, sample_rate)
+}
+
+/// Run speech-to-text on raw audio in `format`, usually straight from Discord, getting up to
+/// `max_candidates` candidates. This blocks until it's done, see `run_stt` for async code.
+///
+/// Timings in the transcript are from the start of `input_data`, silence and all.
+pub fn transcribe_audio(
+    input_data: &[i16],
+    format: AudioFormat,
+    m: &dyn SttBackend,
+    settings: &SttSettings,
+    max_candidates: u32,
+) -> Result<Transcript, SttError> {
+    let audio_buf = prepare_audio(input_data, format, m.sample_rate(), settings.denoise);
+
+    // cut off silence at either end, and skip inference entirely if nobody actually spoke
+    let speech = speech_range(&audio_buf, m.sample_rate(), &settings.vad);
+    if speech.is_empty() {
+        return Ok(Transcript::default());
+    }
+    let offset_ms = (speech.start as u64 * 1000 / m.sample_rate() as u64) as u32;
+
+    // and finally run the actual speech to text algorithm,
+    // in overlapping chunks if it's too long to do in one go
+    let mut transcript =
+        transcribe_chunked(m, &audio_buf[speech], &settings.hot_words, max_candidates)?;
+    transcript.shift(offset_ms);
+    Ok(transcript)
+}
+
+/// Run `transcribe_audio` on a blocking thread.
+pub async fn run_stt(
+    input_data: Vec<i16>,
+    format: AudioFormat,
+    m: Arc<dyn SttBackend>,
+    settings: SttSettings,
+    max_candidates: u32,
+) -> Result<Transcript, SttError> {
+    tokio::task::spawn_blocking(move || {
+        transcribe_audio(&input_data, format, &*m, &settings, max_candidates)
+    })
+    .await
+    .expect("Failed to spawn blocking!")
+}
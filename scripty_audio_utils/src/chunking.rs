@@ -0,0 +1,151 @@
+use crate::{Candidate, HotWord, SttBackend, SttError, Token, Transcript, Word};
+use std::ops::Range;
+
+/// Audio longer than this, in milliseconds, is split into overlapping windows before inference.
+/// Inference time and memory use grow faster than linearly with input length for most
+/// backends, so a few short runs are cheaper than one long one.
+pub const WINDOW_MS: u32 = 20_000;
+
+/// How much consecutive windows overlap, in milliseconds.
+/// This needs to be long enough that any word cut off at the end of one window is heard in
+/// full at the start of the next.
+pub const OVERLAP_MS: u32 = 2_000;
+
+// words either side of a seam closer together than this with the same text are the same word
+const DUPLICATE_WINDOW_MS: u32 = 500;
+
+/// Split `len` samples of audio at `sample_rate` into overlapping windows.
+///
+/// Audio that fits in a single window is returned as one range.
+pub fn chunk_windows(len: usize, sample_rate: u32) -> Vec<Range<usize>> {
+    let window = (sample_rate as u64 * WINDOW_MS as u64 / 1000) as usize;
+    let overlap = (sample_rate as u64 * OVERLAP_MS as u64 / 1000) as usize;
+    let step = window - overlap;
+
+    let mut windows = Vec::with_capacity(len / step + 1);
+    let mut start = 0;
+    loop {
+        let end = (start + window).min(len);
+        windows.push(start..end);
+        if end == len {
+            break;
+        }
+        start += step;
+    }
+    windows
+}
+
+/// Run speech-to-text over audio of any length, splitting it into overlapping windows if it's
+/// too long and stitching the results back together.
+///
+/// Only audio short enough for one window gets up to `max_candidates` candidates: there's no
+/// stitching alternatives of different windows together.
+pub fn transcribe_chunked(
+    m: &dyn SttBackend,
+    audio: &[i16],
+    hot_words: &[HotWord],
+    max_candidates: u32,
+) -> Result<Transcript, SttError> {
+    let sample_rate = m.sample_rate();
+    let windows = chunk_windows(audio.len(), sample_rate);
+    if windows.len() == 1 {
+        return m.transcribe(audio, hot_words, max_candidates);
+    }
+
+    let mut chunks = Vec::with_capacity(windows.len());
+    for window in windows {
+        let offset_ms = (window.start as u64 * 1000 / sample_rate as u64) as u32;
+        let mut transcript = m.transcribe(&audio[window], hot_words, 1)?;
+        // raw confidences only mean the same thing for candidates with as many tokens
+        transcript.map_confidence(|c| m.normalized_confidence(c));
+        chunks.push((offset_ms, transcript));
+    }
+    let mut transcript = stitch(&chunks);
+    transcript.map_confidence(|c| m.denormalize_confidence(c.confidence, c));
+    Ok(transcript)
+}
+
+/// Stitch the transcripts of overlapping windows back into one transcript.
+///
+/// `chunks` is a list of each window's offset from the start of the audio, in milliseconds,
+/// and its transcript, in order. Only the best candidate of each window is kept.
+/// Their confidences are averaged, weighted by how long each one is, so they need to be
+/// normalized first (see `SttBackend::normalized_confidence`).
+///
+/// At each seam, words from the earlier window are kept up to the middle of the overlap and
+/// words from the later window after it. A word that was heard in both windows but with
+/// slightly different timings is only kept once.
+pub fn stitch(chunks: &[(u32, Transcript)]) -> Transcript {
+    let mut words: Vec<Word> = Vec::new();
+    let mut confidence = 0.0;
+    let mut confidence_weight = 0.0;
+
+    for (i, (offset_ms, transcript)) in chunks.iter().enumerate() {
+        let candidate = match transcript.best() {
+            Some(c) => c,
+            None => continue,
+        };
+
+        // the seam between this window and the last one is in the middle of their overlap
+        let seam_start = if i == 0 {
+            0
+        } else {
+            offset_ms + OVERLAP_MS / 2
+        };
+        let seam_end = match chunks.get(i + 1) {
+            Some((next_offset, _)) => next_offset + OVERLAP_MS / 2,
+            None => u32::MAX,
+        };
+
+        for mut word in candidate.words() {
+            word.start_ms += offset_ms;
+            word.end_ms += offset_ms;
+            if word.start_ms < seam_start || word.start_ms >= seam_end {
+                continue;
+            }
+            if let Some(last) = words.last() {
+                if last.text.eq_ignore_ascii_case(&word.text)
+                    && word.start_ms.saturating_sub(last.start_ms) < DUPLICATE_WINDOW_MS
+                {
+                    continue;
+                }
+            }
+            words.push(word);
+        }
+
+        let length = candidate.end_ms().saturating_sub(candidate.start_ms()) as f64;
+        confidence += candidate.confidence * length;
+        confidence_weight += length;
+    }
+
+    if words.is_empty() {
+        return Transcript::default();
+    }
+
+    let mut text = String::new();
+    let mut tokens = Vec::with_capacity(words.len());
+    for word in words {
+        let token_text = if text.is_empty() {
+            word.text
+        } else {
+            format!(" {}", word.text)
+        };
+        text.push_str(&token_text);
+        tokens.push(Token {
+            text: token_text,
+            start_ms: word.start_ms,
+            end_ms: word.end_ms,
+        });
+    }
+
+    Transcript::new(vec![Candidate {
+        text,
+        confidence: if confidence_weight > 0.0 {
+            confidence / confidence_weight
+        } else {
+            0.0
+        },
+        tokens,
+        segments: Vec::new(),
+    }])
+}
@@ -1,98 +1,382 @@
-use deepspeech::{errors::DeepspeechError, Metadata, Model as DsModel};
-use scripty_config::BotConfig;
+use crate::{Candidate, HotWord, SttBackend, SttError, SttStream, Token, Transcript};
+use deepspeech::{Metadata, Model as DsModel, Stream as DsStream};
 use std::{
+    lazy::SyncOnceCell as OnceCell,
     path::Path,
-    sync::{Arc, RwLock},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
 };
+use tracing::{info, warn};
 
 // The model has been trained on this specific
 // sample rate. This is in Hz.
 pub const SAMPLE_RATE: u32 = 16_000;
 
-pub struct Model {
+// DeepSpeech reports token positions in timesteps of 20ms each.
+const TIMESTEP_MS: u32 = 20;
+
+/// A set of hot words, as it's looked up: each word and the bits of its boost.
+type HotWordsKey = Vec<(String, u32)>;
+
+/// Used to tell which backend a copy in `BOOSTED_MODELS` belongs to.
+static NEXT_BACKEND_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// Every copy of a model with hot words added, across every backend in this process.
+///
+/// Each copy takes up as much memory as the model it was loaded from, so there's one limit
+/// for all of them instead of one per backend, which would grow with every model and instance.
+static BOOSTED_MODELS: OnceCell<Mutex<BoostedModels>> = OnceCell::new();
+
+fn boosted_models() -> std::sync::MutexGuard<'static, BoostedModels> {
+    BOOSTED_MODELS
+        .get_or_init(|| Mutex::new(BoostedModels::default()))
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+}
+
+/// Copies of models with hot words added, least recently used first.
+#[derive(Default)]
+struct BoostedModels {
+    models: Vec<BoostedModel>,
+    // how much disk space the graphs in `models` take up, which is about what they take up in
+    // memory too
+    bytes: u64,
+}
+
+struct BoostedModel {
+    backend: usize,
+    hot_words: HotWordsKey,
+    model: Arc<DsModel>,
+    bytes: u64,
+}
+
+// the copies are only shared between decodes the same way the model in `DeepSpeechBackend` is
+unsafe impl Send for BoostedModel {}
+
+impl BoostedModels {
+    /// Get the copy `backend` has for `hot_words`, marking it as the most recently used.
+    fn get(&mut self, backend: usize, hot_words: &[(String, u32)]) -> Option<Arc<DsModel>> {
+        let i = self
+            .models
+            .iter()
+            .position(|m| m.backend == backend && m.hot_words == hot_words)?;
+        let entry = self.models.remove(i);
+        let model = Arc::clone(&entry.model);
+        self.models.push(entry);
+        Some(model)
+    }
+
+    /// Add a copy, dropping the least recently used ones until there's at most `max` left.
+    fn insert(&mut self, entry: BoostedModel, max: usize) {
+        self.bytes += entry.bytes;
+        self.models.push(entry);
+        while self.models.len() > max {
+            let dropped = self.models.remove(0);
+            self.bytes -= dropped.bytes;
+        }
+    }
+
+    /// Drop every copy `backend` has.
+    fn remove_backend(&mut self, backend: usize) {
+        let bytes = &mut self.bytes;
+        self.models.retain(|m| {
+            if m.backend == backend {
+                *bytes -= m.bytes;
+            }
+            m.backend != backend
+        });
+    }
+}
+
+pub struct DeepSpeechBackend {
+    id: usize,
+    graph: Box<Path>,
+    scorer: Option<Box<Path>>,
     ds_model: DsModel,
+    // hot words are set on the model itself, not per decode, so each set of hot words gets its
+    // own copy of the model with them added, that way decodes never change a model someone
+    // else is using. copies are loaded the first time their hot words are used, and kept in
+    // `BOOSTED_MODELS` until there are more than this many
+    max_boosted: usize,
 }
 
-// these two impls SHOULD
-unsafe impl Send for Model {}
-unsafe impl Sync for Model {}
+// the model holds raw pointers into libdeepspeech, so it isn't Send or Sync by itself. it's
+// only changed while it's being loaded though, and libdeepspeech can run several decodes on one
+// model at once, so sharing it between threads is fine
+unsafe impl Send for DeepSpeechBackend {}
+unsafe impl Sync for DeepSpeechBackend {}
 
-impl Model {
-    pub fn load_from_files(model_path: &Path) -> Self {
-        Self {
-            ds_model: DsModel::load_from_files(model_path).expect("failed to load model"),
+impl DeepSpeechBackend {
+    /// Load a DeepSpeech model from a directory.
+    ///
+    /// The first `.pb` or `.pbmm` file found is used as the graph (falling back to
+    /// `output_graph.pb`), and the first `.scorer` file found is enabled as the external scorer,
+    /// unless a different `scorer` is given.
+    ///
+    /// Copies of the model with hot words added are shared by every backend, and at most
+    /// `max_boosted` of them are kept loaded. If it's 0, a copy is loaded for every decode that
+    /// uses hot words instead.
+    pub fn load(
+        dir_path: &Path,
+        scorer: Option<&Path>,
+        max_boosted: usize,
+    ) -> Result<Self, SttError> {
+        let mut graph_name: Box<Path> = dir_path.join("output_graph.pb").into_boxed_path();
+        let mut scorer_name: Option<Box<Path>> = None;
+        // search for model in model directory
+        for file in dir_path
+            .read_dir()
+            .map_err(|e| SttError::Load(format!("can't read model dir: {}", e)))?
+            .flatten()
+        {
+            let file_path = file.path();
+            if file_path.is_file() {
+                if let Some(ext) = file_path.extension() {
+                    if ext == "pb" || ext == "pbmm" {
+                        graph_name = file_path.into_boxed_path();
+                    } else if ext == "scorer" {
+                        scorer_name = Some(file_path.into_boxed_path());
+                    }
+                }
+            }
         }
+        // enable external scorer if given or found in the model folder
+        let scorer = scorer.map(Box::from).or(scorer_name);
+        let ds_model = load_model(&graph_name, scorer.as_deref())?;
+
+        Ok(Self {
+            id: NEXT_BACKEND_ID.fetch_add(1, Ordering::Relaxed),
+            graph: graph_name,
+            scorer,
+            ds_model,
+            max_boosted,
+        })
     }
 
-    pub fn speech_to_text(&self, buffer: &[i16]) -> Result<String, DeepspeechError> {
-        self.ds_model.speech_to_text(buffer)
+    /// Get the model to decode with `hot_words`, loading a copy with them added if there
+    /// isn't one yet.
+    fn model_for(&self, hot_words: &[HotWord]) -> Result<ModelRef<'_>, SttError> {
+        if hot_words.is_empty() {
+            return Ok(ModelRef::Base(&self.ds_model));
+        }
+        let key: HotWordsKey = hot_words
+            .iter()
+            .map(|h| (h.word.clone(), h.boost.to_bits()))
+            .collect();
+        if let Some(model) = boosted_models().get(self.id, &key) {
+            return Ok(ModelRef::Boosted(model));
+        }
+
+        // loading takes a while, so other decodes aren't held up by it
+        let mut model = load_model(&self.graph, self.scorer.as_deref())?;
+        for hot_word in hot_words {
+            model
+                .add_hot_word(&hot_word.word, hot_word.boost)
+                .map_err(|e| {
+                    SttError::Decode(format!("couldn't add hot word {}: {}", hot_word.word, e))
+                })?;
+        }
+
+        let model = Arc::new(model);
+        if self.max_boosted == 0 {
+            return Ok(ModelRef::Boosted(model));
+        }
+
+        let bytes = std::fs::metadata(&self.graph).map_or(0, |m| m.len());
+        let mut boosted = boosted_models();
+        // someone else might have loaded the same hot words in the meantime
+        if let Some(model) = boosted.get(self.id, &key) {
+            return Ok(ModelRef::Boosted(model));
+        }
+        boosted.insert(
+            BoostedModel {
+                backend: self.id,
+                hot_words: key,
+                model: Arc::clone(&model),
+                bytes,
+            },
+            self.max_boosted,
+        );
+        info!(
+            "loaded a copy of {} with {} hot word(s), copies with hot words now take up about {} \
+            MiB between {} of them",
+            self.graph.display(),
+            hot_words.len(),
+            boosted.bytes / (1024 * 1024),
+            boosted.models.len()
+        );
+        Ok(ModelRef::Boosted(model))
     }
+}
 
-    pub fn speech_to_text_with_metadata(
-        &self,
-        buffer: &[i16],
-    ) -> Result<Metadata, DeepspeechError> {
-        self.ds_model.speech_to_text_with_metadata(buffer, 1)
+impl Drop for DeepSpeechBackend {
+    fn drop(&mut self) {
+        // copies in use finish their decodes first, they're only freed once those are done
+        boosted_models().remove_backend(self.id);
     }
+}
 
-    pub fn enable_external_scorer(&mut self, scorer_path: &Path) -> Result<(), DeepspeechError> {
-        self.ds_model.enable_external_scorer(scorer_path)
+/// Load the graph, enabling the external scorer if there is one.
+fn load_model(graph: &Path, scorer: Option<&Path>) -> Result<DsModel, SttError> {
+    let mut model = DsModel::load_from_files(graph).map_err(|e| SttError::Load(e.to_string()))?;
+    if let Some(scorer) = scorer {
+        model
+            .enable_external_scorer(scorer)
+            .map_err(|e| SttError::Load(e.to_string()))?;
     }
+    Ok(model)
 }
 
-pub fn load_model() -> Model {
-    let model_dir_str = BotConfig::get()
-        .expect("Failed to load config!")
-        .model_path();
-    let dir_path = Path::new(model_dir_str);
-    let mut graph_name: Box<Path> = dir_path.join("output_graph.pb").into_boxed_path();
-    let mut scorer_name: Option<Box<Path>> = None;
-    // search for model in model directory
-    for file in dir_path
-        .read_dir()
-        .expect("Specified model dir is not a dir")
-        .flatten()
-    {
-        let file_path = file.path();
-        if file_path.is_file() {
-            if let Some(ext) = file_path.extension() {
-                if ext == "pb" || ext == "pbmm" {
-                    graph_name = file_path.into_boxed_path();
-                } else if ext == "scorer" {
-                    scorer_name = Some(file_path.into_boxed_path());
-                }
-            }
+/// The model a decode runs on: the one without hot words, or a copy with some added.
+/// Copies stay loaded while they're in use, even if they're dropped from the backend.
+enum ModelRef<'a> {
+    Base(&'a DsModel),
+    Boosted(Arc<DsModel>),
+}
+
+impl std::ops::Deref for ModelRef<'_> {
+    type Target = DsModel;
+
+    fn deref(&self) -> &DsModel {
+        match self {
+            ModelRef::Base(m) => m,
+            ModelRef::Boosted(m) => m,
         }
     }
-    let mut m = Model::load_from_files(&graph_name);
-    // enable external scorer if found in the model folder
-    if let Some(scorer) = scorer_name {
-        m.enable_external_scorer(&scorer).unwrap();
+}
+
+impl SttBackend for DeepSpeechBackend {
+    fn name(&self) -> &'static str {
+        "deepspeech"
+    }
+
+    fn sample_rate(&self) -> u32 {
+        SAMPLE_RATE
     }
 
-    m
+    fn transcribe(
+        &self,
+        audio: &[i16],
+        hot_words: &[HotWord],
+        max_candidates: u32,
+    ) -> Result<Transcript, SttError> {
+        self.model_for(hot_words)?
+            .speech_to_text_with_metadata(audio, max_candidates.max(1) as _)
+            .map(metadata_to_transcript)
+            .map_err(|e| SttError::Decode(e.to_string()))
+    }
+
+    // DeepSpeech's confidence is a sum of log-probabilities over every token, so it gets lower
+    // the longer a utterance is. averaging it per token and undoing the log gives the
+    // (geometric) mean probability of each token instead
+    fn normalized_confidence(&self, candidate: &Candidate) -> f64 {
+        (candidate.confidence / candidate.tokens.len().max(1) as f64)
+            .exp()
+            .min(1.0)
+    }
+
+    fn denormalize_confidence(&self, normalized: f64, candidate: &Candidate) -> f64 {
+        // a probability of 0 would be a log-probability of negative infinity
+        normalized.max(f64::MIN_POSITIVE).ln() * candidate.tokens.len().max(1) as f64
+    }
+
+    fn supports_streaming(&self) -> bool {
+        true
+    }
+
+    fn create_stream(
+        &self,
+        hot_words: &[HotWord],
+        max_candidates: u32,
+    ) -> Result<Box<dyn SttStream>, SttError> {
+        // streams take a copy of the hot words when they're created
+        let model = self.model_for(hot_words)?;
+        let stream = model
+            .create_stream()
+            .map_err(|e| SttError::Decode(e.to_string()))?;
+        Ok(Box::new(DeepSpeechStream {
+            stream,
+            _model: match model {
+                ModelRef::Base(_) => None,
+                ModelRef::Boosted(m) => Some(m),
+            },
+            max_candidates: max_candidates.max(1),
+        }))
+    }
+}
+
+/// A streaming decode using DeepSpeech's `Stream` API.
+pub struct DeepSpeechStream {
+    stream: DsStream,
+    // the copy of the model with hot words the stream runs on, which has to outlive it
+    _model: Option<Arc<DsModel>>,
+    max_candidates: u32,
+}
+
+// same as above
+unsafe impl Send for DeepSpeechStream {}
+
+impl SttStream for DeepSpeechStream {
+    fn feed_audio(&mut self, audio: &[i16]) -> Result<(), SttError> {
+        self.stream.feed_audio(audio);
+        Ok(())
+    }
+
+    fn intermediate(&mut self) -> Result<Transcript, SttError> {
+        self.stream
+            .intermediate_decode_with_metadata(1)
+            .map(metadata_to_transcript)
+            .map_err(|e| SttError::Decode(e.to_string()))
+    }
+
+    fn finish(self: Box<Self>) -> Result<Transcript, SttError> {
+        self.stream
+            .finish_with_metadata(self.max_candidates as _)
+            .map(metadata_to_transcript)
+            .map_err(|e| SttError::Decode(e.to_string()))
+    }
 }
 
-pub async fn run_stt(
-    input_data: Vec<i16>,
-    m: Arc<RwLock<Model>>,
-) -> Result<Metadata, DeepspeechError> {
-    tokio::task::spawn_blocking(move || {
-        // Start off by converting from stereo audio to mono.
-        let input_data = super::stereo_to_mono(input_data);
-
-        // Then convert from 48KHz to SAMPLE_RATE (usually 16KHz)
-        let audio_buf = super::hz_to_hz(input_data, 48_000_f64, SAMPLE_RATE as f64);
-
-        // load the model from the Arc<RwLock<Model>> passed in
-        // and panic if any other thread panicked while trying to load the model too
-        let model = m
-            .read()
-            .expect("a thread panicked while trying to load the model");
-
-        // and finally run the actual speech to text algorithm
-        model.speech_to_text_with_metadata(&audio_buf)
-    })
-    .await
-    .expect("Failed to spawn blocking!")
+/// Convert DeepSpeech's `Metadata` into a backend-neutral transcript.
+fn metadata_to_transcript(metadata: Metadata) -> Transcript {
+    let candidates = metadata
+        .transcripts()
+        .iter()
+        .map(|t| {
+            let ds_tokens = t.tokens();
+            let mut text = String::new();
+            let mut tokens = Vec::with_capacity(ds_tokens.len());
+            for (i, token) in ds_tokens.iter().enumerate() {
+                let token_text = match token.text() {
+                    Ok(text) => text,
+                    Err(e) => {
+                        warn!("transcription contained invalid UTF-8? {}", e);
+                        continue;
+                    }
+                };
+                text.push_str(token_text);
+
+                // DeepSpeech only gives us the start of each token,
+                // so assume each one lasts until the next one starts
+                let start_ms = token.timestep() * TIMESTEP_MS;
+                let end_ms = ds_tokens
+                    .get(i + 1)
+                    .map_or(start_ms + TIMESTEP_MS, |next| next.timestep() * TIMESTEP_MS);
+                tokens.push(Token {
+                    text: token_text.to_string(),
+                    start_ms,
+                    end_ms,
+                });
+            }
+            Candidate {
+                text,
+                confidence: t.confidence(),
+                tokens,
+                segments: Vec::new(),
+            }
+        })
+        .collect();
+    Transcript::new(candidates)
 }
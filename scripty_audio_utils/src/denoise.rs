@@ -0,0 +1,73 @@
+use nnnoiseless::DenoiseState;
+
+/// The only sample rate the denoiser's model works at.
+pub const DENOISE_SAMPLE_RATE: u32 = 48_000;
+
+/// A streaming noise suppressor for mono 48KHz audio, using the RNNoise model.
+///
+/// It takes out steady background noise like fans, keyboards and music,
+/// which the speech-to-text models would otherwise try to transcribe.
+pub struct Denoiser {
+    state: Box<DenoiseState<'static>>,
+    // input that didn't fill a whole frame yet
+    pending: Vec<f32>,
+    frame: Vec<f32>,
+}
+
+impl Denoiser {
+    pub fn new() -> Self {
+        Self {
+            state: DenoiseState::new(),
+            pending: Vec::with_capacity(DenoiseState::FRAME_SIZE),
+            frame: vec![0.0; DenoiseState::FRAME_SIZE],
+        }
+    }
+
+    /// Push more mono 48KHz audio in, and append any denoised audio it produces to `output`.
+    pub fn process(&mut self, input: &[i16], output: &mut Vec<i16>) {
+        for sample in input {
+            self.pending.push(*sample as f32);
+            if self.pending.len() == DenoiseState::FRAME_SIZE {
+                self.process_pending(output);
+            }
+        }
+    }
+
+    /// Denoise whatever is left over that didn't fill a whole frame, and append it to `output`.
+    /// This should only be called once all input has been pushed.
+    pub fn flush(&mut self, output: &mut Vec<i16>) {
+        let len = self.pending.len();
+        if len == 0 {
+            return;
+        }
+        self.pending.resize(DenoiseState::FRAME_SIZE, 0.0);
+        self.process_pending(output);
+        output.truncate(output.len() - (DenoiseState::FRAME_SIZE - len));
+    }
+
+    fn process_pending(&mut self, output: &mut Vec<i16>) {
+        // the model also gives back how likely the frame is to be speech, but we have our own VAD
+        self.state.process_frame(&mut self.frame, &self.pending);
+        self.pending.clear();
+        output.extend(
+            self.frame
+                .iter()
+                .map(|s| s.round().clamp(i16::MIN as f32, i16::MAX as f32) as i16),
+        );
+    }
+}
+
+impl Default for Denoiser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Denoise a whole buffer of mono 48KHz audio.
+pub fn denoise(input_data: &[i16]) -> Vec<i16> {
+    let mut denoiser = Denoiser::new();
+    let mut output = Vec::with_capacity(input_data.len());
+    denoiser.process(input_data, &mut output);
+    denoiser.flush(&mut output);
+    output
+}
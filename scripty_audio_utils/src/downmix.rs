@@ -0,0 +1,50 @@
+/// How the channels of some audio are laid out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChannelLayout {
+    Mono,
+    /// Interleaved left and right samples: `L R L R ...`
+    Stereo,
+}
+
+impl ChannelLayout {
+    pub const fn channels(self) -> usize {
+        match self {
+            ChannelLayout::Mono => 1,
+            ChannelLayout::Stereo => 2,
+        }
+    }
+}
+
+/// The layout and sample rate of some raw audio.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AudioFormat {
+    pub layout: ChannelLayout,
+    pub sample_rate: u32,
+}
+
+impl AudioFormat {
+    /// What Discord sends us: 48KHz interleaved stereo.
+    pub const DISCORD: AudioFormat = AudioFormat {
+        layout: ChannelLayout::Stereo,
+        sample_rate: 48_000,
+    };
+
+    /// How many samples (across all channels) make up one second of audio in this format.
+    pub const fn samples_per_sec(self) -> usize {
+        self.sample_rate as usize * self.layout.channels()
+    }
+}
+
+/// Mix interleaved audio in `format` down to a single channel, at the same sample rate.
+///
+/// Every frame is averaged across its channels. A trailing partial frame is dropped.
+pub fn downmix_to_mono(input_data: &[i16], format: AudioFormat) -> Vec<i16> {
+    match format.layout {
+        ChannelLayout::Mono => input_data.to_vec(),
+        ChannelLayout::Stereo => input_data
+            .chunks_exact(2)
+            // sum in 32 bits: two loud samples would overflow an i16
+            .map(|frame| ((frame[0] as i32 + frame[1] as i32) / 2) as i16)
+            .collect(),
+    }
+}
@@ -0,0 +1,112 @@
+use crate::{build_cues, to_srt, to_webvtt, Cue, Utterance};
+use std::{fmt::Write, str::FromStr};
+
+/// What to export a session's transcript as.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    Text,
+    Markdown,
+    Srt,
+    WebVtt,
+}
+
+/// Name of every export format, as used in commands and the database.
+pub const EXPORT_FORMATS: [&str; 4] = ["txt", "md", "srt", "vtt"];
+
+impl ExportFormat {
+    /// The name of this format in `EXPORT_FORMATS`, which is also its file extension.
+    pub fn name(self) -> &'static str {
+        match self {
+            ExportFormat::Text => "txt",
+            ExportFormat::Markdown => "md",
+            ExportFormat::Srt => "srt",
+            ExportFormat::WebVtt => "vtt",
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "txt" | "text" => ExportFormat::Text,
+            "md" | "markdown" => ExportFormat::Markdown,
+            "srt" => ExportFormat::Srt,
+            "vtt" | "webvtt" => ExportFormat::WebVtt,
+            _ => return Err(()),
+        })
+    }
+}
+
+/// Format a session's transcript as one document, with `title` as its heading where the
+/// format has one. `entries` must be in order, with timings relative to the session.
+///
+/// Subtitles are split into cues using each utterance's word timings, where it has them.
+pub fn export_transcript(entries: &[Utterance], format: ExportFormat, title: &str) -> String {
+    match format {
+        ExportFormat::Text => {
+            let mut out = format!("{}\n\n", title);
+            for e in entries {
+                let _ = writeln!(out, "[{}] {}: {}", clock(e.start_ms), e.speaker, e.text);
+            }
+            out
+        }
+        ExportFormat::Markdown => {
+            let mut out = format!("# {}\n\n", title);
+            for e in entries {
+                let _ = write!(
+                    out,
+                    "**[{}] {}:** {}\n\n",
+                    clock(e.start_ms),
+                    escape_markdown(&e.speaker),
+                    // redacted words are masked with asterisks, which would turn into italics
+                    escape_markdown(&e.text)
+                );
+            }
+            out
+        }
+        ExportFormat::Srt => to_srt(&cues(entries)),
+        ExportFormat::WebVtt => to_webvtt(&cues(entries)),
+    }
+}
+
+fn cues(entries: &[Utterance]) -> Vec<Cue> {
+    let mut cues = Vec::with_capacity(entries.len());
+    for e in entries {
+        let utterance_cues = if e.words.is_empty() {
+            vec![Cue {
+                text: e.text.clone(),
+                start_ms: e.start_ms,
+                end_ms: e.end_ms,
+            }]
+        } else {
+            build_cues(&e.words)
+        };
+        cues.extend(utterance_cues.into_iter().map(|c| Cue {
+            text: format!("{}: {}", e.speaker, c.text),
+            start_ms: c.start_ms,
+            // players skip cues with no length
+            end_ms: c.end_ms.max(c.start_ms + 1),
+        }));
+    }
+    cues
+}
+
+/// Format `ms` as `HH:MM:SS`.
+fn clock(ms: u32) -> String {
+    let secs = ms / 1000;
+    format!("{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+/// Escape anything Markdown would format.
+fn escape_markdown(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        if matches!(c, '*' | '_' | '`' | '~' | '|' | '[' | ']' | '\\' | '#') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
@@ -1,9 +1,43 @@
-#![feature(slice_as_chunks)]
+#![feature(once_cell)]
 
+mod backend;
+mod chunking;
 mod deepspeech;
-mod interpolate;
-mod stereo_to_mono;
+mod denoise;
+mod downmix;
+mod export;
+mod ogg_opus;
+mod redact;
+mod registry;
+mod resample;
+mod scorer;
+mod settings;
+mod subtitles;
+mod transcript;
+mod utterance;
+mod vad;
+#[cfg(feature = "vosk")]
+mod vosk;
+#[cfg(feature = "whisper")]
+mod whisper;
 
 pub use crate::deepspeech::*;
-pub use interpolate::*;
-pub use stereo_to_mono::*;
+pub use backend::*;
+pub use chunking::*;
+pub use denoise::*;
+pub use downmix::*;
+pub use export::*;
+pub use ogg_opus::*;
+pub use redact::*;
+pub use registry::*;
+pub use resample::*;
+pub use scorer::*;
+pub use settings::*;
+pub use subtitles::*;
+pub use transcript::*;
+pub use utterance::*;
+pub use vad::*;
+#[cfg(feature = "vosk")]
+pub use vosk::*;
+#[cfg(feature = "whisper")]
+pub use whisper::*;
@@ -0,0 +1,149 @@
+use ogg::{PacketWriteEndInfo, PacketWriter};
+use std::io::{self, Write};
+
+/// Opus always runs at 48KHz: timestamps and granule positions are counted in these samples.
+pub const OPUS_SAMPLE_RATE: u32 = 48_000;
+/// Samples at the start of the stream a decoder should throw away. This is the encoder delay of
+/// libopus at 48KHz, which Discord clients encode with.
+pub const OPUS_PRE_SKIP: u16 = 312;
+/// 20ms of silence as a single Opus frame, the same one Discord sends when someone stops talking.
+pub const SILENT_FRAME: [u8; 3] = [0xf8, 0xff, 0xfe];
+const SILENT_FRAME_SAMPLES: u64 = 960;
+/// Pages are ended at least this often, in samples, that way a crash loses at most this much.
+const PAGE_SAMPLES: u64 = OPUS_SAMPLE_RATE as u64;
+
+/// Get how long an Opus packet is, in samples at 48KHz, from its TOC byte.
+///
+/// Returns `None` for packets that aren't valid Opus.
+/// See <https://datatracker.ietf.org/doc/html/rfc6716#section-3.1>.
+pub fn opus_packet_samples(packet: &[u8]) -> Option<u32> {
+    let toc = *packet.first()?;
+    let config = (toc >> 3) as usize;
+    let frame_samples = match config {
+        // SILK: 10, 20, 40 or 60ms
+        0..=11 => [480, 960, 1920, 2880][config % 4],
+        // hybrid: 10 or 20ms
+        12..=15 => [480, 960][config % 2],
+        // CELT: 2.5, 5, 10 or 20ms
+        _ => [120, 240, 480, 960][config % 4],
+    };
+    let frames = match toc & 0b11 {
+        0 => 1,
+        1 | 2 => 2,
+        _ => (*packet.get(1)? & 0b0011_1111) as u32,
+    };
+    let samples = frame_samples * frames;
+    // packets can't be longer than 120ms
+    if frames == 0 || samples > 5_760 {
+        None
+    } else {
+        Some(samples)
+    }
+}
+
+/// Writes Opus packets to an Ogg Opus file as-is, without decoding or re-encoding them.
+pub struct OggOpusWriter<W: Write> {
+    writer: PacketWriter<W>,
+    serial: u32,
+    /// Samples written so far. Granule positions in the file are ahead of this by the pre-skip.
+    granule: u64,
+    /// Where the last page ended, in samples.
+    page_start: u64,
+    /// The last packet written, held back so the stream can be ended on it.
+    pending: Option<(Box<[u8]>, u64)>,
+}
+
+impl<W: Write> OggOpusWriter<W> {
+    /// Start a stream with `channels` channels, writing its headers to `inner`.
+    ///
+    /// `serial` identifies the stream in the file, and should be random.
+    pub fn new(inner: W, serial: u32, channels: u8) -> io::Result<Self> {
+        let mut writer = PacketWriter::new(inner);
+
+        // https://datatracker.ietf.org/doc/html/rfc7845#section-5.1
+        let mut head = Vec::with_capacity(19);
+        head.extend_from_slice(b"OpusHead");
+        head.push(1); // version
+        head.push(channels);
+        head.extend_from_slice(&OPUS_PRE_SKIP.to_le_bytes());
+        head.extend_from_slice(&OPUS_SAMPLE_RATE.to_le_bytes());
+        head.extend_from_slice(&0i16.to_le_bytes()); // output gain
+        head.push(0); // channel mapping family: mono or stereo
+        writer.write_packet(head.into(), serial, PacketWriteEndInfo::EndPage, 0)?;
+
+        // https://datatracker.ietf.org/doc/html/rfc7845#section-5.2
+        let vendor = b"scripty";
+        let mut tags = Vec::with_capacity(8 + 4 + vendor.len() + 4);
+        tags.extend_from_slice(b"OpusTags");
+        tags.extend_from_slice(&(vendor.len() as u32).to_le_bytes());
+        tags.extend_from_slice(vendor);
+        tags.extend_from_slice(&0u32.to_le_bytes()); // no comments
+        writer.write_packet(tags.into(), serial, PacketWriteEndInfo::EndPage, 0)?;
+
+        Ok(Self {
+            writer,
+            serial,
+            granule: 0,
+            page_start: 0,
+            pending: None,
+        })
+    }
+
+    /// How much audio has been written so far, in samples at 48KHz.
+    pub fn granule(&self) -> u64 {
+        self.granule
+    }
+
+    /// Write a single Opus packet.
+    pub fn write_packet(&mut self, packet: &[u8]) -> io::Result<()> {
+        let samples = opus_packet_samples(packet)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid Opus packet"))?;
+        self.push(packet.into(), samples as u64)
+    }
+
+    /// Write silence until `granule` samples have been written, to the nearest 20ms.
+    pub fn write_silence_until(&mut self, granule: u64) -> io::Result<()> {
+        while self.granule + SILENT_FRAME_SAMPLES / 2 < granule {
+            self.push(SILENT_FRAME[..].into(), SILENT_FRAME_SAMPLES)?;
+        }
+        Ok(())
+    }
+
+    fn push(&mut self, packet: Box<[u8]>, samples: u64) -> io::Result<()> {
+        if let Some((last, last_granule)) = self.pending.take() {
+            let end = if last_granule - self.page_start >= PAGE_SAMPLES {
+                self.page_start = last_granule;
+                PacketWriteEndInfo::EndPage
+            } else {
+                PacketWriteEndInfo::NormalPacket
+            };
+            self.writer.write_packet(
+                last,
+                self.serial,
+                end,
+                last_granule + OPUS_PRE_SKIP as u64,
+            )?;
+        }
+        self.granule += samples;
+        self.pending = Some((packet, self.granule));
+        Ok(())
+    }
+
+    /// End the stream and flush it, returning the inner writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        // the stream has to end on a packet, even if nothing was ever said
+        let (last, last_granule) = match self.pending.take() {
+            Some(p) => p,
+            None => (SILENT_FRAME[..].into(), SILENT_FRAME_SAMPLES),
+        };
+        self.writer.write_packet(
+            last,
+            self.serial,
+            PacketWriteEndInfo::EndStream,
+            last_granule + OPUS_PRE_SKIP as u64,
+        )?;
+        let mut inner = self.writer.into_inner();
+        inner.flush()?;
+        Ok(inner)
+    }
+}
@@ -0,0 +1,457 @@
+use crate::Word;
+use std::ops::Range;
+
+/// Which kinds of content to mask in transcripts before they're sent anywhere.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RedactionRules {
+    /// Mask swear words, keeping only their first letter.
+    pub profanity: bool,
+    /// Replace phone numbers with `[phone number]`.
+    pub phone_numbers: bool,
+    /// Replace email addresses with `[email]`.
+    pub emails: bool,
+    /// Replace runs of digits that look like payment card numbers with `[card number]`.
+    pub cards: bool,
+    /// Replace street addresses with `[address]`.
+    pub addresses: bool,
+    /// Extra words to mask like profanity, such as names. These are masked even if
+    /// `profanity` is off.
+    pub words: Vec<String>,
+}
+
+/// Name of every rule, as used in commands and the database.
+pub const REDACTION_RULES: [&str; 5] = ["profanity", "phone", "email", "card", "address"];
+
+impl RedactionRules {
+    /// Whether any rule is enabled, or there are extra words to mask.
+    pub fn is_active(&self) -> bool {
+        self.profanity
+            || self.phone_numbers
+            || self.emails
+            || self.cards
+            || self.addresses
+            || !self.words.is_empty()
+    }
+
+    /// Get a rule's toggle by its name in `REDACTION_RULES`.
+    pub fn rule_mut(&mut self, name: &str) -> Option<&mut bool> {
+        Some(match name {
+            "profanity" => &mut self.profanity,
+            "phone" => &mut self.phone_numbers,
+            "email" => &mut self.emails,
+            "card" => &mut self.cards,
+            "address" => &mut self.addresses,
+            _ => return None,
+        })
+    }
+
+    /// Get the names of every enabled rule.
+    pub fn enabled(&self) -> Vec<&'static str> {
+        let flags = [
+            self.profanity,
+            self.phone_numbers,
+            self.emails,
+            self.cards,
+            self.addresses,
+        ];
+        REDACTION_RULES
+            .iter()
+            .zip(flags.iter())
+            .filter(|(_, on)| **on)
+            .map(|(name, _)| *name)
+            .collect()
+    }
+
+    /// Build rules from the names of the enabled ones. Unknown names are ignored.
+    pub fn from_enabled<S: AsRef<str>>(names: &[S], words: Vec<String>) -> Self {
+        let mut rules = Self {
+            words,
+            ..Default::default()
+        };
+        for name in names {
+            if let Some(rule) = rules.rule_mut(name.as_ref()) {
+                *rule = true;
+            }
+        }
+        rules
+    }
+}
+
+// kept short on purpose: guilds that need more can add their own words
+const PROFANITY: &[&str] = &[
+    "arse",
+    "arsehole",
+    "ass",
+    "asshole",
+    "bastard",
+    "bitch",
+    "bitches",
+    "bollocks",
+    "bullshit",
+    "cock",
+    "crap",
+    "cunt",
+    "damn",
+    "dick",
+    "dickhead",
+    "fuck",
+    "fucked",
+    "fucker",
+    "fuckers",
+    "fucking",
+    "fucks",
+    "motherfucker",
+    "piss",
+    "pissed",
+    "prick",
+    "pussy",
+    "shit",
+    "shits",
+    "shitty",
+    "slut",
+    "twat",
+    "wanker",
+    "whore",
+];
+
+const DIGIT_WORDS: &[&str] = &[
+    "zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine",
+];
+
+// spelled out numbers that can start a house number, on top of the digits
+const NUMBER_WORDS: &[&str] = &[
+    "zero",
+    "one",
+    "two",
+    "three",
+    "four",
+    "five",
+    "six",
+    "seven",
+    "eight",
+    "nine",
+    "ten",
+    "eleven",
+    "twelve",
+    "thirteen",
+    "fourteen",
+    "fifteen",
+    "sixteen",
+    "seventeen",
+    "eighteen",
+    "nineteen",
+    "twenty",
+    "thirty",
+    "forty",
+    "fifty",
+    "sixty",
+    "seventy",
+    "eighty",
+    "ninety",
+    "hundred",
+    "thousand",
+];
+
+const STREET_SUFFIXES: &[&str] = &[
+    "street",
+    "st",
+    "avenue",
+    "ave",
+    "road",
+    "rd",
+    "lane",
+    "ln",
+    "drive",
+    "dr",
+    "boulevard",
+    "blvd",
+    "court",
+    "ct",
+    "way",
+    "place",
+    "pl",
+    "terrace",
+    "crescent",
+    "close",
+    "square",
+    "highway",
+    "parkway",
+];
+
+const PHONE_DIGITS: std::ops::RangeInclusive<usize> = 7..=15;
+const CARD_DIGITS: std::ops::RangeInclusive<usize> = 13..=19;
+
+/// Mask everything `rules` asks for in `text`.
+///
+/// Speech-to-text output has numbers and emails both written out (`555-0134`,
+/// `jo@example.com`) and spelled out (`five five five`, `jo at example dot com`) depending on
+/// the backend, so both are recognized. Whitespace between words is collapsed to single spaces.
+pub fn redact(text: &str, rules: &RedactionRules) -> String {
+    if !rules.is_active() {
+        return text.to_string();
+    }
+
+    let words: Vec<&str> = text.split_whitespace().collect();
+    redact_spans(&words, rules)
+        .into_iter()
+        .map(|(_, w)| w)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Mask everything `rules` asks for in a list of timed words, the same way `redact` does.
+///
+/// Anything that spans several words, like a phone number, becomes one word covering all of
+/// their time, so the timings still line up with what was said.
+pub fn redact_words(words: &[Word], rules: &RedactionRules) -> Vec<Word> {
+    if !rules.is_active() {
+        return words.to_vec();
+    }
+
+    let text: Vec<&str> = words.iter().map(|w| w.text.as_str()).collect();
+    redact_spans(&text, rules)
+        .into_iter()
+        .map(|(span, text)| Word {
+            text,
+            start_ms: words[span.start].start_ms,
+            end_ms: words[span.end - 1].end_ms,
+        })
+        .collect()
+}
+
+/// Mask `words`, returning what each span of them was replaced with.
+/// Words that aren't masked as part of something longer are their own span.
+fn redact_spans(words: &[&str], rules: &RedactionRules) -> Vec<(Range<usize>, String)> {
+    let mut out = Vec::with_capacity(words.len());
+    let mut i = 0;
+    while i < words.len() {
+        match span_at(words, i, rules) {
+            Some((n, tag)) => {
+                // keep punctuation after the span, so sentences still end properly
+                let (_, _, suffix) = split_punctuation(words[i + n - 1]);
+                out.push((i..i + n, format!("{}{}", tag, suffix)));
+                i += n;
+            }
+            None => {
+                // a run of digits that isn't masked as a whole shouldn't be masked in parts,
+                // like the last 12 digits of a 16 digit serial number
+                let n = if rules.phone_numbers || rules.cards {
+                    digits_at(words, i).map_or(1, |(n, _)| n)
+                } else {
+                    1
+                };
+                out.extend((i..i + n).map(|j| (j..j + 1, mask_word(words[j], rules))));
+                i += n;
+            }
+        }
+    }
+    out
+}
+
+/// If something `rules` asks to mask starts at `words[i]`, get how many words it spans and
+/// what to replace it with.
+fn span_at(words: &[&str], i: usize, rules: &RedactionRules) -> Option<(usize, &'static str)> {
+    if rules.emails {
+        if let Some(n) = email_at(words, i) {
+            return Some((n, "[email]"));
+        }
+    }
+    // before phone numbers and cards, since addresses start with a number too
+    if rules.addresses {
+        if let Some(n) = address_at(words, i) {
+            return Some((n, "[address]"));
+        }
+    }
+    if rules.phone_numbers || rules.cards {
+        let (n, digits) = digits_at(words, i)?;
+        if rules.cards && CARD_DIGITS.contains(&digits.len()) && luhn(&digits) {
+            return Some((n, "[card number]"));
+        }
+        if rules.phone_numbers && PHONE_DIGITS.contains(&digits.len()) {
+            return Some((n, "[phone number]"));
+        }
+    }
+    None
+}
+
+/// Split leading and trailing punctuation off a word.
+fn split_punctuation(word: &str) -> (&str, &str, &str) {
+    let is_punctuation = |c: char| !c.is_alphanumeric();
+    let start = word.len() - word.trim_start_matches(is_punctuation).len();
+    let end = word.trim_end_matches(is_punctuation).len().max(start);
+    (&word[..start], &word[start..end], &word[end..])
+}
+
+fn normalize(word: &str) -> String {
+    split_punctuation(word).1.to_lowercase()
+}
+
+/// Mask `word` if it's profanity or one of the extra words, keeping its first letter.
+fn mask_word(word: &str, rules: &RedactionRules) -> String {
+    let (prefix, core, suffix) = split_punctuation(word);
+    let lower = core.to_lowercase();
+    let masked = (rules.profanity && PROFANITY.contains(&lower.as_str()))
+        || rules.words.iter().any(|w| w.to_lowercase() == lower);
+    if !masked {
+        return word.to_string();
+    }
+    let mut chars = core.chars();
+    let first = chars.next().map(String::from).unwrap_or_default();
+    format!("{}{}{}{}", prefix, first, "*".repeat(chars.count()), suffix)
+}
+
+/// If a run of digits starts at `words[i]`, get how many words it spans and the digits in it.
+///
+/// Digits can be written (`555-0134`, `(555)`) or spelled out (`five five five`), including
+/// `oh` for zero and `double`/`triple` repeating the next digit.
+fn digits_at(words: &[&str], i: usize) -> Option<(usize, Vec<u8>)> {
+    let mut digits = Vec::new();
+    let mut n = 0;
+    let mut repeat = 1;
+    for word in &words[i..] {
+        let word = word.trim_matches(|c: char| matches!(c, ',' | '.' | '?' | '!' | ':' | ';'));
+        let lower = word.to_lowercase();
+        if !word.is_empty()
+            && word.chars().any(|c| c.is_ascii_digit())
+            && word
+                .chars()
+                .all(|c| c.is_ascii_digit() || matches!(c, '-' | '(' | ')' | '+' | '.'))
+        {
+            digits.extend(word.bytes().filter(u8::is_ascii_digit).map(|b| b - b'0'));
+        } else if let Some(d) = DIGIT_WORDS.iter().position(|w| *w == lower) {
+            digits.extend(std::iter::repeat(d as u8).take(repeat));
+        } else if lower == "oh" && !digits.is_empty() {
+            digits.extend(std::iter::repeat(0).take(repeat));
+        } else if (lower == "double" || lower == "triple") && repeat == 1 {
+            repeat = if lower == "double" { 2 } else { 3 };
+            n += 1;
+            continue;
+        } else {
+            break;
+        }
+        repeat = 1;
+        n += 1;
+    }
+    // a trailing "double" without a digit after it isn't part of the number
+    if repeat > 1 {
+        n -= 1;
+    }
+    if digits.is_empty() {
+        None
+    } else {
+        Some((n, digits))
+    }
+}
+
+/// Check a card number's checksum, so long runs of other digits aren't masked as cards.
+fn luhn(digits: &[u8]) -> bool {
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, d)| {
+            let d = *d as u32;
+            if i % 2 == 1 {
+                let d = d * 2;
+                if d > 9 {
+                    d - 9
+                } else {
+                    d
+                }
+            } else {
+                d
+            }
+        })
+        .sum();
+    sum % 10 == 0
+}
+
+/// If a email address starts at `words[i]`, get how many words it spans.
+fn email_at(words: &[&str], i: usize) -> Option<usize> {
+    // written out: jo@example.com
+    let (_, core, _) = split_punctuation(words[i]);
+    if let Some(at) = core.find('@') {
+        if at > 0 && core[at + 1..].contains('.') {
+            return Some(1);
+        }
+    }
+
+    // spelled out: jo dot smith at example dot com
+    let is_part = |w: &str| {
+        let w = normalize(w);
+        !w.is_empty() && w != "at" && w != "dot"
+    };
+    let mut n = 0;
+    // local part
+    loop {
+        if !is_part(words.get(i + n)?) {
+            return None;
+        }
+        n += 1;
+        if normalize(words.get(i + n)?) == "dot" {
+            n += 1;
+        } else {
+            break;
+        }
+    }
+    if normalize(words.get(i + n)?) != "at" {
+        return None;
+    }
+    n += 1;
+    // domain, which needs at least one dot in it
+    let mut dotted = false;
+    loop {
+        let part = words.get(i + n)?;
+        if !is_part(part) {
+            return None;
+        }
+        dotted |= split_punctuation(part).1.contains('.');
+        n += 1;
+        match words.get(i + n) {
+            Some(w)
+                if normalize(w) == "dot" && words.get(i + n + 1).map_or(false, |w| is_part(w)) =>
+            {
+                dotted = true;
+                n += 1;
+            }
+            _ => break,
+        }
+    }
+    if dotted {
+        Some(n)
+    } else {
+        None
+    }
+}
+
+/// If a street address starts at `words[i]`, get how many words it spans.
+///
+/// This looks for a house number, up to three words of street name and a street suffix,
+/// like `221 baker street` or `twelve oak lane`.
+fn address_at(words: &[&str], i: usize) -> Option<usize> {
+    let is_number = |w: &str| {
+        let w = normalize(w);
+        (!w.is_empty() && w.len() <= 5 && w.chars().all(|c| c.is_ascii_digit()))
+            || NUMBER_WORDS.contains(&w.as_str())
+    };
+    let mut n = 0;
+    while n < 4 && words.get(i + n).map_or(false, |w| is_number(w)) {
+        n += 1;
+    }
+    if n == 0 {
+        return None;
+    }
+    for name_words in 1..=3 {
+        let suffix = normalize(words.get(i + n + name_words)?);
+        let name_ok = words[i + n..i + n + name_words]
+            .iter()
+            .all(|w| split_punctuation(w).1.chars().all(char::is_alphabetic));
+        if !name_ok {
+            return None;
+        }
+        if STREET_SUFFIXES.contains(&suffix.as_str()) {
+            return Some(n + name_words + 1);
+        }
+    }
+    None
+}
@@ -0,0 +1,304 @@
+use crate::{load_backend, Candidate, HotWord, SttBackend, SttError, SttStream, Transcript};
+use scripty_config::BotConfig;
+use std::{
+    collections::HashMap,
+    fmt,
+    lazy::SyncOnceCell as OnceCell,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex, RwLock,
+    },
+};
+use tracing::info;
+
+/// Every model loaded in this process, keyed by what it was loaded from.
+static MODEL_REGISTRY: OnceCell<ModelRegistry> = OnceCell::new();
+
+/// What a model in the registry was loaded from: the model for a language, and optionally a
+/// custom scorer to use in place of the one in the model's directory.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ModelKey {
+    pub language: String,
+    pub scorer: Option<PathBuf>,
+}
+
+impl ModelKey {
+    /// The key for the plain model for `language`.
+    pub fn language(language: impl Into<String>) -> Self {
+        Self {
+            language: language.into(),
+            scorer: None,
+        }
+    }
+}
+
+impl fmt::Display for ModelKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.scorer {
+            Some(scorer) => write!(f, "{} (scorer {})", self.language, scorer.display()),
+            None => f.write_str(&self.language),
+        }
+    }
+}
+
+/// Loads each model once and hands out shared handles to it,
+/// so voice connections don't each keep their own copy in memory.
+pub struct ModelRegistry {
+    pools: RwLock<HashMap<ModelKey, Arc<ModelPool>>>,
+    // held while loading, so two connections joining at once don't both load the same model.
+    // this is separate from `pools` so models that are already loaded can be used meanwhile
+    loading: Mutex<()>,
+}
+
+impl ModelRegistry {
+    /// Get the process-wide registry.
+    pub fn global() -> &'static ModelRegistry {
+        MODEL_REGISTRY.get_or_init(|| ModelRegistry {
+            pools: RwLock::new(HashMap::new()),
+            loading: Mutex::new(()),
+        })
+    }
+
+    /// Get the model for `key` if it's loaded already.
+    pub fn get(&self, key: &ModelKey) -> Option<Arc<ModelPool>> {
+        self.pools
+            .read()
+            .expect("poisoned")
+            .get(key)
+            .map(Arc::clone)
+    }
+
+    /// Get the model for `key`, loading it with the settings in `config` if it isn't yet.
+    pub fn get_or_load(
+        &self,
+        config: &BotConfig,
+        key: &ModelKey,
+    ) -> Result<Arc<ModelPool>, SttError> {
+        if let Some(pool) = self.get(key) {
+            return Ok(pool);
+        }
+
+        let _loading = self.loading.lock().expect("poisoned");
+        if let Some(pool) = self.get(key) {
+            return Ok(pool);
+        }
+        self.load(config, key)
+    }
+
+    /// Drop the registry's handle to the model for `key`, so it's freed once nothing uses it.
+    ///
+    /// Returns whether it was loaded.
+    pub fn remove(&self, key: &ModelKey) -> bool {
+        self.pools.write().expect("poisoned").remove(key).is_some()
+    }
+
+    /// Load every model in the registry from disk again, and swap the new copies in.
+    ///
+    /// Every model is loaded before any of them are swapped in, so if one fails to load none of
+    /// them are, and every failure is reported together. Decodes already running finish on the
+    /// old copy, which is freed once they're done. Models with a custom scorer that's been
+    /// deleted since are skipped. Returns how many were reloaded.
+    pub fn reload(&self, config: &BotConfig) -> Result<usize, SttError> {
+        let _loading = self.loading.lock().expect("poisoned");
+        let keys: Vec<ModelKey> = self
+            .pools
+            .read()
+            .expect("poisoned")
+            .keys()
+            .cloned()
+            .collect();
+
+        // everything else can keep using the old copies until the new ones are loaded
+        let mut pools = Vec::with_capacity(keys.len());
+        let mut errors = Vec::new();
+        for key in keys {
+            if let Some(scorer) = key.scorer.as_deref().filter(|s| !s.is_file()) {
+                // the guild switched scorers or removed theirs, so it's about to be unloaded
+                info!("not reloading {}, {} is gone", key, scorer.display());
+                continue;
+            }
+            match Self::load_pool(config, &key) {
+                Ok(pool) => pools.push((key, pool)),
+                Err(e) => errors.push(format!("{}: {}", key, e)),
+            }
+        }
+        if !errors.is_empty() {
+            return Err(SttError::Load(errors.join("; ")));
+        }
+
+        let reloaded = pools.len();
+        self.pools.write().expect("poisoned").extend(pools);
+        Ok(reloaded)
+    }
+
+    // callers must hold `loading`
+    fn load(&self, config: &BotConfig, key: &ModelKey) -> Result<Arc<ModelPool>, SttError> {
+        let pool = Self::load_pool(config, key)?;
+        self.pools
+            .write()
+            .expect("poisoned")
+            .insert(key.clone(), Arc::clone(&pool));
+        Ok(pool)
+    }
+
+    fn load_pool(config: &BotConfig, key: &ModelKey) -> Result<Arc<ModelPool>, SttError> {
+        // custom scorers are only used by a single guild each, so they get a single instance
+        let count = if key.scorer.is_some() {
+            1
+        } else {
+            config.inference_instances()
+        };
+        ModelPool::load(config, key, count).map(Arc::new)
+    }
+}
+
+/// One instance of a model, and how many decodes are running on it right now.
+struct Instance {
+    backend: Arc<dyn SttBackend>,
+    in_use: AtomicUsize,
+}
+
+/// Marks a instance as busy until dropped.
+struct InUse(Arc<Instance>);
+
+impl InUse {
+    fn new(instance: &Arc<Instance>) -> Self {
+        instance.in_use.fetch_add(1, Ordering::AcqRel);
+        Self(Arc::clone(instance))
+    }
+}
+
+impl Drop for InUse {
+    fn drop(&mut self) {
+        self.0.in_use.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// Some number of identical instances of one model.
+///
+/// Each decode goes to whichever instance is least busy, so backends that can only run one
+/// decode at a time (like whisper) can still decode several utterances in parallel.
+pub struct ModelPool {
+    instances: Vec<Arc<Instance>>,
+}
+
+impl ModelPool {
+    /// Load `count` instances of the model `config` has for `key`.
+    pub fn load(config: &BotConfig, key: &ModelKey, count: u16) -> Result<Self, SttError> {
+        let model_dir = Path::new(config.model_dir(&key.language).ok_or_else(|| {
+            SttError::Load(format!(
+                "there's no model for the language `{}`",
+                key.language
+            ))
+        })?);
+        let instances = (0..count.max(1))
+            .map(|_| {
+                load_backend(config, model_dir, &key.language, key.scorer.as_deref()).map(
+                    |backend| {
+                        Arc::new(Instance {
+                            backend,
+                            in_use: AtomicUsize::new(0),
+                        })
+                    },
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        info!(
+            "loaded {} instance(s) of the {} {} model in {}",
+            instances.len(),
+            key,
+            instances[0].backend.name(),
+            model_dir.display()
+        );
+        Ok(Self { instances })
+    }
+
+    /// How many instances of the model are loaded.
+    pub fn instances(&self) -> usize {
+        self.instances.len()
+    }
+
+    fn least_busy(&self) -> InUse {
+        let instance = self
+            .instances
+            .iter()
+            .min_by_key(|i| i.in_use.load(Ordering::Acquire))
+            .expect("pools always have at least one instance");
+        InUse::new(instance)
+    }
+}
+
+impl SttBackend for ModelPool {
+    fn name(&self) -> &'static str {
+        self.instances[0].backend.name()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.instances[0].backend.sample_rate()
+    }
+
+    fn transcribe(
+        &self,
+        audio: &[i16],
+        hot_words: &[HotWord],
+        max_candidates: u32,
+    ) -> Result<Transcript, SttError> {
+        let instance = self.least_busy();
+        instance
+            .0
+            .backend
+            .transcribe(audio, hot_words, max_candidates)
+    }
+
+    fn normalized_confidence(&self, candidate: &Candidate) -> f64 {
+        self.instances[0].backend.normalized_confidence(candidate)
+    }
+
+    fn denormalize_confidence(&self, normalized: f64, candidate: &Candidate) -> f64 {
+        self.instances[0]
+            .backend
+            .denormalize_confidence(normalized, candidate)
+    }
+
+    fn supports_streaming(&self) -> bool {
+        self.instances[0].backend.supports_streaming()
+    }
+
+    fn create_stream(
+        &self,
+        hot_words: &[HotWord],
+        max_candidates: u32,
+    ) -> Result<Box<dyn SttStream>, SttError> {
+        let instance = self.least_busy();
+        let stream = instance
+            .0
+            .backend
+            .create_stream(hot_words, max_candidates)?;
+        Ok(Box::new(PooledStream {
+            stream,
+            _instance: instance,
+        }))
+    }
+}
+
+/// A stream on one instance of a pool, which counts as busy for as long as the stream lives.
+struct PooledStream {
+    stream: Box<dyn SttStream>,
+    _instance: InUse,
+}
+
+impl SttStream for PooledStream {
+    fn feed_audio(&mut self, audio: &[i16]) -> Result<(), SttError> {
+        self.stream.feed_audio(audio)
+    }
+
+    fn intermediate(&mut self) -> Result<Transcript, SttError> {
+        self.stream.intermediate()
+    }
+
+    fn finish(self: Box<Self>) -> Result<Transcript, SttError> {
+        let PooledStream { stream, _instance } = *self;
+        stream.finish()
+    }
+}
@@ -0,0 +1,166 @@
+use std::f64::consts::PI;
+
+/// Stopband attenuation of the anti-aliasing filter, in dB.
+const STOPBAND_ATTENUATION_DB: f64 = 80.0;
+
+/// Where the passband ends, as a fraction of the output Nyquist frequency.
+/// Everything between this and Nyquist is the filter's transition band.
+const PASSBAND_EDGE: f64 = 0.85;
+
+/// A streaming polyphase resampler with a Kaiser-windowed sinc anti-aliasing filter.
+///
+/// Converts between any two integer sample rates. Audio can be pushed in chunks of any size,
+/// and the output is the same as if it was all pushed in at once.
+pub struct Resampler {
+    // interpolation and decimation factors: the output rate is `input * up / down`
+    up: usize,
+    down: usize,
+    // filter taps, split into `up` phases of `taps_per_phase` taps each.
+    // each phase is stored reversed, that way it can be multiplied straight against the input
+    phases: Vec<Vec<f32>>,
+    taps_per_phase: usize,
+    // input samples not fully consumed yet, including `taps_per_phase - 1` samples of history
+    buffer: Vec<f32>,
+    // position of the next output sample, at the upsampled rate, relative to `buffer[0]`
+    position: usize,
+}
+
+impl Resampler {
+    /// Create a new resampler converting from `source_hz` to `target_hz`.
+    ///
+    /// # Panics
+    /// Panics if either rate is zero.
+    pub fn new(source_hz: u32, target_hz: u32) -> Self {
+        assert!(
+            source_hz > 0 && target_hz > 0,
+            "sample rates must be nonzero"
+        );
+        let g = gcd(source_hz as usize, target_hz as usize);
+        let up = target_hz as usize / g;
+        let down = source_hz as usize / g;
+
+        // design the filter at the upsampled rate, with the cutoff in cycles per sample
+        let factor = up.max(down) as f64;
+        let nyquist = 0.5 / factor;
+        let transition = nyquist * (1.0 - PASSBAND_EDGE);
+        let cutoff = nyquist * PASSBAND_EDGE + transition / 2.0;
+
+        // Kaiser's formulas for the window shape and the length needed for the attenuation
+        let beta = 0.1102 * (STOPBAND_ATTENUATION_DB - 8.7);
+        let min_taps = ((STOPBAND_ATTENUATION_DB - 8.0) / (2.285 * 2.0 * PI * transition)).ceil();
+        let taps_per_phase = (min_taps as usize + up - 1) / up + 1;
+        let len = taps_per_phase * up;
+
+        // keep the centre on a whole tap, so the filter's delay can be cancelled out exactly
+        let centre = (len - 1) / 2;
+        let i0_beta = bessel_i0(beta);
+        let taps: Vec<f64> = (0..len)
+            .map(|n| {
+                let x = n as f64 - centre as f64;
+                let r = x / centre as f64;
+                if r.abs() > 1.0 {
+                    return 0.0;
+                }
+                let sinc = if x == 0.0 {
+                    2.0 * cutoff
+                } else {
+                    (2.0 * PI * cutoff * x).sin() / (PI * x)
+                };
+                let window = bessel_i0(beta * (1.0 - r * r).max(0.0).sqrt()) / i0_beta;
+                // gain of `up` makes up for the zeros inserted when upsampling
+                sinc * window * up as f64
+            })
+            .collect();
+
+        let phases = (0..up)
+            .map(|p| {
+                (0..taps_per_phase)
+                    .rev()
+                    .map(|j| taps[p + j * up] as f32)
+                    .collect()
+            })
+            .collect();
+
+        Self {
+            up,
+            down,
+            phases,
+            taps_per_phase,
+            buffer: vec![0.0; taps_per_phase - 1],
+            // the filter delays everything by `centre` samples:
+            // starting that far in keeps the output lined up with the input
+            position: (taps_per_phase - 1) * up + centre,
+        }
+    }
+
+    /// Push more input in and append any output it produces to `output`.
+    pub fn process(&mut self, input: &[i16], output: &mut Vec<i16>) {
+        self.buffer.extend(input.iter().map(|s| *s as f32));
+        output.reserve(input.len() * self.up / self.down + 1);
+
+        while self.position / self.up < self.buffer.len() {
+            let index = self.position / self.up;
+            let phase = &self.phases[self.position % self.up];
+            let history = &self.buffer[index + 1 - self.taps_per_phase..=index];
+            let sample: f32 = phase.iter().zip(history).map(|(h, x)| h * x).sum();
+            self.position += self.down;
+            output.push(sample.round().clamp(i16::MIN as f32, i16::MAX as f32) as i16);
+        }
+
+        // drop input that no future output sample will need
+        let consumed = (self.position / self.up + 1).saturating_sub(self.taps_per_phase);
+        if consumed > 0 {
+            self.buffer.drain(..consumed);
+            self.position -= consumed * self.up;
+        }
+    }
+
+    /// Push enough silence through to get the output for the last of the input out,
+    /// and append it to `output`. This should only be called once all input has been pushed.
+    pub fn flush(&mut self, output: &mut Vec<i16>) {
+        let tail = vec![0; self.taps_per_phase];
+        self.process(&tail, output);
+    }
+}
+
+/// Resample a whole buffer of mono audio from `source_hz` to `target_hz`.
+pub fn hz_to_hz(input_data: &[i16], source_hz: u32, target_hz: u32) -> Vec<i16> {
+    if source_hz == target_hz {
+        return input_data.to_vec();
+    }
+    let mut resampler = Resampler::new(source_hz, target_hz);
+    let mut output = Vec::new();
+    resampler.process(input_data, &mut output);
+    resampler.flush(&mut output);
+
+    // flushing overshoots a little: cut it back to exactly the expected length
+    let expected = ((input_data.len() as u64 * target_hz as u64 + source_hz as u64 - 1)
+        / source_hz as u64) as usize;
+    output.truncate(expected);
+    output
+}
+
+fn gcd(mut a: usize, mut b: usize) -> usize {
+    while b != 0 {
+        let t = b;
+        b = a % b;
+        a = t;
+    }
+    a
+}
+
+/// Zeroth order modified Bessel function of the first kind, used for the Kaiser window.
+fn bessel_i0(x: f64) -> f64 {
+    let mut sum = 1.0;
+    let mut term = 1.0;
+    let half_x = x / 2.0;
+    for k in 1..50 {
+        term *= half_x / k as f64;
+        let squared = term * term;
+        sum += squared;
+        if squared < sum * 1e-12 {
+            break;
+        }
+    }
+    sum
+}
@@ -0,0 +1,161 @@
+use crate::SttError;
+use scripty_config::BotConfig;
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+use tracing::info;
+
+/// Only this many of the most common words in a corpus make it into the vocabulary.
+const MAX_VOCAB: usize = 500_000;
+/// Order of the n-gram model KenLM builds.
+const LM_ORDER: &str = "5";
+// alpha and beta DeepSpeech's own scorers were tuned to, which are a sane start for custom ones
+const DEFAULT_ALPHA: &str = "0.931289039105002";
+const DEFAULT_BETA: &str = "1.1834137581510284";
+
+/// Build a KenLM scorer for the `language` model from the text corpus at `corpus`.
+///
+/// Everything is written to `out_dir`, and the path of the finished scorer is returned.
+/// This runs `lmplz`, `build_binary` and `generate_scorer_package` from the `scorer_tools`
+/// directory, and can take minutes for big corpora, so only call it on a blocking thread.
+pub fn build_scorer(
+    config: &BotConfig,
+    language: &str,
+    corpus: &Path,
+    out_dir: &Path,
+) -> Result<PathBuf, SttError> {
+    let tools =
+        Path::new(config.scorer_tools().ok_or_else(|| {
+            SttError::Scorer("`scorer_tools` isn't set in the config".to_string())
+        })?);
+    let model_dir = Path::new(config.model_dir(language).ok_or_else(|| {
+        SttError::Scorer(format!("there's no model for the language `{}`", language))
+    })?);
+    let alphabet_path = model_dir.join("alphabet.txt");
+    let alphabet = read_alphabet(&alphabet_path)?;
+
+    fs::create_dir_all(out_dir).map_err(|e| scorer_err("can't create output dir", e))?;
+    let text = fs::read_to_string(corpus).map_err(|e| scorer_err("can't read corpus", e))?;
+    let (lines, vocab) = normalize_corpus(&text, &alphabet);
+    if vocab.is_empty() {
+        return Err(SttError::Scorer(
+            "the corpus doesn't have any words in the model's alphabet".to_string(),
+        ));
+    }
+
+    let text_path = out_dir.join("corpus.normalized.txt");
+    let vocab_path = out_dir.join("vocab.txt");
+    let arpa_path = out_dir.join("lm.arpa");
+    let binary_path = out_dir.join("lm.binary");
+    let scorer_path = out_dir.join("kenlm.scorer");
+    fs::write(&text_path, lines.join("\n")).map_err(|e| scorer_err("can't write corpus", e))?;
+    fs::write(&vocab_path, vocab.join(" ")).map_err(|e| scorer_err("can't write vocab", e))?;
+
+    run(Command::new(tools.join("lmplz"))
+        .args(&["--order", LM_ORDER, "--discount_fallback", "--text"])
+        .arg(&text_path)
+        .arg("--arpa")
+        .arg(&arpa_path)
+        .arg("--limit_vocab_file")
+        .arg(&vocab_path))?;
+    run(Command::new(tools.join("build_binary"))
+        .args(&["-a", "255", "-q", "8", "-v", "trie"])
+        .arg(&arpa_path)
+        .arg(&binary_path))?;
+    run(Command::new(tools.join("generate_scorer_package"))
+        .arg("--alphabet")
+        .arg(&alphabet_path)
+        .arg("--lm")
+        .arg(&binary_path)
+        .arg("--vocab")
+        .arg(&vocab_path)
+        .arg("--package")
+        .arg(&scorer_path)
+        .args(&[
+            "--default_alpha",
+            DEFAULT_ALPHA,
+            "--default_beta",
+            DEFAULT_BETA,
+        ]))?;
+
+    // the intermediate files are bigger than the scorer, and useless once it's built
+    for path in &[&text_path, &arpa_path, &binary_path] {
+        let _ = fs::remove_file(path);
+    }
+    info!(
+        "built a {} scorer with {} words in {}",
+        language,
+        vocab.len(),
+        scorer_path.display()
+    );
+    Ok(scorer_path)
+}
+
+/// Read a DeepSpeech alphabet file: one character per line, with `#` starting comments.
+fn read_alphabet(path: &Path) -> Result<HashSet<char>, SttError> {
+    let alphabet = fs::read_to_string(path).map_err(|e| scorer_err("can't read alphabet", e))?;
+    Ok(alphabet
+        .lines()
+        .filter(|l| !l.starts_with('#'))
+        .filter_map(|l| {
+            let mut chars = l.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Some(c),
+                _ => None,
+            }
+        })
+        .collect())
+}
+
+/// Lowercase `text` and drop every character the model can't output, then split it into
+/// sentences (one per line) and a vocabulary of the most common words.
+fn normalize_corpus(text: &str, alphabet: &HashSet<char>) -> (Vec<String>, Vec<String>) {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    let lines: Vec<String> = text
+        .lines()
+        .map(|line| {
+            line.to_lowercase()
+                .chars()
+                .map(|c| if alphabet.contains(&c) { c } else { ' ' })
+                .collect::<String>()
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .filter(|l| !l.is_empty())
+        .collect();
+    for word in lines.iter().flat_map(|l| l.split(' ')) {
+        *counts.entry(word).or_insert(0) += 1;
+    }
+    let mut vocab: Vec<(&str, usize)> = counts.into_iter().collect();
+    vocab.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    let vocab = vocab
+        .into_iter()
+        .take(MAX_VOCAB)
+        .map(|(w, _)| w.to_string())
+        .collect();
+    (lines, vocab)
+}
+
+fn run(command: &mut Command) -> Result<(), SttError> {
+    let output = command
+        .output()
+        .map_err(|e| scorer_err(&format!("can't run {:?}", command), e))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(SttError::Scorer(format!(
+            "{:?} failed with {}: {}",
+            command,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )))
+    }
+}
+
+fn scorer_err(what: &str, e: impl std::fmt::Display) -> SttError {
+    SttError::Scorer(format!("{}: {}", what, e))
+}
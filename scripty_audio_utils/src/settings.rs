@@ -0,0 +1,64 @@
+use crate::{ModelKey, RedactionRules, VadSettings};
+use scripty_config::BotConfig;
+use std::path::PathBuf;
+
+/// Settings for the speech-to-text pipeline that can change between guilds.
+#[derive(Clone, Debug, Default)]
+pub struct SttSettings {
+    /// Voice activity detection thresholds, used to split and trim utterances.
+    pub vad: VadSettings,
+    /// Whether to run background noise suppression before speech-to-text.
+    pub denoise: bool,
+    /// Code of the language to transcribe as. `None` means the bot's default language.
+    pub language: Option<String>,
+    /// Words to bias recognition towards, like server names, game terms and nicknames.
+    pub hot_words: Vec<HotWord>,
+    /// A scorer built from this guild's own text, used in place of the model's own one.
+    pub scorer: Option<CustomScorer>,
+    /// Transcripts the backend is less confident in than this (from 0 to 1) are filtered.
+    /// `None` means the bot's default threshold.
+    pub min_confidence: Option<f32>,
+    /// Whether filtered transcripts are still sent, marked as unsure, instead of dropped.
+    pub mark_low_confidence: bool,
+    /// What to mask in transcripts before they're sent.
+    pub redaction: RedactionRules,
+}
+
+impl SttSettings {
+    /// Get the key of the model these settings transcribe with.
+    ///
+    /// The custom scorer is only used if it was built for the language that's picked now.
+    pub fn model_key(&self, default_language: &str) -> ModelKey {
+        let language = self.language.as_deref().unwrap_or(default_language);
+        ModelKey {
+            language: language.to_string(),
+            scorer: self
+                .scorer
+                .as_ref()
+                .filter(|s| s.language == language)
+                .map(|s| s.path.clone()),
+        }
+    }
+
+    /// Get the confidence threshold, falling back to the bot's default if there isn't one.
+    pub fn confidence_threshold(&self) -> f32 {
+        self.min_confidence
+            .unwrap_or_else(|| BotConfig::get().map_or(0.0, BotConfig::min_confidence))
+    }
+}
+
+/// A KenLM scorer built for one guild.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CustomScorer {
+    /// Code of the language whose model the scorer was built for.
+    pub language: String,
+    pub path: PathBuf,
+}
+
+/// A word the speech-to-text backend should be more (or less) likely to hear.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HotWord {
+    pub word: String,
+    /// How strongly to bias towards this word. Negative values bias away from it.
+    pub boost: f32,
+}
@@ -0,0 +1,100 @@
+use crate::Word;
+use std::fmt::Write;
+
+/// A pause between words longer than this, in milliseconds, always starts a new cue.
+pub const CUE_GAP_MS: u32 = 1_000;
+/// Cues are split before they get longer than this, in milliseconds.
+pub const MAX_CUE_MS: u32 = 7_000;
+/// Cues are split before their text gets longer than this many characters, which is about
+/// two lines on most players.
+pub const MAX_CUE_CHARS: usize = 84;
+
+/// One subtitle: some text and when to show it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cue {
+    pub text: String,
+    /// Offset from the start of the audio, in milliseconds.
+    pub start_ms: u32,
+    /// Offset from the start of the audio, in milliseconds.
+    pub end_ms: u32,
+}
+
+/// Group words into subtitle cues.
+///
+/// A new cue is started after a pause, or when the current one would get too long to read
+/// comfortably. `words` must be in order.
+pub fn build_cues(words: &[Word]) -> Vec<Cue> {
+    let mut cues: Vec<Cue> = Vec::new();
+    let mut current: Option<Cue> = None;
+    for word in words {
+        if let Some(cue) = &current {
+            if word.start_ms.saturating_sub(cue.end_ms) > CUE_GAP_MS
+                || word.end_ms.saturating_sub(cue.start_ms) > MAX_CUE_MS
+                || cue.text.len() + 1 + word.text.len() > MAX_CUE_CHARS
+            {
+                cues.extend(current.take());
+            }
+        }
+        match &mut current {
+            Some(cue) => {
+                cue.text.push(' ');
+                cue.text.push_str(&word.text);
+                cue.end_ms = word.end_ms;
+            }
+            None => {
+                current = Some(Cue {
+                    text: word.text.clone(),
+                    start_ms: word.start_ms,
+                    end_ms: word.end_ms,
+                })
+            }
+        }
+    }
+    cues.extend(current);
+    cues
+}
+
+/// Format cues as a SubRip (`.srt`) file.
+pub fn to_srt(cues: &[Cue]) -> String {
+    let mut out = String::new();
+    for (i, cue) in cues.iter().enumerate() {
+        let _ = write!(
+            out,
+            "{}\n{} --> {}\n{}\n\n",
+            i + 1,
+            timestamp(cue.start_ms, ','),
+            timestamp(cue.end_ms, ','),
+            cue.text
+        );
+    }
+    out
+}
+
+/// Format cues as a WebVTT (`.vtt`) file.
+pub fn to_webvtt(cues: &[Cue]) -> String {
+    let mut out = String::from("WEBVTT\n\n");
+    for cue in cues {
+        let _ = write!(
+            out,
+            "{} --> {}\n{}\n\n",
+            timestamp(cue.start_ms, '.'),
+            timestamp(cue.end_ms, '.'),
+            // `-->` in the text would be read as another timing line
+            cue.text.replace("-->", "->")
+        );
+    }
+    out
+}
+
+/// Format `ms` as `HH:MM:SS` followed by `separator` and the milliseconds.
+/// SRT uses a comma there, WebVTT a period.
+fn timestamp(ms: u32, separator: char) -> String {
+    format!(
+        "{:02}:{:02}:{:02}{}{:03}",
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1_000 % 60,
+        separator,
+        ms % 1_000
+    )
+}
@@ -0,0 +1,145 @@
+/// The result of running speech-to-text over one utterance, independent of which backend
+/// produced it.
+#[derive(Clone, Debug, Default)]
+pub struct Transcript {
+    /// Every candidate the backend returned, most likely first.
+    candidates: Vec<Candidate>,
+}
+
+/// One possible transcription of an utterance.
+#[derive(Clone, Debug, Default)]
+pub struct Candidate {
+    /// The full text of this candidate.
+    pub text: String,
+    /// How confident the backend is in this candidate.
+    ///
+    /// The scale depends on the backend: DeepSpeech returns a unbounded log-probability,
+    /// so only compare this to other candidates from the same backend.
+    pub confidence: f64,
+    /// The individual tokens that make up `text`, along with their timings.
+    pub tokens: Vec<Token>,
+    /// Sentence-level segments, if the backend splits its output into them.
+    /// Backends that don't will leave this empty.
+    pub segments: Vec<Segment>,
+}
+
+/// A single token (a character for DeepSpeech, a word or word piece for most other engines)
+/// along with where it was heard in the audio.
+#[derive(Clone, Debug, Default)]
+pub struct Token {
+    pub text: String,
+    /// Offset from the start of the audio passed to the backend, in milliseconds.
+    pub start_ms: u32,
+    /// Offset from the start of the audio passed to the backend, in milliseconds.
+    pub end_ms: u32,
+}
+
+/// A span of text the backend decoded as one unit, usually a sentence or phrase.
+#[derive(Clone, Debug, Default)]
+pub struct Segment {
+    pub text: String,
+    /// Offset from the start of the audio passed to the backend, in milliseconds.
+    pub start_ms: u32,
+    /// Offset from the start of the audio passed to the backend, in milliseconds.
+    pub end_ms: u32,
+}
+
+/// A whole word, assembled from one or more tokens.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Word {
+    pub text: String,
+    /// Offset from the start of the audio passed to the backend, in milliseconds.
+    pub start_ms: u32,
+    /// Offset from the start of the audio passed to the backend, in milliseconds.
+    pub end_ms: u32,
+}
+
+impl Transcript {
+    /// Create a transcript from a list of candidates. They must be sorted most likely first.
+    pub fn new(candidates: Vec<Candidate>) -> Self {
+        Self { candidates }
+    }
+
+    /// The most likely candidate, if the backend returned any.
+    pub fn best(&self) -> Option<&Candidate> {
+        self.candidates.first()
+    }
+
+    /// Every candidate other than the most likely one.
+    pub fn alternatives(&self) -> &[Candidate] {
+        self.candidates.get(1..).unwrap_or(&[])
+    }
+
+    /// Every candidate the backend returned, most likely first.
+    pub fn candidates(&self) -> &[Candidate] {
+        &self.candidates
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.candidates.is_empty()
+    }
+
+    /// Replace the confidence of every candidate with what `f` returns for it.
+    pub fn map_confidence(&mut self, f: impl Fn(&Candidate) -> f64) {
+        for c in &mut self.candidates {
+            c.confidence = f(c);
+        }
+    }
+
+    /// Move every timing `offset_ms` later, like when the audio passed to the backend started
+    /// partway into the utterance.
+    pub fn shift(&mut self, offset_ms: u32) {
+        for c in &mut self.candidates {
+            for t in &mut c.tokens {
+                t.start_ms += offset_ms;
+                t.end_ms += offset_ms;
+            }
+            for s in &mut c.segments {
+                s.start_ms += offset_ms;
+                s.end_ms += offset_ms;
+            }
+        }
+    }
+}
+
+impl Candidate {
+    /// The offset of the first token, in milliseconds.
+    pub fn start_ms(&self) -> u32 {
+        self.tokens.first().map_or(0, |t| t.start_ms)
+    }
+
+    /// The offset of the end of the last token, in milliseconds.
+    pub fn end_ms(&self) -> u32 {
+        self.tokens.last().map_or(0, |t| t.end_ms)
+    }
+
+    /// Group tokens into whole words with word-level timings.
+    ///
+    /// Tokens are split on whitespace, so this works both for backends that return single
+    /// characters (with spaces as their own tokens) and ones that return word pieces with a
+    /// leading space.
+    pub fn words(&self) -> Vec<Word> {
+        let mut words = Vec::new();
+        let mut current: Option<Word> = None;
+        for token in &self.tokens {
+            if token.text.starts_with(char::is_whitespace) {
+                words.extend(current.take());
+            }
+            let text = token.text.trim();
+            if !text.is_empty() {
+                let word = current.get_or_insert_with(|| Word {
+                    text: String::new(),
+                    start_ms: token.start_ms,
+                    end_ms: token.end_ms,
+                });
+                word.text.push_str(text);
+                word.end_ms = token.end_ms;
+            }
+            if token.text.ends_with(char::is_whitespace) {
+                words.extend(current.take());
+            }
+        }
+        words.extend(current);
+        words
+    }
+}
@@ -0,0 +1,65 @@
+use crate::{redact, redact_words, Candidate, RedactionRules, Word};
+
+/// One speaker's transcribed utterance, as it's logged, stored and exported.
+///
+/// Unlike a `Candidate`, this has already been redacted, and its timings can be moved to be
+/// relative to the whole session instead of the audio that was transcribed.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Utterance {
+    /// Discord ID of whoever said it.
+    pub speaker_id: u64,
+    /// Their name when they said it.
+    pub speaker: String,
+    /// In milliseconds, from the start of the session (or the audio, before `shift`).
+    pub start_ms: u32,
+    /// In milliseconds, from the start of the session (or the audio, before `shift`).
+    pub end_ms: u32,
+    pub text: String,
+    /// How confident the model was in this transcript, from 0 to 1.
+    pub confidence: f64,
+    /// Each word and when it was said, on the same clock as `start_ms`.
+    /// Empty if the backend doesn't return timings.
+    pub words: Vec<Word>,
+}
+
+impl Utterance {
+    /// Build a utterance from the candidate picked for `duration_ms` of audio, masking
+    /// everything `rules` asks for in both the text and the words. It starts with the first
+    /// word, and ends with the audio.
+    ///
+    /// `confidence` should be `SttBackend::normalized_confidence` of the candidate.
+    pub fn new(
+        speaker_id: u64,
+        speaker: String,
+        candidate: &Candidate,
+        confidence: f64,
+        duration_ms: u32,
+        rules: &RedactionRules,
+    ) -> Self {
+        Self {
+            speaker_id,
+            speaker,
+            start_ms: candidate.start_ms(),
+            // backends can pad the last token past the end of the audio
+            end_ms: duration_ms.max(candidate.end_ms()),
+            text: redact(&candidate.text, rules),
+            confidence,
+            words: redact_words(&candidate.words(), rules),
+        }
+    }
+
+    /// Move every timing `offset_ms` later, like from the start of the audio to the start of
+    /// the session.
+    pub fn shift(&mut self, offset_ms: u32) {
+        self.start_ms += offset_ms;
+        self.end_ms += offset_ms;
+        for word in &mut self.words {
+            word.start_ms += offset_ms;
+            word.end_ms += offset_ms;
+        }
+    }
+
+    pub fn duration_ms(&self) -> u32 {
+        self.end_ms - self.start_ms
+    }
+}
@@ -0,0 +1,203 @@
+// A simple energy and zero-crossing based voice activity detector.
+//
+// Discord's speaking flag stays on through pauses and background noise,
+// so this is used to find where people actually stop talking.
+
+use std::ops::Range;
+
+/// Length of each frame the detector looks at, in milliseconds.
+/// Discord sends 20ms packets, so this lines up with them.
+const FRAME_MS: u32 = 20;
+
+/// Frames with more zero crossings than this (as a fraction of samples) are treated as noise
+/// unless they're also well above the energy threshold. Hiss and fans cross zero far more
+/// often than voiced speech does.
+const MAX_SPEECH_ZCR: f32 = 0.35;
+
+/// How far above the threshold, in dB, a frame has to be to count as speech regardless of its
+/// zero crossing rate. This keeps loud fricatives ("s", "f") from being cut off.
+const LOUD_MARGIN_DB: f32 = 12.0;
+
+/// Settings for voice activity detection. These are set per guild.
+#[derive(Clone, Copy, Debug)]
+pub struct VadSettings {
+    /// Frames quieter than this, in dBFS, are treated as silence.
+    pub threshold_db: f32,
+    /// How long a pause has to be, in milliseconds, to end the utterance there.
+    pub min_silence_ms: u32,
+    /// How much silence to keep either side of speech when trimming, in milliseconds.
+    pub padding_ms: u32,
+}
+
+impl Default for VadSettings {
+    fn default() -> Self {
+        Self {
+            threshold_db: -45.0,
+            min_silence_ms: 800,
+            padding_ms: 200,
+        }
+    }
+}
+
+/// What the detector heard in the audio pushed into it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VadEvent {
+    /// At least one frame of speech.
+    Speech,
+    /// Nothing but silence.
+    Silence,
+    /// The speaker paused for long enough that the current utterance should end here.
+    Pause,
+}
+
+/// A streaming voice activity detector for one speaker.
+pub struct VoiceActivityDetector {
+    settings: VadSettings,
+    channels: usize,
+    // samples per frame, across all channels
+    frame_len: usize,
+    // samples left over from the last push that didn't fill a whole frame
+    remainder: Vec<i16>,
+    silent_ms: u32,
+    heard_speech: bool,
+}
+
+impl VoiceActivityDetector {
+    /// Create a detector for interleaved audio at `sample_rate` with `channels` channels.
+    pub fn new(settings: VadSettings, sample_rate: u32, channels: usize) -> Self {
+        let frame_len = frame_len(sample_rate) * channels;
+        Self {
+            settings,
+            channels,
+            frame_len,
+            remainder: Vec::with_capacity(frame_len),
+            silent_ms: 0,
+            heard_speech: false,
+        }
+    }
+
+    /// Change the settings used for audio pushed in after this.
+    pub fn set_settings(&mut self, settings: VadSettings) {
+        self.settings = settings;
+    }
+
+    /// Forget about any speech heard so far, for when the utterance was ended some other way.
+    pub fn reset(&mut self) {
+        self.remainder.clear();
+        self.silent_ms = 0;
+        self.heard_speech = false;
+    }
+
+    /// Whether any speech has been heard since the last pause or reset.
+    pub fn heard_speech(&self) -> bool {
+        self.heard_speech
+    }
+
+    /// Run detection on the next chunk of audio.
+    pub fn push(&mut self, audio: &[i16]) -> VadEvent {
+        let mut any_speech = false;
+        let mut paused = false;
+
+        self.remainder.extend_from_slice(audio);
+        let whole_frames = self.remainder.len() / self.frame_len * self.frame_len;
+        for frame in self.remainder[..whole_frames].chunks_exact(self.frame_len) {
+            if is_speech(frame, self.channels, self.settings.threshold_db) {
+                any_speech = true;
+                self.heard_speech = true;
+                self.silent_ms = 0;
+            } else {
+                self.silent_ms += FRAME_MS;
+                if self.heard_speech && self.silent_ms >= self.settings.min_silence_ms {
+                    paused = true;
+                    self.heard_speech = false;
+                    self.silent_ms = 0;
+                }
+            }
+        }
+        self.remainder.drain(..whole_frames);
+
+        if paused {
+            VadEvent::Pause
+        } else if any_speech {
+            VadEvent::Speech
+        } else {
+            VadEvent::Silence
+        }
+    }
+}
+
+/// Trim leading and trailing silence from mono audio, keeping `padding_ms` either side.
+///
+/// Returns a empty slice if there's no speech at all.
+pub fn trim_silence<'a>(audio: &'a [i16], sample_rate: u32, settings: &VadSettings) -> &'a [i16] {
+    &audio[speech_range(audio, sample_rate, settings)]
+}
+
+/// Find the part of mono audio `trim_silence` would keep, as a range of samples.
+///
+/// Its start is how much was trimmed off the front, which timings of the trimmed audio need
+/// adding back to be relative to the whole thing.
+pub fn speech_range(audio: &[i16], sample_rate: u32, settings: &VadSettings) -> Range<usize> {
+    let frame_len = frame_len(sample_rate);
+    let is_speech_frame = |frame: &[i16]| is_speech(frame, 1, settings.threshold_db);
+
+    let first = match audio.chunks(frame_len).position(is_speech_frame) {
+        Some(f) => f,
+        None => return 0..0,
+    };
+    let last = audio
+        .chunks(frame_len)
+        .rposition(is_speech_frame)
+        .unwrap_or(first);
+
+    let padding = (sample_rate * settings.padding_ms / 1000) as usize;
+    let start = (first * frame_len).saturating_sub(padding);
+    let end = ((last + 1) * frame_len + padding).min(audio.len());
+    start..end
+}
+
+/// Samples in one frame of mono audio at `sample_rate`. Rates too low to fit a sample in a frame
+/// still get one, otherwise there'd be no frames at all.
+fn frame_len(sample_rate: u32) -> usize {
+    ((sample_rate * FRAME_MS / 1000) as usize).max(1)
+}
+
+/// Decide whether a single frame of interleaved audio contains speech.
+fn is_speech(frame: &[i16], channels: usize, threshold_db: f32) -> bool {
+    let mut sum_squares = 0_i64;
+    let mut zero_crossings = 0_u32;
+    let mut last_positive = None;
+    let mut samples = 0_u32;
+
+    // every channel is averaged together, that way a stereo frame is analysed as one signal
+    for chunk in frame.chunks_exact(channels) {
+        let sample = chunk.iter().map(|s| *s as i32).sum::<i32>() / channels as i32;
+        sum_squares += (sample as i64) * (sample as i64);
+
+        let positive = sample >= 0;
+        if let Some(last) = last_positive {
+            if last != positive {
+                zero_crossings += 1;
+            }
+        }
+        last_positive = Some(positive);
+        samples += 1;
+    }
+    if samples == 0 {
+        return false;
+    }
+
+    let rms = (sum_squares as f64 / samples as f64).sqrt();
+    // floor of -100dBFS for true digital silence, where log10(0) would be -inf
+    let db = if rms > 0.0 {
+        (20.0 * (rms / i16::MAX as f64).log10()) as f32
+    } else {
+        -100.0
+    };
+    if db < threshold_db {
+        return false;
+    }
+
+    let zcr = zero_crossings as f32 / samples as f32;
+    zcr <= MAX_SPEECH_ZCR || db >= threshold_db + LOUD_MARGIN_DB
+}
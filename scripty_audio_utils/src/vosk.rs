@@ -0,0 +1,175 @@
+use crate::{Candidate, HotWord, SttBackend, SttError, SttStream, Token, Transcript};
+use std::path::Path;
+use vosk::{CompleteResult, DecodingState, Model as VoskModel, Recognizer, Word as VoskWord};
+
+// Most small Vosk models are trained on 16KHz audio. Kaldi will resample internally if the
+// model was trained on something else, so this doesn't need to exactly match.
+const VOSK_SAMPLE_RATE: u32 = 16_000;
+
+pub struct VoskBackend {
+    model: VoskModel,
+}
+
+impl VoskBackend {
+    /// Load a Vosk model from a directory.
+    ///
+    /// `dir_path` should be a unpacked Vosk model, ie it contains the `am`, `conf` and `graph`
+    /// directories. Each language is its own model directory.
+    pub fn load(dir_path: &Path) -> Result<Self, SttError> {
+        let path = dir_path
+            .to_str()
+            .ok_or_else(|| SttError::Load("model path isn't valid UTF-8".to_string()))?;
+        let model = VoskModel::new(path).ok_or_else(|| {
+            SttError::Load(format!(
+                "{} doesn't look like a Vosk model directory",
+                dir_path.display()
+            ))
+        })?;
+
+        Ok(Self { model })
+    }
+
+    fn recognizer(&self) -> Result<Recognizer, SttError> {
+        let mut recognizer = Recognizer::new(&self.model, VOSK_SAMPLE_RATE as f32)
+            .ok_or_else(|| SttError::Decode("failed to create a recognizer".to_string()))?;
+        recognizer.set_words(true);
+        Ok(recognizer)
+    }
+}
+
+impl SttBackend for VoskBackend {
+    fn name(&self) -> &'static str {
+        "vosk"
+    }
+
+    fn sample_rate(&self) -> u32 {
+        VOSK_SAMPLE_RATE
+    }
+
+    // Vosk can only be restricted to a fixed grammar, not biased towards words, so hot words are
+    // ignored here
+    fn transcribe(
+        &self,
+        audio: &[i16],
+        hot_words: &[HotWord],
+        max_candidates: u32,
+    ) -> Result<Transcript, SttError> {
+        // even for complete utterances, Vosk is used through its streaming API:
+        // there just isn't a intermediate result taken
+        let mut stream = self.create_stream(hot_words, max_candidates)?;
+        stream.feed_audio(audio)?;
+        stream.finish()
+    }
+
+    fn supports_streaming(&self) -> bool {
+        true
+    }
+
+    // alternatives come without word timings, and only for the utterance after the last
+    // endpoint, so Vosk only ever returns its best guess
+    fn create_stream(
+        &self,
+        _hot_words: &[HotWord],
+        _max_candidates: u32,
+    ) -> Result<Box<dyn SttStream>, SttError> {
+        Ok(Box::new(VoskStream {
+            recognizer: self.recognizer()?,
+            finalized: Candidate::default(),
+            finalized_words: 0,
+        }))
+    }
+}
+
+/// A streaming decode using Vosk's own streaming recognizer.
+///
+/// Vosk splits long audio into utterances on its own whenever it hears a endpoint, so every
+/// utterance it finalizes is appended to `finalized` and the partial result is only for audio
+/// after the last endpoint.
+pub struct VoskStream {
+    recognizer: Recognizer,
+    finalized: Candidate,
+    finalized_words: usize,
+}
+
+// the recognizer is only ever used from one thread at a time through `&mut self`
+unsafe impl Send for VoskStream {}
+
+impl VoskStream {
+    fn push_words(candidate: &mut Candidate, words: &[VoskWord]) {
+        for word in words {
+            let text = if candidate.text.is_empty() {
+                word.word.to_string()
+            } else {
+                format!(" {}", word.word)
+            };
+            candidate.text.push_str(&text);
+            candidate.tokens.push(Token {
+                text,
+                start_ms: (word.start * 1000.0) as u32,
+                end_ms: (word.end * 1000.0) as u32,
+            });
+        }
+    }
+
+    // takes the fields separately since `result` borrows the recognizer
+    fn push_result(finalized: &mut Candidate, finalized_words: &mut usize, result: CompleteResult) {
+        if let Some(result) = result.single() {
+            // running total of word confidences, turned into a mean when finishing
+            finalized.confidence += result.result.iter().map(|w| w.conf as f64).sum::<f64>();
+            *finalized_words += result.result.len();
+            Self::push_words(finalized, &result.result);
+        }
+    }
+}
+
+impl SttStream for VoskStream {
+    fn feed_audio(&mut self, audio: &[i16]) -> Result<(), SttError> {
+        match self.recognizer.accept_waveform(audio) {
+            DecodingState::Running => {}
+            DecodingState::Finalized => {
+                let result = self.recognizer.result();
+                Self::push_result(&mut self.finalized, &mut self.finalized_words, result);
+            }
+            DecodingState::Failed => {
+                return Err(SttError::Decode("Vosk failed to decode audio".to_string()))
+            }
+        }
+        Ok(())
+    }
+
+    fn intermediate(&mut self) -> Result<Transcript, SttError> {
+        let mut candidate = self.finalized.clone();
+        let partial = self.recognizer.partial_result();
+        Self::push_words(&mut candidate, &partial.partial_result);
+        if candidate.text.is_empty() && !partial.partial.is_empty() {
+            // partial word timings are only returned if partial words are enabled,
+            // so fall back to the bare text
+            candidate.text = partial.partial.to_string();
+        }
+        candidate.confidence = 0.0;
+        Ok(if candidate.text.is_empty() {
+            Transcript::default()
+        } else {
+            Transcript::new(vec![candidate])
+        })
+    }
+
+    fn finish(self: Box<Self>) -> Result<Transcript, SttError> {
+        let VoskStream {
+            mut recognizer,
+            mut finalized,
+            mut finalized_words,
+        } = *self;
+        Self::push_result(
+            &mut finalized,
+            &mut finalized_words,
+            recognizer.final_result(),
+        );
+
+        if finalized.text.is_empty() {
+            return Ok(Transcript::default());
+        }
+        finalized.confidence /= finalized_words.max(1) as f64;
+        Ok(Transcript::new(vec![finalized]))
+    }
+}
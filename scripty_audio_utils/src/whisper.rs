@@ -0,0 +1,157 @@
+use crate::{Candidate, HotWord, Segment, SttBackend, SttError, Token, Transcript};
+use std::{path::Path, sync::Mutex};
+use whisper_rs::{
+    FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters, WhisperError,
+    WhisperState,
+};
+
+// Whisper is always trained on 16KHz audio.
+const WHISPER_SAMPLE_RATE: u32 = 16_000;
+
+// whisper.cpp reports timestamps in units of 10ms.
+const TIMESTAMP_MS: i64 = 10;
+
+pub struct WhisperBackend {
+    // the state holds the model and the buffers used while decoding:
+    // only one decode can run on a state at a time
+    state: Mutex<WhisperState>,
+    threads: i32,
+    language: String,
+}
+
+impl WhisperBackend {
+    /// Load a whisper.cpp model from a directory.
+    ///
+    /// The first `.bin` or `.ggml` file found is used as the model. Only ggml models are
+    /// supported, since that's all whisper.cpp can load.
+    /// `threads` is the number of CPU threads to use for each decode.
+    /// `language` is the code of the language to transcribe as, like `en`.
+    pub fn load(dir_path: &Path, threads: u16, language: &str) -> Result<Self, SttError> {
+        let mut model_name: Option<Box<Path>> = None;
+        for file in dir_path
+            .read_dir()
+            .map_err(|e| SttError::Load(format!("can't read model dir: {}", e)))?
+            .flatten()
+        {
+            let file_path = file.path();
+            if file_path.is_file() {
+                if let Some(ext) = file_path.extension() {
+                    if ext == "bin" || ext == "ggml" {
+                        model_name = Some(file_path.into_boxed_path());
+                        break;
+                    }
+                }
+            }
+        }
+        let model_name = model_name.ok_or_else(|| {
+            SttError::Load(format!("no ggml model found in {}", dir_path.display()))
+        })?;
+
+        let ctx = WhisperContext::new_with_params(
+            model_name
+                .to_str()
+                .ok_or_else(|| SttError::Load("model path isn't valid UTF-8".to_string()))?,
+            WhisperContextParameters::default(),
+        )
+        .map_err(|e| SttError::Load(format!("{:?}", e)))?;
+        let state = ctx
+            .create_state()
+            .map_err(|e| SttError::Load(format!("{:?}", e)))?;
+
+        Ok(Self {
+            state: Mutex::new(state),
+            threads: threads.max(1) as i32,
+            language: language.to_string(),
+        })
+    }
+}
+
+impl SttBackend for WhisperBackend {
+    fn name(&self) -> &'static str {
+        "whisper"
+    }
+
+    fn sample_rate(&self) -> u32 {
+        WHISPER_SAMPLE_RATE
+    }
+
+    // greedy decoding only ever comes up with one candidate
+    fn transcribe(
+        &self,
+        audio: &[i16],
+        hot_words: &[HotWord],
+        _max_candidates: u32,
+    ) -> Result<Transcript, SttError> {
+        // whisper wants f32 samples in the range -1.0..1.0
+        let audio: Vec<f32> = audio.iter().map(|s| *s as f32 / 32768.0).collect();
+
+        let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+        params.set_n_threads(self.threads);
+        params.set_language(Some(&self.language));
+        params.set_token_timestamps(true);
+        params.set_print_progress(false);
+        params.set_print_realtime(false);
+        params.set_print_special(false);
+        params.set_print_timestamps(false);
+        // whisper can't boost words directly, but it does copy the spelling of words in the
+        // prompt, which is what matters for names. boosts below zero can't be expressed at all
+        let prompt = hot_words
+            .iter()
+            .filter(|h| h.boost > 0.0)
+            .map(|h| h.word.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+        if !prompt.is_empty() {
+            params.set_initial_prompt(&prompt);
+        }
+
+        let mut state = self
+            .state
+            .lock()
+            .expect("a thread panicked while running whisper");
+        let decode_err = |e: WhisperError| SttError::Decode(format!("{:?}", e));
+        state.full(params, &audio).map_err(decode_err)?;
+
+        let mut text = String::new();
+        let mut tokens = Vec::new();
+        let mut segments = Vec::new();
+        let mut probability_sum = 0.0;
+        for i in 0..state.full_n_segments().map_err(decode_err)? {
+            let segment_text = state.full_get_segment_text(i).map_err(decode_err)?;
+            text.push_str(&segment_text);
+            segments.push(Segment {
+                text: segment_text.trim().to_string(),
+                start_ms: (state.full_get_segment_t0(i).map_err(decode_err)? * TIMESTAMP_MS) as u32,
+                end_ms: (state.full_get_segment_t1(i).map_err(decode_err)? * TIMESTAMP_MS) as u32,
+            });
+
+            for j in 0..state.full_n_tokens(i).map_err(decode_err)? {
+                let token_text = state.full_get_token_text(i, j).map_err(decode_err)?;
+                // skip special tokens like [_BEG_] and timestamp tokens
+                if token_text.starts_with("[_") {
+                    continue;
+                }
+                let data = state.full_get_token_data(i, j).map_err(decode_err)?;
+                probability_sum += data.p as f64;
+                tokens.push(Token {
+                    text: token_text,
+                    start_ms: (data.t0 * TIMESTAMP_MS) as u32,
+                    end_ms: (data.t1 * TIMESTAMP_MS) as u32,
+                });
+            }
+        }
+
+        if tokens.is_empty() {
+            return Ok(Transcript::default());
+        }
+        // the mean token probability is the closest thing whisper has to a overall confidence
+        let confidence = probability_sum / tokens.len() as f64;
+
+        Ok(Transcript::new(vec![Candidate {
+            text: text.trim().to_string(),
+            confidence,
+            tokens,
+            segments,
+        }]))
+    }
+}
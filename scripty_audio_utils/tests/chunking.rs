@@ -0,0 +1,250 @@
+use scripty_audio_utils::{
+    chunk_windows, stitch, transcribe_chunked, Candidate, HotWord, SttBackend, SttError, Token,
+    Transcript, OVERLAP_MS, WINDOW_MS,
+};
+
+const RATE: u32 = 16_000;
+
+fn samples(ms: u32) -> usize {
+    (RATE as u64 * ms as u64 / 1000) as usize
+}
+
+/// A transcript of one candidate, from words and their offsets in the window.
+fn transcript(words: &[(&str, u32, u32)], confidence: f64) -> Transcript {
+    let tokens: Vec<_> = words
+        .iter()
+        .enumerate()
+        .map(|(i, (text, start_ms, end_ms))| Token {
+            text: if i == 0 {
+                text.to_string()
+            } else {
+                format!(" {}", text)
+            },
+            start_ms: *start_ms,
+            end_ms: *end_ms,
+        })
+        .collect();
+    Transcript::new(vec![Candidate {
+        text: tokens.iter().map(|t| t.text.as_str()).collect(),
+        confidence,
+        tokens,
+        segments: Vec::new(),
+    }])
+}
+
+fn words(t: &Transcript) -> Vec<(String, u32, u32)> {
+    t.best()
+        .map(|c| {
+            c.words()
+                .into_iter()
+                .map(|w| (w.text, w.start_ms, w.end_ms))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Offset of the second window, in milliseconds.
+const STEP_MS: u32 = WINDOW_MS - OVERLAP_MS;
+/// Where the first and second windows are stitched together, in milliseconds.
+const SEAM_MS: u32 = STEP_MS + OVERLAP_MS / 2;
+
+#[test]
+fn short_audio_is_one_window() {
+    assert_eq!(chunk_windows(samples(5_000), RATE), vec![0..samples(5_000)]);
+    assert_eq!(chunk_windows(0, RATE), vec![0..0]);
+}
+
+#[test]
+fn exactly_one_window() {
+    let len = samples(WINDOW_MS);
+    assert_eq!(chunk_windows(len, RATE), vec![0..len]);
+}
+
+#[test]
+fn exact_multiples_of_the_window() {
+    for multiple in 2..5 {
+        let len = samples(WINDOW_MS) * multiple;
+        let windows = chunk_windows(len, RATE);
+
+        assert_eq!(windows.first().unwrap().start, 0);
+        assert_eq!(windows.last().unwrap().end, len);
+        for w in &windows {
+            assert!(w.len() <= samples(WINDOW_MS), "{:?} is too long", w);
+        }
+        for pair in windows.windows(2) {
+            assert_eq!(pair[1].start, pair[0].start + samples(STEP_MS));
+            // every window but the last is full, so they all overlap by as much
+            assert_eq!(pair[0].end - pair[1].start, samples(OVERLAP_MS));
+        }
+    }
+}
+
+#[test]
+fn audio_ending_with_a_window_has_no_empty_tail() {
+    let len = samples(WINDOW_MS + STEP_MS);
+    assert_eq!(
+        chunk_windows(len, RATE),
+        vec![0..samples(WINDOW_MS), samples(STEP_MS)..len]
+    );
+}
+
+#[test]
+fn single_chunk_is_unchanged() {
+    let t = transcript(&[("hello", 100, 400), ("there", 500, 900)], 0.8);
+    let stitched = stitch(&[(0, t.clone())]);
+    assert_eq!(words(&stitched), words(&t));
+    assert_eq!(stitched.best().unwrap().text, "hello there");
+}
+
+#[test]
+fn words_straddling_the_window_end_come_from_the_next_window() {
+    let chunks = [
+        (
+            0,
+            // "morning" gets cut off by the end of the window
+            transcript(
+                &[("good", 17_000, 17_400), ("morn", 19_500, WINDOW_MS)],
+                0.5,
+            ),
+        ),
+        (
+            STEP_MS,
+            transcript(&[("good", 0, 400), ("morning", 1_500, 2_100)], 0.5),
+        ),
+    ];
+    assert_eq!(
+        words(&stitch(&chunks)),
+        vec![
+            ("good".to_string(), 17_000, 17_400),
+            ("morning".to_string(), STEP_MS + 1_500, STEP_MS + 2_100),
+        ]
+    );
+}
+
+#[test]
+fn words_heard_either_side_of_the_seam_are_kept_once() {
+    let chunks = [
+        (
+            0,
+            transcript(
+                &[
+                    ("see", 18_500, 18_800),
+                    ("you", SEAM_MS - 100, SEAM_MS + 200),
+                ],
+                0.5,
+            ),
+        ),
+        (
+            STEP_MS,
+            // the later window heard "you" slightly later, just past the seam
+            transcript(
+                &[
+                    ("you", OVERLAP_MS / 2 + 50, OVERLAP_MS / 2 + 300),
+                    ("later", 1_500, 1_900),
+                ],
+                0.5,
+            ),
+        ),
+    ];
+    let stitched = stitch(&chunks);
+    assert_eq!(
+        words(&stitched),
+        vec![
+            ("see".to_string(), 18_500, 18_800),
+            ("you".to_string(), SEAM_MS - 100, SEAM_MS + 200),
+            ("later".to_string(), STEP_MS + 1_500, STEP_MS + 1_900),
+        ]
+    );
+    assert_eq!(stitched.best().unwrap().text, "see you later");
+}
+
+#[test]
+fn repeated_words_further_apart_are_both_kept() {
+    let chunks = [
+        (0, transcript(&[("no", SEAM_MS - 600, SEAM_MS - 400)], 0.5)),
+        (
+            STEP_MS,
+            transcript(&[("no", OVERLAP_MS / 2 + 100, OVERLAP_MS / 2 + 300)], 0.5),
+        ),
+    ];
+    assert_eq!(words(&stitch(&chunks)).len(), 2);
+}
+
+#[test]
+fn confidence_is_weighted_by_length() {
+    let chunks = [
+        (0, transcript(&[("a", 0, 3_000)], 1.0)),
+        (STEP_MS, transcript(&[("b", 1_000, 2_000)], 0.0)),
+    ];
+    let confidence = stitch(&chunks).best().unwrap().confidence;
+    assert!((confidence - 0.75).abs() < 1e-9, "{}", confidence);
+}
+
+#[test]
+fn empty_windows_stitch_to_nothing() {
+    let chunks = [(0, Transcript::default()), (STEP_MS, Transcript::default())];
+    assert!(stitch(&chunks).is_empty());
+}
+
+/// A backend on DeepSpeech's scale, where confidence is the sum of every character's
+/// log-probability, that hears the same sentence at the start of every window.
+struct LogProbBackend {
+    per_char: f64,
+}
+
+impl SttBackend for LogProbBackend {
+    fn name(&self) -> &'static str {
+        "log-prob"
+    }
+
+    fn sample_rate(&self) -> u32 {
+        RATE
+    }
+
+    fn transcribe(&self, _: &[i16], _: &[HotWord], _: u32) -> Result<Transcript, SttError> {
+        let text = "the quick brown fox";
+        let tokens: Vec<_> = text
+            .chars()
+            .enumerate()
+            .map(|(i, c)| Token {
+                text: c.to_string(),
+                start_ms: 100 + i as u32 * 50,
+                end_ms: 150 + i as u32 * 50,
+            })
+            .collect();
+        Ok(Transcript::new(vec![Candidate {
+            text: text.to_string(),
+            confidence: self.per_char * tokens.len() as f64,
+            tokens,
+            segments: Vec::new(),
+        }]))
+    }
+
+    fn normalized_confidence(&self, candidate: &Candidate) -> f64 {
+        (candidate.confidence / candidate.tokens.len().max(1) as f64)
+            .exp()
+            .min(1.0)
+    }
+
+    fn denormalize_confidence(&self, normalized: f64, candidate: &Candidate) -> f64 {
+        normalized.ln() * candidate.tokens.len().max(1) as f64
+    }
+}
+
+#[test]
+fn stitching_keeps_the_normalized_confidence() {
+    let m = LogProbBackend { per_char: -0.2 };
+    let audio = vec![0; samples(WINDOW_MS + STEP_MS)];
+    assert_eq!(chunk_windows(audio.len(), RATE).len(), 2);
+
+    // the stitched transcript has a token per word, not per character like each window
+    let stitched = transcribe_chunked(&m, &audio, &[], 1).unwrap();
+    let best = stitched.best().unwrap();
+    assert_eq!(best.text, "the quick brown fox");
+    let confidence = m.normalized_confidence(best);
+    assert!(
+        (confidence - (-0.2f64).exp()).abs() < 1e-9,
+        "{}",
+        confidence
+    );
+}
@@ -0,0 +1,52 @@
+use scripty_audio_utils::{denoise, Denoiser};
+
+/// Deterministic white noise, so the test doesn't need a RNG.
+fn white_noise(len: usize, amplitude: f64) -> Vec<i16> {
+    let mut state = 0x2545_f491_u32;
+    (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            ((state as f64 / u32::MAX as f64 * 2.0 - 1.0) * amplitude) as i16
+        })
+        .collect()
+}
+
+fn rms(audio: &[i16]) -> f64 {
+    (audio.iter().map(|s| (*s as f64).powi(2)).sum::<f64>() / audio.len() as f64).sqrt()
+}
+
+#[test]
+fn length_is_preserved() {
+    // deliberately not a multiple of the frame size
+    let input = white_noise(48_000 + 123, 2_000.0);
+    assert_eq!(denoise(&input).len(), input.len());
+}
+
+#[test]
+fn steady_noise_is_suppressed() {
+    let input = white_noise(48_000 * 3, 1_000.0);
+    let output = denoise(&input);
+    // give the model a couple seconds to learn what the noise sounds like
+    let reduction = 20.0 * (rms(&output[48_000 * 2..]) / rms(&input[48_000 * 2..])).log10();
+    assert!(
+        reduction < -15.0,
+        "only reduced noise by {:.1} dB",
+        reduction
+    );
+}
+
+#[test]
+fn streaming_matches_whole_buffer() {
+    let input = white_noise(48_000, 2_000.0);
+    let whole = denoise(&input);
+
+    let mut denoiser = Denoiser::new();
+    let mut streamed = Vec::new();
+    for chunk in input.chunks(960) {
+        denoiser.process(chunk, &mut streamed);
+    }
+    denoiser.flush(&mut streamed);
+    assert_eq!(streamed, whole);
+}
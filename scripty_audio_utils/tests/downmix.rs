@@ -0,0 +1,113 @@
+use scripty_audio_utils::{downmix_to_mono, prepare_audio, AudioFormat, ChannelLayout};
+use std::path::Path;
+
+/// Load a WAV file from `tests/fixtures`, along with its format.
+fn fixture(name: &str) -> (Vec<i16>, AudioFormat) {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name);
+    let mut reader = hound::WavReader::open(&path).expect("failed to open fixture");
+    let spec = reader.spec();
+    let layout = match spec.channels {
+        1 => ChannelLayout::Mono,
+        2 => ChannelLayout::Stereo,
+        n => panic!("fixture has {} channels", n),
+    };
+    let samples = reader
+        .samples::<i16>()
+        .collect::<Result<Vec<_>, _>>()
+        .expect("failed to read fixture");
+    (
+        samples,
+        AudioFormat {
+            layout,
+            sample_rate: spec.sample_rate,
+        },
+    )
+}
+
+fn duration_secs(mono: &[i16], sample_rate: u32) -> f64 {
+    mono.len() as f64 / sample_rate as f64
+}
+
+/// Estimate the pitch of a pure tone from how often it crosses zero.
+fn pitch_hz(mono: &[i16], sample_rate: u32) -> f64 {
+    let crossings = mono.windows(2).filter(|w| (w[0] < 0) != (w[1] < 0)).count();
+    crossings as f64 / 2.0 / duration_secs(mono, sample_rate)
+}
+
+#[test]
+fn downmix_keeps_every_frame() {
+    for name in [
+        "stereo_440hz_48k.wav",
+        "stereo_440hz_unbalanced_48k.wav",
+        "mono_440hz_16k.wav",
+    ] {
+        let (audio, format) = fixture(name);
+        let mono = downmix_to_mono(&audio, format);
+        assert_eq!(
+            mono.len(),
+            audio.len() / format.layout.channels(),
+            "{}",
+            name
+        );
+        assert!(
+            (duration_secs(&mono, format.sample_rate) - 0.5).abs() < 1e-9,
+            "{}",
+            name
+        );
+        let pitch = pitch_hz(&mono, format.sample_rate);
+        assert!(
+            (pitch - 440.0).abs() < 5.0,
+            "{}: pitch {:.1} Hz",
+            name,
+            pitch
+        );
+    }
+}
+
+#[test]
+fn downmix_averages_channels() {
+    let (audio, format) = fixture("stereo_440hz_unbalanced_48k.wav");
+    let mono = downmix_to_mono(&audio, format);
+    for (frame, mixed) in audio.chunks_exact(2).zip(&mono) {
+        assert_eq!(*mixed as i32, (frame[0] as i32 + frame[1] as i32) / 2);
+    }
+}
+
+#[test]
+fn downmix_does_not_overflow() {
+    let (audio, format) = fixture("stereo_full_scale_48k.wav");
+    let mono = downmix_to_mono(&audio, format);
+    assert!(mono.iter().any(|s| *s == i16::MAX));
+    assert!(mono.iter().any(|s| *s == i16::MIN));
+    for (frame, mixed) in audio.chunks_exact(2).zip(&mono) {
+        assert_eq!(frame[0], *mixed);
+    }
+}
+
+#[test]
+fn downmix_drops_partial_frame() {
+    let format = AudioFormat::DISCORD;
+    assert_eq!(downmix_to_mono(&[100, 200, 300], format), vec![150]);
+}
+
+#[test]
+fn prepare_audio_preserves_duration_and_pitch() {
+    for name in [
+        "stereo_440hz_48k.wav",
+        "stereo_440hz_unbalanced_48k.wav",
+        "mono_440hz_16k.wav",
+    ] {
+        let (audio, format) = fixture(name);
+        let prepared = prepare_audio(&audio, format, 16_000, false);
+        assert_eq!(prepared.len(), 8_000, "{}", name);
+        let pitch = pitch_hz(&prepared, 16_000);
+        assert!(
+            (pitch - 440.0).abs() < 5.0,
+            "{}: pitch {:.1} Hz",
+            name,
+            pitch
+        );
+    }
+}
@@ -0,0 +1,107 @@
+use scripty_audio_utils::{export_transcript, ExportFormat, Utterance, Word, EXPORT_FORMATS};
+
+fn entries() -> Vec<Utterance> {
+    vec![
+        Utterance {
+            speaker_id: 1,
+            speaker: "alice".to_string(),
+            start_ms: 1_200,
+            end_ms: 3_000,
+            text: "hello everyone".to_string(),
+            confidence: 0.9,
+            words: Vec::new(),
+        },
+        Utterance {
+            speaker_id: 2,
+            speaker: "bob_the_builder".to_string(),
+            start_ms: 3_725_000,
+            end_ms: 3_727_500,
+            text: "well f*** that".to_string(),
+            confidence: 0.8,
+            words: Vec::new(),
+        },
+    ]
+}
+
+fn word(text: &str, start_ms: u32, end_ms: u32) -> Word {
+    Word {
+        text: text.to_string(),
+        start_ms,
+        end_ms,
+    }
+}
+
+#[test]
+fn formats_round_trip_by_name() {
+    for name in EXPORT_FORMATS.iter() {
+        let format: ExportFormat = name.parse().unwrap();
+        assert_eq!(format.name(), *name);
+    }
+    assert!("doc".parse::<ExportFormat>().is_err());
+}
+
+#[test]
+fn text() {
+    assert_eq!(
+        export_transcript(&entries(), ExportFormat::Text, "Session"),
+        "Session\n\n\
+        [00:00:01] alice: hello everyone\n\
+        [01:02:05] bob_the_builder: well f*** that\n"
+    );
+}
+
+#[test]
+fn markdown_is_escaped() {
+    assert_eq!(
+        export_transcript(&entries(), ExportFormat::Markdown, "Session"),
+        "# Session\n\n\
+        **[00:00:01] alice:** hello everyone\n\n\
+        **[01:02:05] bob\\_the\\_builder:** well f\\*\\*\\* that\n\n"
+    );
+}
+
+#[test]
+fn subtitles_have_speakers() {
+    let srt = export_transcript(&entries(), ExportFormat::Srt, "Session");
+    assert!(srt.starts_with("1\n00:00:01,200 --> 00:00:03,000\nalice: hello everyone\n\n2\n"));
+    let vtt = export_transcript(&entries(), ExportFormat::WebVtt, "Session");
+    assert!(vtt.starts_with("WEBVTT\n\n00:00:01.200 --> 00:00:03.000\nalice: hello everyone\n\n"));
+    assert!(vtt.contains("01:02:05.000 --> 01:02:07.500\nbob_the_builder: well f*** that"));
+}
+
+#[test]
+fn subtitles_follow_word_timings() {
+    let entries = vec![Utterance {
+        speaker_id: 1,
+        speaker: "alice".to_string(),
+        start_ms: 10_000,
+        end_ms: 16_000,
+        text: "hi there how are you".to_string(),
+        confidence: 0.9,
+        words: vec![
+            word("hi", 10_100, 10_300),
+            word("there", 10_300, 10_700),
+            // a long pause starts a new cue
+            word("how", 14_000, 14_200),
+            word("are", 14_200, 14_400),
+            word("you", 14_400, 14_800),
+        ],
+    }];
+    assert_eq!(
+        export_transcript(&entries, ExportFormat::Srt, "Session"),
+        "1\n00:00:10,100 --> 00:00:10,700\nalice: hi there\n\n\
+        2\n00:00:14,000 --> 00:00:14,800\nalice: how are you\n\n"
+    );
+}
+
+#[test]
+fn empty_session() {
+    assert_eq!(
+        export_transcript(&[], ExportFormat::Text, "Session"),
+        "Session\n\n"
+    );
+    assert_eq!(
+        export_transcript(&[], ExportFormat::WebVtt, "Session"),
+        "WEBVTT\n\n"
+    );
+}
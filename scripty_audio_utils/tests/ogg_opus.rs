@@ -0,0 +1,83 @@
+use ogg::PacketReader;
+use scripty_audio_utils::{opus_packet_samples, OggOpusWriter, OPUS_PRE_SKIP, SILENT_FRAME};
+use std::io::Cursor;
+
+// a 20ms CELT fullband stereo frame, like most of what Discord sends
+const CELT_20MS: [u8; 4] = [0xfc, 0x01, 0x02, 0x03];
+
+#[test]
+fn packet_lengths() {
+    assert_eq!(opus_packet_samples(&SILENT_FRAME), Some(960));
+    assert_eq!(opus_packet_samples(&CELT_20MS), Some(960));
+    // SILK 60ms
+    assert_eq!(opus_packet_samples(&[0x18]), Some(2880));
+    // two 10ms hybrid frames
+    assert_eq!(opus_packet_samples(&[0x61, 0x00]), Some(960));
+    // code 3: three 20ms CELT frames
+    assert_eq!(opus_packet_samples(&[0xfb, 0x03]), Some(2880));
+    // code 3 packets over 120ms are invalid
+    assert_eq!(opus_packet_samples(&[0xfb, 0x07]), None);
+    assert_eq!(opus_packet_samples(&[]), None);
+}
+
+/// Read every packet of a stream back, along with its granule position and whether it
+/// ended the stream.
+fn read_back(file: Vec<u8>) -> Vec<(Vec<u8>, u64, bool)> {
+    let mut reader = PacketReader::new(Cursor::new(file));
+    let mut packets = Vec::new();
+    while let Some(p) = reader.read_packet().expect("invalid ogg stream") {
+        let granule = p.absgp_page();
+        let eos = p.last_in_stream();
+        packets.push((p.data, granule, eos));
+    }
+    packets
+}
+
+#[test]
+fn headers_and_gaps() {
+    let mut writer = OggOpusWriter::new(Vec::new(), 1234, 2).unwrap();
+    writer.write_packet(&CELT_20MS).unwrap();
+    // someone stops talking for a second
+    writer.write_silence_until(960 + 48_000).unwrap();
+    assert_eq!(writer.granule(), 960 + 48_000);
+    writer.write_packet(&CELT_20MS).unwrap();
+    // a gap that doesn't line up with 20ms frames is rounded
+    writer
+        .write_silence_until(writer.granule() + 1_000)
+        .unwrap();
+    assert_eq!(writer.granule(), 960 * 53);
+    writer.write_silence_until(0).unwrap();
+    assert_eq!(writer.granule(), 960 * 53);
+
+    let packets = read_back(writer.finish().unwrap());
+    let head = &packets[0].0;
+    assert_eq!(&head[..8], b"OpusHead");
+    assert_eq!(head[9], 2);
+    assert_eq!(u16::from_le_bytes([head[10], head[11]]), OPUS_PRE_SKIP);
+    assert_eq!(&packets[1].0[..8], b"OpusTags");
+
+    let audio = &packets[2..];
+    assert_eq!(audio.len(), 53);
+    assert_eq!(audio[0].0, CELT_20MS);
+    assert_eq!(audio[51].0, CELT_20MS);
+    assert!(audio[1..51].iter().all(|p| p.0 == SILENT_FRAME));
+    let last = audio.last().unwrap();
+    assert!(last.2);
+    // granule positions count the samples the decoder skips
+    assert_eq!(last.1, 960 * 53 + OPUS_PRE_SKIP as u64);
+}
+
+#[test]
+fn empty_stream_is_still_valid() {
+    let writer = OggOpusWriter::new(Vec::new(), 1, 2).unwrap();
+    let packets = read_back(writer.finish().unwrap());
+    assert_eq!(packets.len(), 3);
+    assert!(packets[2].2);
+}
+
+#[test]
+fn rejects_invalid_packets() {
+    let mut writer = OggOpusWriter::new(Vec::new(), 1, 2).unwrap();
+    assert!(writer.write_packet(&[]).is_err());
+    assert_eq!(writer.granule(), 0);
+}
@@ -0,0 +1,131 @@
+use scripty_audio_utils::{redact, redact_words, RedactionRules, Word};
+
+fn all_rules() -> RedactionRules {
+    RedactionRules {
+        profanity: true,
+        phone_numbers: true,
+        emails: true,
+        cards: true,
+        addresses: true,
+        words: Vec::new(),
+    }
+}
+
+#[test]
+fn nothing_changes_without_rules() {
+    let text = "call me at 555 0134, it's shit";
+    assert_eq!(redact(text, &RedactionRules::default()), text);
+}
+
+#[test]
+fn written_phone_numbers() {
+    assert_eq!(
+        redact("call me on (555) 867-5309 tomorrow.", &all_rules()),
+        "call me on [phone number] tomorrow."
+    );
+    assert_eq!(
+        redact("my number is +44 7700 900123.", &all_rules()),
+        "my number is [phone number]."
+    );
+}
+
+#[test]
+fn spelled_out_phone_numbers() {
+    assert_eq!(
+        redact(
+            "it's five five five oh one three four double two call me",
+            &all_rules()
+        ),
+        "it's [phone number] call me"
+    );
+}
+
+#[test]
+fn short_numbers_are_kept() {
+    let text = "i rolled a 20 and then a one two three";
+    assert_eq!(redact(text, &all_rules()), text);
+}
+
+#[test]
+fn cards_need_a_valid_checksum() {
+    assert_eq!(
+        redact("card is 4111 1111 1111 1111 thanks", &all_rules()),
+        "card is [card number] thanks"
+    );
+    // 16 digits that fail the checksum and are too long for a phone number
+    let text = "serial 4111 1111 1111 1112 thanks";
+    assert_eq!(redact(text, &all_rules()), text);
+}
+
+#[test]
+fn emails() {
+    assert_eq!(
+        redact("mail jo.smith@example.com, thanks", &all_rules()),
+        "mail [email], thanks"
+    );
+    assert_eq!(
+        redact(
+            "it's jo dot smith at example dot co dot uk okay",
+            &all_rules()
+        ),
+        "it's [email] okay"
+    );
+    // "at" alone doesn't make a email
+    let text = "look at this";
+    assert_eq!(redact(text, &all_rules()), text);
+}
+
+#[test]
+fn addresses() {
+    assert_eq!(
+        redact("i live at 221 baker street, london", &all_rules()),
+        "i live at [address], london"
+    );
+    assert_eq!(
+        redact("meet at twelve old oak lane later", &all_rules()),
+        "meet at [address] later"
+    );
+}
+
+#[test]
+fn profanity_keeps_the_first_letter() {
+    assert_eq!(
+        redact("oh Shit, fucking hell", &all_rules()),
+        "oh S***, f****** hell"
+    );
+}
+
+#[test]
+fn extra_words_without_profanity() {
+    let rules = RedactionRules {
+        words: vec!["Alice".to_string()],
+        ..Default::default()
+    };
+    assert_eq!(redact("alice said shit", &rules), "a**** said shit");
+}
+
+#[test]
+fn rules_by_name() {
+    let rules = RedactionRules::from_enabled(&["email", "card", "nonsense"], Vec::new());
+    assert!(rules.emails && rules.cards);
+    assert!(!rules.profanity && !rules.phone_numbers && !rules.addresses);
+    assert_eq!(rules.enabled(), vec!["email", "card"]);
+}
+
+#[test]
+fn words_keep_their_timings() {
+    let words: Vec<Word> = "mail jo at example dot com damn"
+        .split(' ')
+        .enumerate()
+        .map(|(i, w)| Word {
+            text: w.to_string(),
+            start_ms: i as u32 * 100,
+            end_ms: i as u32 * 100 + 90,
+        })
+        .collect();
+    let redacted = redact_words(&words, &all_rules());
+    let texts: Vec<_> = redacted.iter().map(|w| w.text.as_str()).collect();
+    assert_eq!(texts, vec!["mail", "[email]", "d***"]);
+    assert_eq!((redacted[1].start_ms, redacted[1].end_ms), (100, 590));
+    assert_eq!((redacted[2].start_ms, redacted[2].end_ms), (600, 690));
+}
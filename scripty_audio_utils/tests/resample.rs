@@ -0,0 +1,99 @@
+use scripty_audio_utils::{hz_to_hz, Resampler};
+use std::f64::consts::PI;
+
+fn sine(freq: f64, sample_rate: u32, seconds: f64, amplitude: f64) -> Vec<i16> {
+    (0..(sample_rate as f64 * seconds) as usize)
+        .map(|n| (amplitude * (2.0 * PI * freq * n as f64 / sample_rate as f64).sin()) as i16)
+        .collect()
+}
+
+fn rms(audio: &[i16]) -> f64 {
+    (audio.iter().map(|s| (*s as f64).powi(2)).sum::<f64>() / audio.len() as f64).sqrt()
+}
+
+/// Amplitude of the `freq` component of `audio`, using a single DFT bin.
+fn amplitude_at(audio: &[i16], freq: f64, sample_rate: u32) -> f64 {
+    let w = 2.0 * PI * freq / sample_rate as f64;
+    let (re, im) = audio
+        .iter()
+        .enumerate()
+        .fold((0.0, 0.0), |(re, im), (n, s)| {
+            let s = *s as f64;
+            (re + s * (w * n as f64).cos(), im - s * (w * n as f64).sin())
+        });
+    2.0 * (re * re + im * im).sqrt() / audio.len() as f64
+}
+
+fn db(ratio: f64) -> f64 {
+    20.0 * ratio.log10()
+}
+
+#[test]
+fn output_length_matches_rate() {
+    for (from, to) in [
+        (48_000, 16_000),
+        (44_100, 16_000),
+        (16_000, 48_000),
+        (48_000, 22_050),
+    ] {
+        let input = sine(440.0, from, 1.0, 10_000.0);
+        assert_eq!(hz_to_hz(&input, from, to).len(), to as usize);
+    }
+}
+
+#[test]
+fn passband_is_preserved() {
+    for (from, to) in [(48_000, 16_000), (44_100, 16_000), (16_000, 48_000)] {
+        let output = hz_to_hz(&sine(1_000.0, from, 1.0, 10_000.0), from, to);
+        // skip the edges, where the filter is still filling up
+        let steady = &output[to as usize / 10..to as usize * 9 / 10];
+        let gain = db(amplitude_at(steady, 1_000.0, to) / 10_000.0);
+        assert!(gain.abs() < 0.1, "{} -> {}: gain {:.3} dB", from, to, gain);
+    }
+}
+
+#[test]
+fn output_is_not_delayed() {
+    // a low tone should come out lined up sample for sample with plain decimation of the input
+    let input = sine(300.0, 48_000, 1.0, 10_000.0);
+    let output = hz_to_hz(&input, 48_000, 16_000);
+    let worst = (1_000..15_000)
+        .map(|n| (output[n] as i32 - input[n * 3] as i32).abs())
+        .max()
+        .unwrap();
+    assert!(worst <= 4, "off by up to {}", worst);
+}
+
+#[test]
+fn aliasing_is_suppressed() {
+    // all of these are above the output's Nyquist frequency and would fold back into speech range
+    for freq in [8_500.0, 10_000.0, 15_000.0, 23_000.0] {
+        let output = hz_to_hz(&sine(freq, 48_000, 1.0, 20_000.0), 48_000, 16_000);
+        let steady = &output[1_600..14_400];
+        let level = db(rms(steady).max(1.0) / (20_000.0 / 2f64.sqrt()));
+        assert!(
+            level < -70.0,
+            "{} Hz leaked through at {:.1} dB",
+            freq,
+            level
+        );
+    }
+}
+
+#[test]
+fn streaming_matches_whole_buffer() {
+    let input = sine(440.0, 48_000, 2.0, 10_000.0);
+    let whole = hz_to_hz(&input, 48_000, 16_000);
+
+    // 20ms at a time, like Discord sends it, plus some awkward sizes
+    for chunk_size in [960, 1, 7, 4_801] {
+        let mut resampler = Resampler::new(48_000, 16_000);
+        let mut streamed = Vec::new();
+        for chunk in input.chunks(chunk_size) {
+            resampler.process(chunk, &mut streamed);
+        }
+        resampler.flush(&mut streamed);
+        streamed.truncate(whole.len());
+        assert_eq!(streamed, whole, "chunk size {}", chunk_size);
+    }
+}
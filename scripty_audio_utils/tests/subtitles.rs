@@ -0,0 +1,92 @@
+use scripty_audio_utils::{build_cues, to_srt, to_webvtt, Cue, Word, MAX_CUE_CHARS};
+
+fn word(text: &str, start_ms: u32, end_ms: u32) -> Word {
+    Word {
+        text: text.to_string(),
+        start_ms,
+        end_ms,
+    }
+}
+
+#[test]
+fn pauses_start_new_cues() {
+    let words = [
+        word("hello", 0, 400),
+        word("there", 500, 900),
+        word("general", 3_000, 3_500),
+        word("kenobi", 3_600, 4_200),
+    ];
+    let cues = build_cues(&words);
+    assert_eq!(
+        cues,
+        vec![
+            Cue {
+                text: "hello there".to_string(),
+                start_ms: 0,
+                end_ms: 900,
+            },
+            Cue {
+                text: "general kenobi".to_string(),
+                start_ms: 3_000,
+                end_ms: 4_200,
+            },
+        ]
+    );
+}
+
+#[test]
+fn long_speech_is_split() {
+    // ten minutes of talking without a pause
+    let words: Vec<Word> = (0..1_000)
+        .map(|i| word("word", i * 600, i * 600 + 500))
+        .collect();
+    let cues = build_cues(&words);
+    assert!(cues.len() > 1);
+    for cue in &cues {
+        assert!(cue.text.len() <= MAX_CUE_CHARS);
+        assert!(cue.end_ms - cue.start_ms <= 7_000);
+    }
+    let total: usize = cues.iter().map(|c| c.text.split(' ').count()).sum();
+    assert_eq!(total, words.len());
+}
+
+#[test]
+fn no_words_no_cues() {
+    assert!(build_cues(&[]).is_empty());
+    assert_eq!(to_srt(&[]), "");
+    assert_eq!(to_webvtt(&[]), "WEBVTT\n\n");
+}
+
+#[test]
+fn srt_format() {
+    let cues = [
+        Cue {
+            text: "hello there".to_string(),
+            start_ms: 1_500,
+            end_ms: 2_250,
+        },
+        Cue {
+            text: "general kenobi".to_string(),
+            start_ms: 3_723_004,
+            end_ms: 3_724_000,
+        },
+    ];
+    assert_eq!(
+        to_srt(&cues),
+        "1\n00:00:01,500 --> 00:00:02,250\nhello there\n\n\
+        2\n01:02:03,004 --> 01:02:04,000\ngeneral kenobi\n\n"
+    );
+}
+
+#[test]
+fn webvtt_format() {
+    let cues = [Cue {
+        text: "a --> b".to_string(),
+        start_ms: 61_001,
+        end_ms: 62_000,
+    }];
+    assert_eq!(
+        to_webvtt(&cues),
+        "WEBVTT\n\n00:01:01.001 --> 00:01:02.000\na -> b\n\n"
+    );
+}
@@ -0,0 +1,103 @@
+use scripty_audio_utils::{Candidate, RedactionRules, Token, Transcript, Utterance, Word};
+
+fn token(text: &str, start_ms: u32, end_ms: u32) -> Token {
+    Token {
+        text: text.to_string(),
+        start_ms,
+        end_ms,
+    }
+}
+
+fn candidate() -> Candidate {
+    Candidate {
+        text: "call 555 0134 now".to_string(),
+        confidence: 0.7,
+        tokens: vec![
+            token("call", 100, 400),
+            token(" 555", 500, 900),
+            token(" 0134", 900, 1_500),
+            token(" now", 1_600, 1_900),
+        ],
+        segments: Vec::new(),
+    }
+}
+
+#[test]
+fn words_are_redacted_with_the_text() {
+    let rules = RedactionRules {
+        phone_numbers: true,
+        ..Default::default()
+    };
+    let u = Utterance::new(42, "alice".to_string(), &candidate(), 0.7, 2_000, &rules);
+    assert_eq!(u.text, "call [phone number] now");
+    assert_eq!(
+        u.words,
+        vec![
+            Word {
+                text: "call".to_string(),
+                start_ms: 100,
+                end_ms: 400
+            },
+            Word {
+                text: "[phone number]".to_string(),
+                start_ms: 500,
+                end_ms: 1_500
+            },
+            Word {
+                text: "now".to_string(),
+                start_ms: 1_600,
+                end_ms: 1_900
+            },
+        ]
+    );
+    // the silence before the first word isn't part of it
+    assert_eq!((u.start_ms, u.end_ms), (100, 2_000));
+}
+
+#[test]
+fn shift_moves_every_timing() {
+    let mut u = Utterance::new(
+        42,
+        "alice".to_string(),
+        &candidate(),
+        0.7,
+        2_000,
+        &RedactionRules::default(),
+    );
+    u.shift(60_000);
+    assert_eq!((u.start_ms, u.end_ms), (60_100, 62_000));
+    assert_eq!(u.duration_ms(), 1_900);
+    assert_eq!(u.words[0].start_ms, 60_100);
+    assert_eq!(u.words[3].end_ms, 61_900);
+}
+
+#[test]
+fn end_covers_padded_tokens() {
+    let u = Utterance::new(
+        42,
+        "alice".to_string(),
+        &candidate(),
+        0.7,
+        1_000,
+        &RedactionRules::default(),
+    );
+    assert_eq!(u.end_ms, 1_900);
+}
+
+#[test]
+fn transcripts_shift_with_their_audio() {
+    let mut t = Transcript::new(vec![candidate()]);
+    t.shift(250);
+    let c = t.best().unwrap();
+    assert_eq!((c.start_ms(), c.end_ms()), (350, 2_150));
+    let u = Utterance::new(
+        42,
+        "alice".to_string(),
+        c,
+        0.7,
+        2_000,
+        &RedactionRules::default(),
+    );
+    assert_eq!(u.start_ms, 350);
+    assert_eq!(u.words[0].start_ms, 350);
+}
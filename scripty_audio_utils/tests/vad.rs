@@ -0,0 +1,142 @@
+use scripty_audio_utils::{
+    speech_range, trim_silence, VadEvent, VadSettings, VoiceActivityDetector,
+};
+use std::f64::consts::PI;
+
+const RATE: u32 = 16_000;
+
+/// `ms` of a low tone loud enough to count as speech.
+fn speech(ms: u32) -> Vec<i16> {
+    (0..(RATE * ms / 1000) as usize)
+        .map(|n| (10_000.0 * (2.0 * PI * 200.0 * n as f64 / RATE as f64).sin()) as i16)
+        .collect()
+}
+
+fn silence(ms: u32) -> Vec<i16> {
+    vec![0; (RATE * ms / 1000) as usize]
+}
+
+/// Deterministic hiss, above the threshold but crossing zero far too often to be speech.
+fn hiss(ms: u32) -> Vec<i16> {
+    let mut state = 0x2545_f491_u32;
+    (0..(RATE * ms / 1000) as usize)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            ((state as f64 / u32::MAX as f64 * 2.0 - 1.0) * 600.0) as i16
+        })
+        .collect()
+}
+
+fn detector() -> VoiceActivityDetector {
+    VoiceActivityDetector::new(VadSettings::default(), RATE, 1)
+}
+
+#[test]
+fn silence_before_speech_is_silence() {
+    let mut vad = detector();
+    // no pause either, there's nothing to end yet
+    assert_eq!(vad.push(&silence(2_000)), VadEvent::Silence);
+    assert!(!vad.heard_speech());
+}
+
+#[test]
+fn hiss_is_not_speech() {
+    let mut vad = detector();
+    assert_eq!(vad.push(&hiss(1_000)), VadEvent::Silence);
+    assert!(!vad.heard_speech());
+}
+
+#[test]
+fn speech_then_pause() {
+    let mut vad = detector();
+    assert_eq!(vad.push(&speech(500)), VadEvent::Speech);
+    assert!(vad.heard_speech());
+    assert_eq!(vad.push(&silence(1_000)), VadEvent::Pause);
+    assert!(!vad.heard_speech());
+    // one pause per utterance
+    assert_eq!(vad.push(&silence(1_000)), VadEvent::Silence);
+}
+
+#[test]
+fn short_pauses_are_held_over() {
+    let settings = VadSettings::default();
+    let mut vad = detector();
+    vad.push(&speech(500));
+    // a frame short of ending the utterance
+    assert_eq!(
+        vad.push(&silence(settings.min_silence_ms - 20)),
+        VadEvent::Silence
+    );
+    assert!(vad.heard_speech());
+    // talking again starts the count over
+    assert_eq!(vad.push(&speech(100)), VadEvent::Speech);
+    assert_eq!(
+        vad.push(&silence(settings.min_silence_ms - 20)),
+        VadEvent::Silence
+    );
+    assert_eq!(vad.push(&silence(20)), VadEvent::Pause);
+}
+
+#[test]
+fn partial_frames_carry_over() {
+    let mut vad = detector();
+    let audio = speech(100);
+    // 7 samples at a time never fills a frame in one push
+    let events: Vec<_> = audio.chunks(7).map(|c| vad.push(c)).collect();
+    assert!(events.contains(&VadEvent::Speech));
+    assert!(vad.heard_speech());
+}
+
+#[test]
+fn reset_forgets_speech() {
+    let mut vad = detector();
+    vad.push(&speech(500));
+    vad.reset();
+    assert!(!vad.heard_speech());
+    assert_eq!(vad.push(&silence(1_000)), VadEvent::Silence);
+}
+
+#[test]
+fn trim_keeps_padding_around_speech() {
+    let settings = VadSettings::default();
+    let mut audio = silence(1_000);
+    audio.extend(speech(500));
+    audio.extend(silence(1_000));
+
+    let trimmed = trim_silence(&audio, RATE, &settings);
+    let padding = (RATE * settings.padding_ms / 1000) as usize;
+    assert_eq!(trimmed.len(), speech(500).len() + padding * 2);
+
+    // it starts `padding` before the speech does
+    let range = speech_range(&audio, RATE, &settings);
+    assert_eq!(range.start, silence(1_000).len() - padding);
+    assert_eq!(&audio[range], trimmed);
+}
+
+#[test]
+fn trim_padding_stops_at_the_edges() {
+    let mut audio = speech(500);
+    audio.extend(silence(50));
+    let trimmed = trim_silence(&audio, RATE, &VadSettings::default());
+    assert_eq!(trimmed.len(), audio.len());
+}
+
+#[test]
+fn trim_all_silence_is_empty() {
+    assert!(trim_silence(&silence(1_000), RATE, &VadSettings::default()).is_empty());
+    assert!(trim_silence(&[], RATE, &VadSettings::default()).is_empty());
+    assert_eq!(
+        speech_range(&silence(1_000), RATE, &VadSettings::default()),
+        0..0
+    );
+}
+
+#[test]
+fn very_low_sample_rates_dont_panic() {
+    let audio = vec![10_000; 100];
+    trim_silence(&audio, 10, &VadSettings::default());
+    let mut vad = VoiceActivityDetector::new(VadSettings::default(), 10, 1);
+    vad.push(&audio);
+}
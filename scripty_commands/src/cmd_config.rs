@@ -0,0 +1,1619 @@
+use scripty_audio::{
+    guild_policy, guild_settings, load_guild_settings, update_guild_policy, update_guild_settings,
+    SpeakerRoles,
+};
+use scripty_audio_utils::{
+    load_model, unload_model, ExportFormat, HotWord, ModelKey, RedactionRules, EXPORT_FORMATS,
+    REDACTION_RULES,
+};
+use scripty_config::BotConfig;
+use scripty_db::PgPoolKey;
+use scripty_macros::handle_serenity_error;
+use serenity::{
+    builder::CreateEmbed,
+    client::Context,
+    framework::standard::{macros::command, Args, CommandResult},
+    model::{
+        id::{GuildId, RoleId},
+        prelude::{Attachment, Message},
+    },
+};
+use sqlx::{query, PgPool};
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+use tokio::fs;
+
+#[command("config")]
+#[aliases("settings", "cfg")]
+#[required_permissions("MANAGE_GUILD")]
+#[only_in("guilds")]
+#[bucket = "general"]
+#[sub_commands(
+    cmd_config_vad,
+    cmd_config_denoise,
+    cmd_config_language,
+    cmd_config_hotwords,
+    cmd_config_scorer,
+    cmd_config_confidence,
+    cmd_config_redact,
+    cmd_config_export,
+    cmd_config_consent,
+    cmd_config_roles
+)]
+#[description = "See how I'm set up to transcribe this server. Use the subcommands to change it."]
+async fn cmd_config(ctx: &Context, msg: &Message) -> CommandResult {
+    let guild_id = match msg.guild_id {
+        Some(g) => g,
+        None => return Ok(()),
+    };
+    if !load_settings(ctx, msg, guild_id).await {
+        return Ok(());
+    }
+    let settings = guild_settings(guild_id);
+    let policy = guild_policy(guild_id);
+
+    let mut embed = CreateEmbed::default();
+    embed
+        .title("Transcription settings")
+        .field(
+            "Voice detection (`config vad`)",
+            format!(
+                "Speech threshold: {}dB\nPause length: {}ms",
+                settings.vad.threshold_db, settings.vad.min_silence_ms
+            ),
+            false,
+        )
+        .field(
+            "Noise suppression (`config denoise`)",
+            if settings.denoise { "On" } else { "Off" },
+            false,
+        )
+        .field(
+            "Language (`config language`)",
+            match &settings.language {
+                Some(l) => l.clone(),
+                None => format!(
+                    "Default ({})",
+                    BotConfig::get().map_or("en", |c| c.default_language())
+                ),
+            },
+            false,
+        )
+        .field(
+            "Hot words (`config hotwords`)",
+            if settings.hot_words.is_empty() {
+                "None".to_string()
+            } else {
+                format_hot_words(&settings.hot_words)
+            },
+            false,
+        )
+        .field(
+            "Confidence threshold (`config confidence`)",
+            format!(
+                "{}%{}, {}",
+                (settings.confidence_threshold() * 100.0).round(),
+                if settings.min_confidence.is_none() {
+                    " (default)"
+                } else {
+                    ""
+                },
+                if settings.mark_low_confidence {
+                    "unsure transcripts are marked"
+                } else {
+                    "unsure transcripts are dropped"
+                }
+            ),
+            false,
+        )
+        .field(
+            "Redaction (`config redact`)",
+            format_redaction(&settings.redaction),
+            false,
+        )
+        .field(
+            "Session transcripts (`config export`)",
+            match policy.transcript_format {
+                Some(f) => format!("Sent as `{}` when everyone leaves", f.name()),
+                None => "Off".to_string(),
+            },
+            false,
+        )
+        .field(
+            "Consent (`config consent`)",
+            if policy.strict_consent {
+                "Everyone has to agree before they're transcribed"
+            } else {
+                "Everyone's transcribed unless they opt out"
+            },
+            false,
+        )
+        .field(
+            "Speaker roles (`config roles`)",
+            format_speaker_roles(&policy.speaker_roles),
+            false,
+        )
+        .field(
+            "Custom language model (`config scorer`)",
+            match settings.scorer {
+                Some(s) => format!("Built for `{}`", s.language),
+                None => "None".to_string(),
+            },
+            false,
+        );
+
+    if let Err(e) = msg
+        .channel_id
+        .send_message(&ctx, |m| {
+            m.embed(|e| {
+                *e = embed;
+                e
+            })
+        })
+        .await
+    {
+        handle_serenity_error!(e);
+    }
+    Ok(())
+}
+
+/// Load the guild's settings from the DB, that way they're up to date even when there's no
+/// session running that would've loaded them already. If they couldn't be, this tells the
+/// user why and returns false.
+async fn load_settings(ctx: &Context, msg: &Message, guild_id: GuildId) -> bool {
+    let e = match load_guild_settings(guild_id).await {
+        Ok(_) => return true,
+        Err(e) => e,
+    };
+    tracing::warn!("Couldn't load settings for {}: {}", guild_id, e);
+    if let Err(e) = msg
+        .channel_id
+        .send_message(&ctx, |m| {
+            m.embed(|e| {
+                e.title("I couldn't load this server's settings")
+                    .description("Make sure I'm set up here with `setup`, then try again.")
+            })
+        })
+        .await
+    {
+        handle_serenity_error!(e);
+    }
+    false
+}
+
+#[command("vad")]
+#[required_permissions("MANAGE_GUILD")]
+#[only_in("guilds")]
+#[bucket = "expensive"]
+#[num_args(2)]
+#[description = "Change how I tell when someone stops talking.\nThe first number is how loud \
+(in dB, from -90 to 0) audio has to be to count as speech: lower picks up quieter voices, higher \
+ignores more background noise. The second is how long (in milliseconds) someone has to pause for \
+me to send what they said so far."]
+#[usage = "<threshold dB> <pause ms>"]
+#[example = "-45 800"]
+async fn cmd_config_vad(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let guild_id = match msg.guild_id {
+        Some(g) => g,
+        None => return Ok(()),
+    };
+    if !load_settings(ctx, msg, guild_id).await {
+        return Ok(());
+    }
+    let mut embed = CreateEmbed::default();
+
+    let threshold = args.single::<f32>();
+    let silence = args.single::<u32>();
+    match (threshold, silence) {
+        (Ok(threshold), Ok(silence))
+            if (-90.0..=0.0).contains(&threshold) && (200..=10_000).contains(&silence) =>
+        {
+            let data = ctx.data.read().await;
+            let db = unsafe { data.get::<PgPoolKey>().unwrap_unchecked() };
+            match query!(
+                "UPDATE guilds SET vad_threshold = $1, vad_silence_ms = $2 WHERE guild_id = $3",
+                threshold,
+                silence as i32,
+                guild_id.0 as i64
+            )
+            .execute(db)
+            .await
+            {
+                Ok(r) if r.rows_affected() == 0 => {
+                    embed
+                        .title("I'm not set up here yet")
+                        .description("Run `setup` first, then try again.");
+                }
+                Ok(_) => {
+                    update_guild_settings(guild_id, |s| {
+                        s.vad.threshold_db = threshold;
+                        s.vad.min_silence_ms = silence;
+                    });
+                    embed.description(format!(
+                        "Got it! Audio louder than {}dB counts as speech now, and I'll send \
+                        what someone said after they pause for {}ms.",
+                        threshold, silence
+                    ));
+                }
+                Err(err) => {
+                    tracing::error!("Couldn't update VAD settings: {}", err);
+                    embed
+                        .title("Ugh, I couldn't write that down..")
+                        .description(
+                            "I just let my developer know, until then you could just try again",
+                        );
+                }
+            }
+        }
+        _ => {
+            embed.title("Those numbers don't look right").description(
+                "The threshold has to be between -90 and 0 dB, and the pause between \
+                    200 and 10000 milliseconds.",
+            );
+        }
+    }
+
+    if let Err(e) = msg
+        .channel_id
+        .send_message(&ctx, |m| {
+            m.embed(|e| {
+                *e = embed;
+                e
+            })
+        })
+        .await
+    {
+        handle_serenity_error!(e);
+    }
+    Ok(())
+}
+
+#[command("denoise")]
+#[required_permissions("MANAGE_GUILD")]
+#[only_in("guilds")]
+#[bucket = "expensive"]
+#[num_args(1)]
+#[description = "Turn background noise suppression on or off.\nIf I keep transcribing fans, \
+keyboards or music as words, turning this on should help. It can make quiet voices a little harder \
+for me to pick up."]
+#[usage = "<on/off>"]
+#[example = "on"]
+async fn cmd_config_denoise(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let guild_id = match msg.guild_id {
+        Some(g) => g,
+        None => return Ok(()),
+    };
+    if !load_settings(ctx, msg, guild_id).await {
+        return Ok(());
+    }
+    let mut embed = CreateEmbed::default();
+
+    let denoise = match args.single::<String>().map(|a| a.to_lowercase()).as_deref() {
+        Ok("on") | Ok("true") | Ok("yes") => Some(true),
+        Ok("off") | Ok("false") | Ok("no") => Some(false),
+        _ => None,
+    };
+    match denoise {
+        Some(denoise) => {
+            let data = ctx.data.read().await;
+            let db = unsafe { data.get::<PgPoolKey>().unwrap_unchecked() };
+            match query!(
+                "UPDATE guilds SET denoise = $1 WHERE guild_id = $2",
+                denoise,
+                guild_id.0 as i64
+            )
+            .execute(db)
+            .await
+            {
+                Ok(r) if r.rows_affected() == 0 => {
+                    embed
+                        .title("I'm not set up here yet")
+                        .description("Run `setup` first, then try again.");
+                }
+                Ok(_) => {
+                    update_guild_settings(guild_id, |s| s.denoise = denoise);
+                    embed.description(if denoise {
+                        "Got it! I'll try to filter out background noise before transcribing."
+                    } else {
+                        "Got it! I won't filter out background noise anymore."
+                    });
+                }
+                Err(err) => {
+                    tracing::error!("Couldn't update denoise setting: {}", err);
+                    embed
+                        .title("Ugh, I couldn't write that down..")
+                        .description(
+                            "I just let my developer know, until then you could just try again",
+                        );
+                }
+            }
+        }
+        None => {
+            embed
+                .title("That doesn't look right")
+                .description("Noise suppression can only be `on` or `off`.");
+        }
+    }
+
+    if let Err(e) = msg
+        .channel_id
+        .send_message(&ctx, |m| {
+            m.embed(|e| {
+                *e = embed;
+                e
+            })
+        })
+        .await
+    {
+        handle_serenity_error!(e);
+    }
+    Ok(())
+}
+
+#[command("language")]
+#[aliases("lang")]
+#[required_permissions("MANAGE_GUILD")]
+#[only_in("guilds")]
+#[bucket = "expensive"]
+#[max_args(1)]
+#[description = "Pick the language I transcribe this server in.\nRun it without a language to see \
+which ones I know, or use `default` to go back to the default one."]
+#[usage = "[language code/default]"]
+#[example = "de"]
+async fn cmd_config_language(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let guild_id = match msg.guild_id {
+        Some(g) => g,
+        None => return Ok(()),
+    };
+    if !load_settings(ctx, msg, guild_id).await {
+        return Ok(());
+    }
+    let config = BotConfig::get().expect("Failed to load config!");
+    let languages = config.languages();
+    let mut embed = CreateEmbed::default();
+
+    let language = match args.single::<String>().map(|a| a.to_lowercase()) {
+        Ok(l) if l == "default" => Some(None),
+        Ok(l) if languages.contains(&l.as_str()) => Some(Some(l)),
+        _ => None,
+    };
+    match language {
+        Some(language) => {
+            // load the model before saving anything, that way a broken model can't be picked
+            let loaded = match &language {
+                Some(l) => load_model(&ModelKey::language(l)).await.map(|_| ()),
+                None => Ok(()),
+            };
+            let data = ctx.data.read().await;
+            let db = unsafe { data.get::<PgPoolKey>().unwrap_unchecked() };
+            match loaded {
+                Ok(()) => match query!(
+                    "UPDATE guilds SET language = $1 WHERE guild_id = $2",
+                    language,
+                    guild_id.0 as i64
+                )
+                .execute(db)
+                .await
+                {
+                    Ok(r) if r.rows_affected() == 0 => {
+                        embed
+                            .title("I'm not set up here yet")
+                            .description("Run `setup` first, then try again.");
+                    }
+                    Ok(_) => {
+                        embed.description(format!(
+                            "Got it! I'll transcribe in `{}` from now on.",
+                            language
+                                .as_deref()
+                                .unwrap_or_else(|| config.default_language())
+                        ));
+                        update_guild_settings(guild_id, |s| s.language = language);
+                    }
+                    Err(err) => {
+                        tracing::error!("Couldn't update language: {}", err);
+                        embed
+                            .title("Ugh, I couldn't write that down..")
+                            .description(
+                                "I just let my developer know, until then you could just try again",
+                            );
+                    }
+                },
+                Err(err) => {
+                    tracing::error!("Couldn't load a model: {}", err);
+                    embed
+                        .title("I couldn't load that language")
+                        .description("I just let my developer know, try again later.");
+                }
+            }
+        }
+        None => {
+            embed.title("Languages I know").description(format!(
+                "{}\n\nPick one with `config language <code>`.",
+                languages
+                    .iter()
+                    .map(|l| format!("`{}`", l))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+    }
+
+    if let Err(e) = msg
+        .channel_id
+        .send_message(&ctx, |m| {
+            m.embed(|e| {
+                *e = embed;
+                e
+            })
+        })
+        .await
+    {
+        handle_serenity_error!(e);
+    }
+    Ok(())
+}
+
+/// Most hot words a guild can have. Every one of them makes decoding a little slower.
+const MAX_HOT_WORDS: i64 = 50;
+/// Boost used when none is given.
+const DEFAULT_BOOST: f32 = 5.0;
+
+fn format_hot_words(hot_words: &[HotWord]) -> String {
+    hot_words
+        .iter()
+        .map(|w| format!("`{}` ({:+})", w.word, w.boost))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[command("hotwords")]
+#[aliases("hotword", "words")]
+#[required_permissions("MANAGE_GUILD")]
+#[only_in("guilds")]
+#[bucket = "expensive"]
+#[max_args(3)]
+#[description = "Teach me words I keep getting wrong, like names, nicknames or game terms.\n\
+`add` a word with an optional boost from -20 to 20 (default 5): higher makes me more likely to \
+hear it, negative makes me less likely to. `remove` takes it back out. Run it without anything \
+to see the current list."]
+#[usage = "[add <word> [boost]/remove <word>]"]
+#[example = "add scripty 10"]
+async fn cmd_config_hotwords(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let guild_id = match msg.guild_id {
+        Some(g) => g,
+        None => return Ok(()),
+    };
+    if !load_settings(ctx, msg, guild_id).await {
+        return Ok(());
+    }
+    let mut embed = CreateEmbed::default();
+
+    let action = args.single::<String>().map(|a| a.to_lowercase());
+    let word = args.single::<String>().map(|w| w.to_lowercase());
+    let boost = if args.is_empty() {
+        Ok(DEFAULT_BOOST)
+    } else {
+        args.single::<f32>()
+    };
+
+    let data = ctx.data.read().await;
+    let db = unsafe { data.get::<PgPoolKey>().unwrap_unchecked() };
+    match (action.as_deref(), word, boost) {
+        (Ok("add"), Ok(word), Ok(boost)) if (-20.0..=20.0).contains(&boost) => {
+            let count = match query!(
+                "SELECT COUNT(*) AS \"count!\" FROM hot_words WHERE guild_id = $1 AND word != $2",
+                guild_id.0 as i64,
+                word
+            )
+            .fetch_one(db)
+            .await
+            {
+                Ok(r) => Ok(r.count),
+                Err(e) => Err(e),
+            };
+            let res = match count {
+                Ok(c) if c >= MAX_HOT_WORDS => Ok(false),
+                Ok(_) => query!(
+                    "INSERT INTO hot_words (guild_id, word, boost) VALUES ($1, $2, $3) \
+                    ON CONFLICT (guild_id, word) DO UPDATE SET boost = $3",
+                    guild_id.0 as i64,
+                    word,
+                    boost
+                )
+                .execute(db)
+                .await
+                .map(|_| true),
+                Err(e) => Err(e),
+            };
+            match res {
+                Ok(true) => {
+                    embed.description(format!(
+                        "Got it! I'll listen out for `{}` with a boost of {}.",
+                        word, boost
+                    ));
+                    update_guild_settings(guild_id, |s| {
+                        match s.hot_words.iter_mut().find(|w| w.word == word) {
+                            Some(w) => w.boost = boost,
+                            None => s.hot_words.push(HotWord { word, boost }),
+                        }
+                    });
+                }
+                Ok(false) => {
+                    embed.title("That's too many words").description(format!(
+                        "This server can only have {} hot words, remove some first.",
+                        MAX_HOT_WORDS
+                    ));
+                }
+                Err(err) => {
+                    tracing::error!("Couldn't add a hot word: {}", err);
+                    embed
+                        .title("Ugh, I couldn't write that down..")
+                        .description(
+                            "I just let my developer know, until then you could just try again",
+                        );
+                }
+            }
+        }
+        (Ok("remove"), Ok(word), _) => match query!(
+            "DELETE FROM hot_words WHERE guild_id = $1 AND word = $2",
+            guild_id.0 as i64,
+            word
+        )
+        .execute(db)
+        .await
+        {
+            Ok(r) if r.rows_affected() == 0 => {
+                embed.description(format!("`{}` isn't a hot word here.", word));
+            }
+            Ok(_) => {
+                embed.description(format!("Got it! `{}` isn't a hot word anymore.", word));
+                update_guild_settings(guild_id, |s| s.hot_words.retain(|w| w.word != word));
+            }
+            Err(err) => {
+                tracing::error!("Couldn't remove a hot word: {}", err);
+                embed
+                    .title("Ugh, I couldn't write that down..")
+                    .description(
+                        "I just let my developer know, until then you could just try again",
+                    );
+            }
+        },
+        (Err(_), _, _) => {
+            let hot_words = guild_settings(guild_id).hot_words;
+            embed
+                .title("Hot words")
+                .description(if hot_words.is_empty() {
+                    "There aren't any yet. Add one with `config hotwords add <word> [boost]`."
+                        .to_string()
+                } else {
+                    format_hot_words(&hot_words)
+                });
+        }
+        _ => {
+            embed.title("That doesn't look right").description(
+                "Use `config hotwords add <word> [boost]` with a boost between -20 and 20, \
+                or `config hotwords remove <word>`.",
+            );
+        }
+    }
+
+    if let Err(e) = msg
+        .channel_id
+        .send_message(&ctx, |m| {
+            m.embed(|e| {
+                *e = embed;
+                e
+            })
+        })
+        .await
+    {
+        handle_serenity_error!(e);
+    }
+    Ok(())
+}
+
+/// Biggest corpus a guild can upload, in bytes.
+const MAX_CORPUS_BYTES: u64 = 8 * 1024 * 1024;
+
+/// Get the directory a guild's custom scorers and corpus are kept in.
+pub(crate) fn guild_scorer_dir(config: &BotConfig, guild_id: GuildId) -> PathBuf {
+    Path::new(config.scorer_dir()).join(guild_id.0.to_string())
+}
+
+/// Get the path a guild's corpus waiting for review is saved at.
+pub(crate) fn guild_corpus_path(config: &BotConfig, guild_id: GuildId) -> PathBuf {
+    guild_scorer_dir(config, guild_id).join("corpus.txt")
+}
+
+#[command("scorer")]
+#[aliases("lm")]
+#[required_permissions("MANAGE_GUILD")]
+#[only_in("guilds")]
+#[bucket = "expensive"]
+#[max_args(1)]
+#[description = "Premium only: teach me how this server talks, so I get more of its words right.\n\
+Attach a `.txt` file of text like what gets said here (session notes, glossaries, docs, old \
+transcripts) and I'll build a language model from it once my developers have looked it over. It's \
+built for the language picked right now. Use `remove` to go back to the normal one."]
+#[usage = "[remove]"]
+#[example = "remove"]
+async fn cmd_config_scorer(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let guild_id = match msg.guild_id {
+        Some(g) => g,
+        None => return Ok(()),
+    };
+    if !load_settings(ctx, msg, guild_id).await {
+        return Ok(());
+    }
+    let config = BotConfig::get().expect("Failed to load config!");
+    let mut embed = CreateEmbed::default();
+
+    let data = ctx.data.read().await;
+    let db = unsafe { data.get::<PgPoolKey>().unwrap_unchecked() };
+    let premium_level = match query!(
+        "SELECT premium_level FROM guilds WHERE guild_id = $1",
+        guild_id.0 as i64
+    )
+    .fetch_optional(db)
+    .await
+    {
+        Ok(r) => r.map(|r| r.premium_level),
+        Err(err) => {
+            tracing::error!("Couldn't fetch premium level: {}", err);
+            None
+        }
+    };
+
+    match (
+        args.single::<String>().map(|a| a.to_lowercase()),
+        premium_level,
+    ) {
+        _ if config.scorer_tools().is_none() => {
+            embed
+                .title("Not available")
+                .description("Custom language models aren't set up on this bot.");
+        }
+        (_, None) => {
+            embed
+                .title("I'm not set up here yet")
+                .description("Run `setup` first, then try again.");
+        }
+        (Ok(a), _) if a == "remove" => {
+            let res = query!(
+                "DELETE FROM custom_scorers WHERE guild_id = $1",
+                guild_id.0 as i64
+            )
+            .execute(db)
+            .await;
+            match res {
+                Ok(_) => {
+                    let old = guild_settings(guild_id).scorer;
+                    update_guild_settings(guild_id, |s| s.scorer = None);
+                    if let Some(old) = old {
+                        unload_model(&ModelKey {
+                            language: old.language,
+                            scorer: Some(old.path),
+                        });
+                    }
+                    if let Err(e) = fs::remove_dir_all(guild_scorer_dir(config, guild_id)).await {
+                        if e.kind() != io::ErrorKind::NotFound {
+                            tracing::warn!("Couldn't delete custom scorer: {}", e);
+                        }
+                    }
+                    embed.description(
+                        "Got it! I'm back to the normal language model, and deleted yours.",
+                    );
+                }
+                Err(err) => {
+                    tracing::error!("Couldn't remove custom scorer: {}", err);
+                    embed
+                        .title("Ugh, I couldn't write that down..")
+                        .description(
+                            "I just let my developer know, until then you could just try again",
+                        );
+                }
+            }
+        }
+        (Ok(_), _) => {
+            embed.title("That doesn't look right").description(
+                "Attach a `.txt` file to `config scorer` to upload text, or use \
+                `config scorer remove`.",
+            );
+        }
+        (Err(_), Some(level)) if !msg.attachments.is_empty() && level < 1 => {
+            embed
+                .title("Premium only")
+                .description("Custom language models are only available for premium servers.");
+        }
+        (Err(_), Some(_)) if !msg.attachments.is_empty() => {
+            let attachment = &msg.attachments[0];
+            if !attachment.filename.ends_with(".txt") || attachment.size > MAX_CORPUS_BYTES {
+                embed.title("I can't use that file").description(format!(
+                    "It has to be a `.txt` file of at most {}MB.",
+                    MAX_CORPUS_BYTES / 1024 / 1024
+                ));
+            } else {
+                let language = guild_settings(guild_id)
+                    .language
+                    .unwrap_or_else(|| config.default_language().to_string());
+                match save_corpus(config, guild_id, attachment).await {
+                    Ok(()) => match query!(
+                        "INSERT INTO custom_scorers (guild_id, pending_language) VALUES ($1, $2) \
+                        ON CONFLICT (guild_id) DO UPDATE SET pending_language = $2",
+                        guild_id.0 as i64,
+                        language
+                    )
+                    .execute(db)
+                    .await
+                    {
+                        Ok(_) => {
+                            tracing::info!(
+                                "{} uploaded a {} corpus for a custom scorer",
+                                guild_id,
+                                language
+                            );
+                            embed.description(
+                                "Got it! My developers will look over your text, and once \
+                                they approve it I'll start using a language model built from it.",
+                            );
+                        }
+                        Err(err) => {
+                            tracing::error!("Couldn't save corpus upload: {}", err);
+                            embed
+                                .title("Ugh, I couldn't write that down..")
+                                .description(
+                                    "I just let my developer know, until then you could just try \
+                                    again",
+                                );
+                        }
+                    },
+                    Err(e) => {
+                        embed.title("I can't use that file").description(e);
+                    }
+                }
+            }
+        }
+        (Err(_), Some(_)) => {
+            let status = match query!(
+                "SELECT language, scorer_path, pending_language FROM custom_scorers \
+                WHERE guild_id = $1",
+                guild_id.0 as i64
+            )
+            .fetch_optional(db)
+            .await
+            {
+                Ok(Some(r)) => {
+                    let mut status = match (r.language, r.scorer_path) {
+                        (Some(l), Some(_)) => format!("In use, built for `{}`.", l),
+                        _ => "None in use.".to_string(),
+                    };
+                    if let Some(l) = r.pending_language {
+                        status.push_str(&format!("\nA `{}` upload is waiting for approval.", l));
+                    }
+                    status
+                }
+                Ok(None) => "None in use.".to_string(),
+                Err(err) => {
+                    tracing::error!("Couldn't fetch custom scorer: {}", err);
+                    "I couldn't check, try again later.".to_string()
+                }
+            };
+            embed.title("Custom language model").description(format!(
+                "{}\n\nAttach a `.txt` file to `config scorer` to upload text for a new one.",
+                status
+            ));
+        }
+    }
+
+    if let Err(e) = msg
+        .channel_id
+        .send_message(&ctx, |m| {
+            m.embed(|e| {
+                *e = embed;
+                e
+            })
+        })
+        .await
+    {
+        handle_serenity_error!(e);
+    }
+    Ok(())
+}
+
+/// Download a corpus upload and save it for review, replacing any older upload.
+///
+/// Errors are meant to be shown to the user.
+async fn save_corpus(
+    config: &BotConfig,
+    guild_id: GuildId,
+    attachment: &Attachment,
+) -> Result<(), String> {
+    let bytes = match attachment.download().await {
+        Ok(b) => b,
+        Err(e) => {
+            tracing::warn!("Couldn't download corpus: {}", e);
+            return Err("I couldn't download it, try again later.".to_string());
+        }
+    };
+    if std::str::from_utf8(&bytes).is_err() {
+        return Err("It has to be plain text.".to_string());
+    }
+    let path = guild_corpus_path(config, guild_id);
+    let res = match fs::create_dir_all(guild_scorer_dir(config, guild_id)).await {
+        Ok(()) => fs::write(&path, bytes).await,
+        Err(e) => Err(e),
+    };
+    res.map_err(|e| {
+        tracing::error!("Couldn't save corpus to {}: {}", path.display(), e);
+        "I couldn't save it, try again later.".to_string()
+    })
+}
+
+#[command("confidence")]
+#[required_permissions("MANAGE_GUILD")]
+#[only_in("guilds")]
+#[bucket = "expensive"]
+#[min_args(1)]
+#[max_args(2)]
+#[description = "Change how sure I have to be of what I heard before sending it.\nThe number is a \
+percentage from 0 to 100: raise it if coughs and keyboard noise turn into junk like \"a\" or \
+\"the\", lower it if I'm skipping real speech. After it, `drop` skips anything I'm unsure of, \
+and `mark` still sends it but marks it with a (?). Use `default` to go back to the default \
+threshold."]
+#[usage = "<percent/default> [drop/mark]"]
+#[example = "40 mark"]
+async fn cmd_config_confidence(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let guild_id = match msg.guild_id {
+        Some(g) => g,
+        None => return Ok(()),
+    };
+    if !load_settings(ctx, msg, guild_id).await {
+        return Ok(());
+    }
+    let mut embed = CreateEmbed::default();
+
+    let threshold = match args.single::<String>().map(|a| a.to_lowercase()) {
+        Ok(t) if t == "default" => Some(None),
+        Ok(t) => match t.trim_end_matches('%').parse::<f32>() {
+            Ok(t) if (0.0..=100.0).contains(&t) => Some(Some(t / 100.0)),
+            _ => None,
+        },
+        Err(_) => None,
+    };
+    // `Some(None)` keeps whatever's in the DB, which the cache might not have loaded yet
+    let mark = match args.single::<String>().map(|a| a.to_lowercase()).as_deref() {
+        Ok("mark") => Some(Some(true)),
+        Ok("drop") => Some(Some(false)),
+        Ok(_) => None,
+        Err(_) => Some(None),
+    };
+    match (threshold, mark) {
+        (Some(threshold), Some(mark)) => {
+            let data = ctx.data.read().await;
+            let db = unsafe { data.get::<PgPoolKey>().unwrap_unchecked() };
+            match query!(
+                "UPDATE guilds SET min_confidence = $1, \
+                mark_low_confidence = COALESCE($2, mark_low_confidence) \
+                WHERE guild_id = $3 RETURNING mark_low_confidence",
+                threshold,
+                mark,
+                guild_id.0 as i64
+            )
+            .fetch_optional(db)
+            .await
+            {
+                Ok(None) => {
+                    embed
+                        .title("I'm not set up here yet")
+                        .description("Run `setup` first, then try again.");
+                }
+                Ok(Some(r)) => {
+                    let mark = r.mark_low_confidence;
+                    update_guild_settings(guild_id, |s| {
+                        s.min_confidence = threshold;
+                        s.mark_low_confidence = mark;
+                    });
+                    embed.description(format!(
+                        "Got it! I'll {} anything I'm less than {}% sure of.",
+                        if mark { "mark" } else { "skip" },
+                        (guild_settings(guild_id).confidence_threshold() * 100.0).round()
+                    ));
+                }
+                Err(err) => {
+                    tracing::error!("Couldn't update confidence threshold: {}", err);
+                    embed
+                        .title("Ugh, I couldn't write that down..")
+                        .description(
+                            "I just let my developer know, until then you could just try again",
+                        );
+                }
+            }
+        }
+        _ => {
+            embed.title("That doesn't look right").description(
+                "The threshold has to be a percentage between 0 and 100 (or `default`), \
+                optionally followed by `drop` or `mark`.",
+            );
+        }
+    }
+
+    if let Err(e) = msg
+        .channel_id
+        .send_message(&ctx, |m| {
+            m.embed(|e| {
+                *e = embed;
+                e
+            })
+        })
+        .await
+    {
+        handle_serenity_error!(e);
+    }
+    Ok(())
+}
+
+/// Most extra words a guild can have masked.
+const MAX_REDACTED_WORDS: i64 = 100;
+
+fn format_redaction(rules: &RedactionRules) -> String {
+    let enabled = rules.enabled();
+    let mut out = if enabled.is_empty() {
+        "No rules on".to_string()
+    } else {
+        format!(
+            "On: {}",
+            enabled
+                .iter()
+                .map(|r| format!("`{}`", r))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    };
+    if !rules.words.is_empty() {
+        out.push_str(&format!("\nExtra words: {}", rules.words.len()));
+    }
+    out
+}
+
+#[command("redact")]
+#[aliases("censor")]
+#[required_permissions("MANAGE_GUILD")]
+#[only_in("guilds")]
+#[bucket = "expensive"]
+#[max_args(2)]
+#[description = "Hide things from transcripts before I send them.\nTurn rules on or off with \
+`<rule> <on/off>`: `profanity` masks swear words, `phone`, `email`, `card` and `address` replace \
+personal details, and `all` changes every rule at once. `add <word>` and `remove <word>` change \
+the extra words I mask, like names. Run it without anything to see what's on."]
+#[usage = "[<rule/all> <on/off>] or [add/remove <word>]"]
+#[example = "phone on"]
+async fn cmd_config_redact(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let guild_id = match msg.guild_id {
+        Some(g) => g,
+        None => return Ok(()),
+    };
+    if !load_settings(ctx, msg, guild_id).await {
+        return Ok(());
+    }
+    let mut embed = CreateEmbed::default();
+
+    let first = args.single::<String>().map(|a| a.to_lowercase());
+    let second = args.single::<String>().map(|a| a.to_lowercase());
+    let toggle = match second.as_deref() {
+        Ok("on") | Ok("true") | Ok("yes") => Some(true),
+        Ok("off") | Ok("false") | Ok("no") => Some(false),
+        _ => None,
+    };
+
+    let data = ctx.data.read().await;
+    let db = unsafe { data.get::<PgPoolKey>().unwrap_unchecked() };
+    match (first.as_deref(), second, toggle) {
+        (Ok(rule), _, Some(on)) if rule == "all" || REDACTION_RULES.contains(&rule) => {
+            let res = match query!(
+                "SELECT redact FROM guilds WHERE guild_id = $1",
+                guild_id.0 as i64
+            )
+            .fetch_optional(db)
+            .await
+            {
+                Ok(Some(r)) => {
+                    let mut rules = RedactionRules::from_enabled(&r.redact, Vec::new());
+                    for name in REDACTION_RULES
+                        .iter()
+                        .filter(|r| rule == "all" || **r == rule)
+                    {
+                        if let Some(toggle) = rules.rule_mut(name) {
+                            *toggle = on;
+                        }
+                    }
+                    let enabled: Vec<String> =
+                        rules.enabled().into_iter().map(String::from).collect();
+                    query!(
+                        "UPDATE guilds SET redact = $1 WHERE guild_id = $2",
+                        &enabled[..],
+                        guild_id.0 as i64
+                    )
+                    .execute(db)
+                    .await
+                    .map(|_| Some(enabled))
+                }
+                Ok(None) => Ok(None),
+                Err(e) => Err(e),
+            };
+            match res {
+                Ok(Some(enabled)) => {
+                    update_guild_settings(guild_id, |s| {
+                        s.redaction = RedactionRules::from_enabled(
+                            &enabled,
+                            std::mem::take(&mut s.redaction.words),
+                        )
+                    });
+                    embed.description(format!(
+                        "Got it! {} {} now.",
+                        if rule == "all" {
+                            "Every rule is".to_string()
+                        } else {
+                            format!("`{}` is", rule)
+                        },
+                        if on { "on" } else { "off" }
+                    ));
+                }
+                Ok(None) => {
+                    embed
+                        .title("I'm not set up here yet")
+                        .description("Run `setup` first, then try again.");
+                }
+                Err(err) => {
+                    tracing::error!("Couldn't update redaction rules: {}", err);
+                    embed
+                        .title("Ugh, I couldn't write that down..")
+                        .description(
+                            "I just let my developer know, until then you could just try again",
+                        );
+                }
+            }
+        }
+        (Ok("add"), Ok(word), _) => {
+            let res = match query!(
+                "SELECT COUNT(*) AS \"count!\" FROM redacted_words WHERE guild_id = $1",
+                guild_id.0 as i64
+            )
+            .fetch_one(db)
+            .await
+            {
+                Ok(r) if r.count >= MAX_REDACTED_WORDS => Ok(false),
+                Ok(_) => query!(
+                    "INSERT INTO redacted_words (guild_id, word) VALUES ($1, $2) \
+                    ON CONFLICT DO NOTHING",
+                    guild_id.0 as i64,
+                    word
+                )
+                .execute(db)
+                .await
+                .map(|_| true),
+                Err(e) => Err(e),
+            };
+            match res {
+                Ok(true) => {
+                    embed.description(format!("Got it! I'll mask `{}` from now on.", word));
+                    update_guild_settings(guild_id, |s| {
+                        if !s.redaction.words.contains(&word) {
+                            s.redaction.words.push(word)
+                        }
+                    });
+                }
+                Ok(false) => {
+                    embed.title("That's too many words").description(format!(
+                        "This server can only have {} extra words, remove some first.",
+                        MAX_REDACTED_WORDS
+                    ));
+                }
+                Err(err) => {
+                    tracing::error!("Couldn't add a redacted word: {}", err);
+                    embed
+                        .title("Ugh, I couldn't write that down..")
+                        .description(
+                            "I just let my developer know, until then you could just try again",
+                        );
+                }
+            }
+        }
+        (Ok("remove"), Ok(word), _) => match query!(
+            "DELETE FROM redacted_words WHERE guild_id = $1 AND word = $2",
+            guild_id.0 as i64,
+            word
+        )
+        .execute(db)
+        .await
+        {
+            Ok(r) if r.rows_affected() == 0 => {
+                embed.description(format!("I wasn't masking `{}` anyway.", word));
+            }
+            Ok(_) => {
+                embed.description(format!("Got it! I won't mask `{}` anymore.", word));
+                update_guild_settings(guild_id, |s| s.redaction.words.retain(|w| *w != word));
+            }
+            Err(err) => {
+                tracing::error!("Couldn't remove a redacted word: {}", err);
+                embed
+                    .title("Ugh, I couldn't write that down..")
+                    .description(
+                        "I just let my developer know, until then you could just try again",
+                    );
+            }
+        },
+        (Err(_), _, _) => {
+            let rules = guild_settings(guild_id).redaction;
+            let mut description = format_redaction(&rules);
+            if !rules.words.is_empty() {
+                description.push_str(&format!(
+                    " ({})",
+                    rules
+                        .words
+                        .iter()
+                        .map(|w| format!("`{}`", w))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ));
+            }
+            embed.title("Redaction").description(format!(
+                "{}\n\nRules: {}",
+                description,
+                REDACTION_RULES
+                    .iter()
+                    .map(|r| format!("`{}`", r))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+        _ => {
+            embed.title("That doesn't look right").description(
+                "Use `config redact <rule/all> <on/off>`, or `config redact add/remove <word>`.",
+            );
+        }
+    }
+
+    if let Err(e) = msg
+        .channel_id
+        .send_message(&ctx, |m| {
+            m.embed(|e| {
+                *e = embed;
+                e
+            })
+        })
+        .await
+    {
+        handle_serenity_error!(e);
+    }
+    Ok(())
+}
+
+#[command("export")]
+#[required_permissions("MANAGE_GUILD")]
+#[only_in("guilds")]
+#[bucket = "expensive"]
+#[num_args(1)]
+#[description = "Pick a format to send the transcript of every voice chat session in, once \
+everyone's left: `txt`, `md`, `srt` or `vtt`. `off` stops sending them.\nAnyone can still get the \
+transcript so far with `transcript`."]
+#[usage = "<txt/md/srt/vtt/off>"]
+#[example = "md"]
+async fn cmd_config_export(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let guild_id = match msg.guild_id {
+        Some(g) => g,
+        None => return Ok(()),
+    };
+    if !load_settings(ctx, msg, guild_id).await {
+        return Ok(());
+    }
+    let mut embed = CreateEmbed::default();
+
+    let format = match args.single::<String>().map(|a| a.to_lowercase()).as_deref() {
+        Ok("off") | Ok("none") => Ok(None),
+        Ok(f) => f.parse::<ExportFormat>().map(Some),
+        Err(_) => Err(()),
+    };
+    match format {
+        Ok(format) => {
+            let data = ctx.data.read().await;
+            let db = unsafe { data.get::<PgPoolKey>().unwrap_unchecked() };
+            match query!(
+                "UPDATE guilds SET transcript_format = $1 WHERE guild_id = $2",
+                format.map(ExportFormat::name),
+                guild_id.0 as i64
+            )
+            .execute(db)
+            .await
+            {
+                Ok(r) if r.rows_affected() == 0 => {
+                    embed
+                        .title("I'm not set up here yet")
+                        .description("Run `setup` first, then try again.");
+                }
+                Ok(_) => {
+                    update_guild_policy(guild_id, |p| p.transcript_format = format);
+                    embed.description(match format {
+                        Some(f) => format!(
+                            "Got it! Once everyone leaves voice chat, I'll send the whole \
+                            transcript as a `{}` file.",
+                            f.name()
+                        ),
+                        None => "Got it! I won't send transcripts when everyone leaves anymore."
+                            .to_string(),
+                    });
+                }
+                Err(err) => {
+                    tracing::error!("Couldn't update transcript format: {}", err);
+                    embed
+                        .title("Ugh, I couldn't write that down..")
+                        .description(
+                            "I just let my developer know, until then you could just try again",
+                        );
+                }
+            }
+        }
+        Err(()) => {
+            embed.title("That doesn't look right").description(format!(
+                "Transcripts can be sent as {}, or `off`.",
+                EXPORT_FORMATS
+                    .iter()
+                    .map(|f| format!("`{}`", f))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+    }
+
+    if let Err(e) = msg
+        .channel_id
+        .send_message(&ctx, |m| {
+            m.embed(|e| {
+                *e = embed;
+                e
+            })
+        })
+        .await
+    {
+        handle_serenity_error!(e);
+    }
+    Ok(())
+}
+
+#[command("consent")]
+#[required_permissions("MANAGE_GUILD")]
+#[only_in("guilds")]
+#[bucket = "expensive"]
+#[num_args(1)]
+#[description = "Turn strict consent on or off.\nWhen it's on, I won't transcribe anyone until \
+they agree to the privacy policy: I'll ask them when they join voice chat. Anyone can stop me \
+transcribing them with `transcription off`, whether this is on or not."]
+#[usage = "<on/off>"]
+#[example = "on"]
+async fn cmd_config_consent(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let guild_id = match msg.guild_id {
+        Some(g) => g,
+        None => return Ok(()),
+    };
+    if !load_settings(ctx, msg, guild_id).await {
+        return Ok(());
+    }
+    let mut embed = CreateEmbed::default();
+
+    let strict = match args.single::<String>().map(|a| a.to_lowercase()).as_deref() {
+        Ok("on") | Ok("true") | Ok("yes") => Some(true),
+        Ok("off") | Ok("false") | Ok("no") => Some(false),
+        _ => None,
+    };
+    match strict {
+        Some(strict) => {
+            let data = ctx.data.read().await;
+            let db = unsafe { data.get::<PgPoolKey>().unwrap_unchecked() };
+            match query!(
+                "UPDATE guilds SET strict_consent = $1 WHERE guild_id = $2",
+                strict,
+                guild_id.0 as i64
+            )
+            .execute(db)
+            .await
+            {
+                Ok(r) if r.rows_affected() == 0 => {
+                    embed
+                        .title("I'm not set up here yet")
+                        .description("Run `setup` first, then try again.");
+                }
+                Ok(_) => {
+                    update_guild_policy(guild_id, |p| p.strict_consent = strict);
+                    embed.description(if strict {
+                        "Got it! From now on, I'll only transcribe people who agreed to it. \
+                        Everyone else will be asked when they join voice chat."
+                    } else {
+                        "Got it! I'll transcribe everyone who hasn't opted out again."
+                    });
+                }
+                Err(err) => {
+                    tracing::error!("Couldn't update strict consent setting: {}", err);
+                    embed
+                        .title("Ugh, I couldn't write that down..")
+                        .description(
+                            "I just let my developer know, until then you could just try again",
+                        );
+                }
+            }
+        }
+        None => {
+            embed
+                .title("That doesn't look right")
+                .description("Strict consent can only be `on` or `off`.");
+        }
+    }
+
+    if let Err(e) = msg
+        .channel_id
+        .send_message(&ctx, |m| {
+            m.embed(|e| {
+                *e = embed;
+                e
+            })
+        })
+        .await
+    {
+        handle_serenity_error!(e);
+    }
+    Ok(())
+}
+
+/// Most roles a guild can allow or deny.
+const MAX_SPEAKER_ROLES: i64 = 25;
+
+/// Describe who gets transcribed.
+fn format_speaker_roles(roles: &SpeakerRoles) -> String {
+    let mention = |ids: &[u64]| {
+        ids.iter()
+            .map(|r| format!("<@&{}>", r))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let mut out = match (roles.allowed.is_empty(), roles.denied.is_empty()) {
+        (true, true) => "Everyone".to_string(),
+        (false, true) => format!("Only {}", mention(&roles.allowed)),
+        (true, false) => format!("Everyone but {}", mention(&roles.denied)),
+        (false, false) => format!(
+            "Only {}, but not {}",
+            mention(&roles.allowed),
+            mention(&roles.denied)
+        ),
+    };
+    if !roles.priority.is_empty() {
+        out.push_str(&format!(
+            "\nFirst in line for a seat: {}",
+            mention(&roles.priority)
+        ));
+    }
+    out
+}
+
+#[command("roles")]
+#[aliases("role", "speakers")]
+#[required_permissions("MANAGE_GUILD")]
+#[only_in("guilds")]
+#[bucket = "expensive"]
+#[max_args(2)]
+#[description = "Choose whose roles get them transcribed.\nOnce you `allow` a role, I only \
+transcribe people with at least one allowed role. People with a role you `deny` are never \
+transcribed, even if they have a allowed one too. When every seat is taken, people with a \
+`priority` role go ahead of everyone else in line for one. `remove` takes a role back off every \
+list. Run it without anything to see the current lists.\nThis applies to people as they join voice \
+chat."]
+#[usage = "[allow/deny/priority/remove <role>]"]
+#[example = "allow @Captioned"]
+async fn cmd_config_roles(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let guild_id = match msg.guild_id {
+        Some(g) => g,
+        None => return Ok(()),
+    };
+    if !load_settings(ctx, msg, guild_id).await {
+        return Ok(());
+    }
+    let mut embed = CreateEmbed::default();
+
+    let action = args.single::<String>().map(|a| a.to_lowercase());
+    let role = args.single::<RoleId>();
+
+    let data = ctx.data.read().await;
+    let db = unsafe { data.get::<PgPoolKey>().unwrap_unchecked() };
+    match (action.as_deref(), role) {
+        (Ok(a @ "allow"), Ok(role)) | (Ok(a @ "deny"), Ok(role)) => {
+            let allowed = a == "allow";
+            let count = match query!(
+                "SELECT COUNT(*) AS \"count!\" FROM speaker_roles \
+                WHERE guild_id = $1 AND role_id != $2",
+                guild_id.0 as i64,
+                role.0 as i64
+            )
+            .fetch_one(db)
+            .await
+            {
+                Ok(r) => Ok(r.count),
+                Err(e) => Err(e),
+            };
+            let res = match count {
+                Ok(c) if c >= MAX_SPEAKER_ROLES => Ok(false),
+                Ok(_) => query!(
+                    "INSERT INTO speaker_roles (guild_id, role_id, allowed) VALUES ($1, $2, $3) \
+                    ON CONFLICT (guild_id, role_id) DO UPDATE SET allowed = $3",
+                    guild_id.0 as i64,
+                    role.0 as i64,
+                    allowed
+                )
+                .execute(db)
+                .await
+                .map(|_| true),
+                Err(e) => Err(e),
+            };
+            match res {
+                Ok(true) => {
+                    update_guild_policy(guild_id, |p| {
+                        let roles = &mut p.speaker_roles;
+                        roles.allowed.retain(|r| *r != role.0);
+                        roles.denied.retain(|r| *r != role.0);
+                        if allowed {
+                            roles.allowed.push(role.0);
+                        } else {
+                            roles.denied.push(role.0);
+                        }
+                    });
+                    embed.description(if allowed {
+                        format!(
+                            "Got it! I'll transcribe people with <@&{}>, and only people with an \
+                            allowed role.",
+                            role
+                        )
+                    } else {
+                        format!("Got it! I won't transcribe people with <@&{}>.", role)
+                    });
+                }
+                Ok(false) => {
+                    embed.title("That's too many roles").description(format!(
+                        "This server can only allow or deny {} roles, remove some first.",
+                        MAX_SPEAKER_ROLES
+                    ));
+                }
+                Err(err) => {
+                    tracing::error!("Couldn't add a speaker role: {}", err);
+                    embed
+                        .title("Ugh, I couldn't write that down..")
+                        .description(
+                            "I just let my developer know, until then you could just try again",
+                        );
+                }
+            }
+        }
+        (Ok("priority"), Ok(role)) => {
+            let count = match query!(
+                "SELECT COUNT(*) AS \"count!\" FROM priority_roles \
+                WHERE guild_id = $1 AND role_id != $2",
+                guild_id.0 as i64,
+                role.0 as i64
+            )
+            .fetch_one(db)
+            .await
+            {
+                Ok(r) => Ok(r.count),
+                Err(e) => Err(e),
+            };
+            let res = match count {
+                Ok(c) if c >= MAX_SPEAKER_ROLES => Ok(false),
+                Ok(_) => query!(
+                    "INSERT INTO priority_roles (guild_id, role_id) VALUES ($1, $2) \
+                    ON CONFLICT DO NOTHING",
+                    guild_id.0 as i64,
+                    role.0 as i64
+                )
+                .execute(db)
+                .await
+                .map(|_| true),
+                Err(e) => Err(e),
+            };
+            match res {
+                Ok(true) => {
+                    update_guild_policy(guild_id, |p| {
+                        let priority = &mut p.speaker_roles.priority;
+                        if !priority.contains(&role.0) {
+                            priority.push(role.0);
+                        }
+                    });
+                    embed.description(format!(
+                        "Got it! When every seat is taken, people with <@&{}> go ahead in line \
+                        for one.",
+                        role
+                    ));
+                }
+                Ok(false) => {
+                    embed.title("That's too many roles").description(format!(
+                        "This server can only have {} priority roles, remove some first.",
+                        MAX_SPEAKER_ROLES
+                    ));
+                }
+                Err(err) => {
+                    tracing::error!("Couldn't add a priority role: {}", err);
+                    embed
+                        .title("Ugh, I couldn't write that down..")
+                        .description(
+                            "I just let my developer know, until then you could just try again",
+                        );
+                }
+            }
+        }
+        (Ok("remove"), Ok(role)) => match remove_speaker_role(db, guild_id, role).await {
+            Ok(0) => {
+                embed.description(format!("<@&{}> isn't on any list here.", role));
+            }
+            Ok(_) => {
+                update_guild_policy(guild_id, |p| {
+                    p.speaker_roles.allowed.retain(|r| *r != role.0);
+                    p.speaker_roles.denied.retain(|r| *r != role.0);
+                    p.speaker_roles.priority.retain(|r| *r != role.0);
+                });
+                embed.description(format!(
+                    "Got it! <@&{}> doesn't change who I transcribe anymore.",
+                    role
+                ));
+            }
+            Err(err) => {
+                tracing::error!("Couldn't remove a speaker role: {}", err);
+                embed
+                    .title("Ugh, I couldn't write that down..")
+                    .description(
+                        "I just let my developer know, until then you could just try again",
+                    );
+            }
+        },
+        (Err(_), _) => {
+            embed
+                .title("Who I transcribe")
+                .description(format_speaker_roles(&guild_policy(guild_id).speaker_roles));
+        }
+        _ => {
+            embed.title("That doesn't look right").description(
+                "Use `config roles allow <role>`, `config roles deny <role>`, \
+                `config roles priority <role>` or `config roles remove <role>`, with a role \
+                mention or ID.",
+            );
+        }
+    }
+
+    if let Err(e) = msg
+        .channel_id
+        .send_message(&ctx, |m| {
+            m.embed(|e| {
+                *e = embed;
+                e
+            })
+        })
+        .await
+    {
+        handle_serenity_error!(e);
+    }
+    Ok(())
+}
+
+/// Take a role off every speaker role list, returning how many it was on.
+async fn remove_speaker_role(db: &PgPool, guild_id: GuildId, role: RoleId) -> sqlx::Result<u64> {
+    let speaker = query!(
+        "DELETE FROM speaker_roles WHERE guild_id = $1 AND role_id = $2",
+        guild_id.0 as i64,
+        role.0 as i64
+    )
+    .execute(db)
+    .await?;
+    let priority = query!(
+        "DELETE FROM priority_roles WHERE guild_id = $1 AND role_id = $2",
+        guild_id.0 as i64,
+        role.0 as i64
+    )
+    .execute(db)
+    .await?;
+    Ok(speaker.rows_affected() + priority.rows_affected())
+}
@@ -0,0 +1,226 @@
+use scripty_audio::{
+    recording_duration, send_recording, set_recording_consent, start_recording, stop_recording,
+    MAX_RECORDING_SECS,
+};
+use scripty_db::PgPoolKey;
+use scripty_macros::handle_serenity_error;
+use serenity::{
+    builder::CreateEmbed,
+    client::Context,
+    framework::standard::{macros::command, CommandResult},
+    model::prelude::Message,
+};
+use sqlx::query;
+
+#[command("record")]
+#[aliases("recording")]
+#[only_in("guilds")]
+#[bucket = "general"]
+#[sub_commands(cmd_record_start, cmd_record_stop, cmd_record_optin, cmd_record_optout)]
+#[description = "Record voice chat, with everyone who agreed to it in their own file.\nNobody is \
+recorded unless they ran `record optin` first. Admins can `record start` and `record stop`, and \
+once it's stopped, or everyone's left, I'll send the recordings here."]
+async fn cmd_record(ctx: &Context, msg: &Message) -> CommandResult {
+    let guild_id = match msg.guild_id {
+        Some(g) => g,
+        None => return Ok(()),
+    };
+    let mut embed = CreateEmbed::default();
+    embed.title("Recording").field(
+        "Status",
+        match recording_duration(guild_id) {
+            Some(d) => format!("Recording for {} minutes", d.as_secs() / 60),
+            None => "Not recording".to_string(),
+        },
+        false,
+    );
+
+    let data = ctx.data.read().await;
+    let db = unsafe { data.get::<PgPoolKey>().unwrap_unchecked() };
+    match query!(
+        "SELECT user_id FROM recording_consent WHERE guild_id = $1 AND user_id = $2",
+        guild_id.0 as i64,
+        msg.author.id.0 as i64
+    )
+    .fetch_optional(db)
+    .await
+    {
+        Ok(r) => {
+            embed.field(
+                "You",
+                if r.is_some() {
+                    "You agreed to be recorded here. Use `record optout` to change your mind."
+                } else {
+                    "You won't be recorded here. Use `record optin` if you want to be."
+                },
+                false,
+            );
+        }
+        Err(err) => {
+            tracing::error!("Couldn't fetch recording consent: {}", err);
+        }
+    }
+
+    if let Err(e) = msg
+        .channel_id
+        .send_message(&ctx, |m| {
+            m.embed(|e| {
+                *e = embed;
+                e
+            })
+        })
+        .await
+    {
+        handle_serenity_error!(e);
+    }
+    Ok(())
+}
+
+#[command("start")]
+#[required_permissions("MANAGE_GUILD")]
+#[only_in("guilds")]
+#[bucket = "expensive"]
+#[description = "Start recording everyone who agreed to it. Recordings stop by themselves after \
+six hours."]
+async fn cmd_record_start(ctx: &Context, msg: &Message) -> CommandResult {
+    let guild_id = match msg.guild_id {
+        Some(g) => g,
+        None => return Ok(()),
+    };
+    let mut embed = CreateEmbed::default();
+    match start_recording(guild_id, msg.channel_id).await {
+        Ok(()) => {
+            embed.title("Recording!").description(format!(
+                "Only people who ran `record optin` are recorded. Run `record stop` when you're \
+                done, or I'll stop after {} hours.",
+                MAX_RECORDING_SECS / 3600
+            ));
+        }
+        Err(e) => {
+            embed.title("I couldn't start recording").description(e);
+        }
+    }
+
+    if let Err(e) = msg
+        .channel_id
+        .send_message(&ctx, |m| {
+            m.embed(|e| {
+                *e = embed;
+                e
+            })
+        })
+        .await
+    {
+        handle_serenity_error!(e);
+    }
+    Ok(())
+}
+
+#[command("stop")]
+#[required_permissions("MANAGE_GUILD")]
+#[only_in("guilds")]
+#[bucket = "expensive"]
+#[description = "Stop recording, and send everyone's recording here."]
+async fn cmd_record_stop(ctx: &Context, msg: &Message) -> CommandResult {
+    let guild_id = match msg.guild_id {
+        Some(g) => g,
+        None => return Ok(()),
+    };
+    let recording = match stop_recording(guild_id).await {
+        Some(r) => r,
+        None => {
+            if let Err(e) = msg
+                .channel_id
+                .send_message(&ctx, |m| {
+                    m.embed(|e| {
+                        e.title("I'm not recording")
+                            .description("Use `record start` to start.")
+                    })
+                })
+                .await
+            {
+                handle_serenity_error!(e);
+            }
+            return Ok(());
+        }
+    };
+    let _typing = msg.channel_id.start_typing(ctx.as_ref())?;
+    send_recording(ctx, guild_id, recording).await;
+    Ok(())
+}
+
+#[command("optin")]
+#[only_in("guilds")]
+#[bucket = "general"]
+#[description = "Agree to be recorded in this server. Until you do, I won't record you."]
+async fn cmd_record_optin(ctx: &Context, msg: &Message) -> CommandResult {
+    set_consent(ctx, msg, true).await
+}
+
+#[command("optout")]
+#[only_in("guilds")]
+#[bucket = "general"]
+#[description = "Stop being recorded in this server. If something's being recorded right now, \
+what I have of you so far is deleted."]
+async fn cmd_record_optout(ctx: &Context, msg: &Message) -> CommandResult {
+    set_consent(ctx, msg, false).await
+}
+
+async fn set_consent(ctx: &Context, msg: &Message, consent: bool) -> CommandResult {
+    let guild_id = match msg.guild_id {
+        Some(g) => g,
+        None => return Ok(()),
+    };
+    let mut embed = CreateEmbed::default();
+
+    let data = ctx.data.read().await;
+    let db = unsafe { data.get::<PgPoolKey>().unwrap_unchecked() };
+    let res = if consent {
+        query!(
+            "INSERT INTO recording_consent (guild_id, user_id) VALUES ($1, $2) \
+            ON CONFLICT DO NOTHING",
+            guild_id.0 as i64,
+            msg.author.id.0 as i64
+        )
+        .execute(db)
+        .await
+    } else {
+        query!(
+            "DELETE FROM recording_consent WHERE guild_id = $1 AND user_id = $2",
+            guild_id.0 as i64,
+            msg.author.id.0 as i64
+        )
+        .execute(db)
+        .await
+    };
+    match res {
+        Ok(_) => {
+            set_recording_consent(guild_id, msg.author.id, consent);
+            embed.description(if consent {
+                "Got it! I'll record you here whenever someone starts a recording."
+            } else {
+                "Got it! I won't record you here anymore."
+            });
+        }
+        Err(err) => {
+            tracing::error!("Couldn't update recording consent: {}", err);
+            embed
+                .title("Ugh, I couldn't write that down..")
+                .description("I just let my developer know, until then you could just try again");
+        }
+    }
+
+    if let Err(e) = msg
+        .channel_id
+        .send_message(&ctx, |m| {
+            m.embed(|e| {
+                *e = embed;
+                e
+            })
+        })
+        .await
+    {
+        handle_serenity_error!(e);
+    }
+    Ok(())
+}
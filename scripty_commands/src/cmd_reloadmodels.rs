@@ -0,0 +1,32 @@
+use scripty_audio_utils::reload_models;
+use scripty_macros::handle_serenity_error;
+use serenity::{
+    client::Context,
+    framework::standard::{macros::command, CommandResult},
+    model::prelude::Message,
+};
+
+#[command("reload_models")]
+#[description = "Reloads the speech-to-text models from disk. Voice chats switch over to the new \
+models between utterances, without reconnecting."]
+#[owners_only]
+async fn cmd_reload_models(ctx: &Context, msg: &Message) -> CommandResult {
+    let mut msg1 = match msg
+        .channel_id
+        .send_message(&ctx, |m| m.content("Reloading models..."))
+        .await
+    {
+        Err(e) => {
+            handle_serenity_error!(e);
+            return Ok(());
+        }
+        Ok(m) => m,
+    };
+    let _typing = msg.channel_id.start_typing(ctx.as_ref())?;
+    let content = match reload_models().await {
+        Ok(n) => format!("Reloaded {} model(s).", n),
+        Err(e) => format!("Couldn't reload models, still using the old ones: {}", e),
+    };
+    let _ = msg1.edit(ctx, |m| m.content(content)).await;
+    Ok(())
+}
@@ -0,0 +1,165 @@
+use crate::{guild_corpus_path, guild_scorer_dir};
+use scripty_audio::update_guild_settings;
+use scripty_audio_utils::{build_scorer, load_model, unload_model, CustomScorer, ModelKey};
+use scripty_config::BotConfig;
+use scripty_db::PgPoolKey;
+use scripty_macros::handle_serenity_error;
+use serenity::{
+    client::Context,
+    framework::standard::{macros::command, Args, CommandResult},
+    model::{channel::Message, id::GuildId},
+};
+use sqlx::{query, PgPool};
+use std::path::Path;
+use tokio::fs;
+
+#[command("review_scorer")]
+#[description = "Reviews custom scorer uploads. With no args, lists the guilds waiting for review. \
+`approve <guild ID>` builds and enables one, `reject <guild ID>` deletes the upload."]
+#[owners_only]
+async fn cmd_review_scorer(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let data = ctx.data.read().await;
+    let db = unsafe { data.get::<PgPoolKey>().unwrap_unchecked() };
+    let config = BotConfig::get().expect("Failed to load config!");
+
+    let action = args.single::<String>();
+    let guild_id = args.single::<u64>().map(GuildId);
+    let content = match (action.as_deref(), guild_id) {
+        (Ok("approve"), Ok(guild_id)) => {
+            let _typing = msg.channel_id.start_typing(ctx.as_ref())?;
+            match approve(db, config, guild_id).await {
+                Ok(language) => {
+                    format!("Built and enabled a {} scorer for {}.", language, guild_id)
+                }
+                Err(e) => format!("Couldn't approve the scorer for {}: {}", guild_id, e),
+            }
+        }
+        (Ok("reject"), Ok(guild_id)) => match query!(
+            "UPDATE custom_scorers SET pending_language = NULL WHERE guild_id = $1",
+            guild_id.0 as i64
+        )
+        .execute(db)
+        .await
+        {
+            Ok(_) => {
+                let _ = fs::remove_file(guild_corpus_path(config, guild_id)).await;
+                format!("Rejected the upload from {}.", guild_id)
+            }
+            Err(e) => format!("DB returned a error: {:?}", e),
+        },
+        (Err(_), _) => match query!(
+            "SELECT guild_id, pending_language FROM custom_scorers \
+            WHERE pending_language IS NOT NULL"
+        )
+        .fetch_all(db)
+        .await
+        {
+            Ok(rows) if rows.is_empty() => "Nothing is waiting for review.".to_string(),
+            Ok(rows) => rows
+                .into_iter()
+                .map(|r| {
+                    format!(
+                        "{}: {} ({})",
+                        r.guild_id,
+                        r.pending_language.unwrap_or_default(),
+                        guild_corpus_path(config, GuildId(r.guild_id as u64)).display()
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+            Err(e) => format!("DB returned a error: {:?}", e),
+        },
+        _ => "usage: review_scorer [approve/reject <guild ID>]".to_string(),
+    };
+
+    if let Err(e) = msg
+        .channel_id
+        .send_message(&ctx, |m| m.content(content))
+        .await
+    {
+        handle_serenity_error!(e);
+    }
+    Ok(())
+}
+
+/// Build the scorer for a guild's pending upload, then switch the guild over to it.
+///
+/// Returns the language it was built for.
+async fn approve(
+    db: &PgPool,
+    config: &'static BotConfig,
+    guild_id: GuildId,
+) -> Result<String, String> {
+    let row = match query!(
+        "SELECT language, scorer_path, pending_language FROM custom_scorers WHERE guild_id = $1",
+        guild_id.0 as i64
+    )
+    .fetch_optional(db)
+    .await
+    {
+        Ok(Some(r)) => r,
+        Ok(None) => return Err("nothing was uploaded".to_string()),
+        Err(e) => return Err(format!("DB returned a error: {:?}", e)),
+    };
+    let language = row
+        .pending_language
+        .ok_or_else(|| "nothing is waiting for review".to_string())?;
+
+    // every build goes in its own directory: the old scorer may still be loaded,
+    // and overwriting it in place could break decodes using it
+    let corpus = guild_corpus_path(config, guild_id);
+    let out_dir =
+        guild_scorer_dir(config, guild_id).join(chrono::Utc::now().timestamp().to_string());
+    let scorer_path = {
+        let language = language.clone();
+        let out_dir = out_dir.clone();
+        tokio::task::spawn_blocking(move || build_scorer(config, &language, &corpus, &out_dir))
+            .await
+            .expect("Failed to spawn blocking!")
+            .map_err(|e| e.to_string())?
+    };
+
+    // make sure the scorer actually loads before anyone uses it
+    let key = ModelKey {
+        language: language.clone(),
+        scorer: Some(scorer_path.clone()),
+    };
+    if let Err(e) = load_model(&key).await {
+        let _ = fs::remove_dir_all(&out_dir).await;
+        return Err(e.to_string());
+    }
+
+    if let Err(e) = query!(
+        "UPDATE custom_scorers SET language = $2, scorer_path = $3, pending_language = NULL \
+        WHERE guild_id = $1",
+        guild_id.0 as i64,
+        language,
+        scorer_path.to_string_lossy().into_owned()
+    )
+    .execute(db)
+    .await
+    {
+        unload_model(&key);
+        return Err(format!("DB returned a error: {:?}", e));
+    }
+    update_guild_settings(guild_id, |s| {
+        s.scorer = Some(CustomScorer {
+            language: language.clone(),
+            path: scorer_path,
+        })
+    });
+
+    // voice chats switch to the new scorer with their next utterance, so the old one can go
+    if let (Some(old_language), Some(old_path)) = (row.language, row.scorer_path) {
+        let old_path = Path::new(&old_path);
+        unload_model(&ModelKey {
+            language: old_language,
+            scorer: Some(old_path.to_path_buf()),
+        });
+        if let Some(old_dir) = old_path.parent() {
+            let _ = fs::remove_dir_all(old_dir).await;
+        }
+    }
+    let _ = fs::remove_file(guild_corpus_path(config, guild_id)).await;
+    Ok(language)
+}
@@ -0,0 +1,99 @@
+use scripty_audio::seat_list;
+use scripty_macros::handle_serenity_error;
+use serenity::{
+    builder::CreateEmbed,
+    client::Context,
+    framework::standard::{macros::command, CommandResult},
+    model::prelude::{Message, UserId},
+    prelude::Mentionable,
+};
+
+/// Discord's limit for the value of a embed field
+const MAX_FIELD_CHARS: usize = 1024;
+
+/// List `users` as mentions, numbered if `numbered`, cutting it short if it doesn't fit in a
+/// embed field.
+fn format_users(users: &[UserId], numbered: bool) -> String {
+    let mut out = String::new();
+    for (i, u) in users.iter().enumerate() {
+        let line = if numbered {
+            format!("{}. {}\n", i + 1, u.mention())
+        } else {
+            format!("{}\n", u.mention())
+        };
+        let more = format!("and {} more", users.len() - i);
+        if out.len() + line.len() + more.len() > MAX_FIELD_CHARS {
+            out.push_str(&more);
+            return out;
+        }
+        out.push_str(&line);
+    }
+    out
+}
+
+#[command("seats")]
+#[aliases("queue")]
+#[only_in("guilds")]
+#[bucket = "general"]
+#[description = "See who I'm transcribing in voice chat, and who's in line for a seat.\nOnly so \
+many people can be transcribed at once. Once every seat is taken, anyone else who joins waits in \
+line, and gets the next seat that opens up."]
+async fn cmd_seats(ctx: &Context, msg: &Message) -> CommandResult {
+    let guild_id = match msg.guild_id {
+        Some(g) => g,
+        None => return Ok(()),
+    };
+    let mut embed = CreateEmbed::default();
+
+    match seat_list(guild_id) {
+        Some(seats) => {
+            embed
+                .title("Seats")
+                .field(
+                    format!(
+                        "Transcribing ({}/{})",
+                        seats.seated.len(),
+                        if seats.max == usize::MAX {
+                            "unlimited".to_string()
+                        } else {
+                            seats.max.to_string()
+                        }
+                    ),
+                    if seats.seated.is_empty() {
+                        "Nobody yet".to_string()
+                    } else {
+                        format_users(&seats.seated, false)
+                    },
+                    false,
+                )
+                .field(
+                    "Waiting for a seat",
+                    if seats.queued.is_empty() {
+                        "Nobody".to_string()
+                    } else {
+                        format_users(&seats.queued, true)
+                    },
+                    false,
+                );
+        }
+        None => {
+            embed
+                .title("I'm not in voice chat")
+                .description("Nobody's being transcribed here right now.");
+        }
+    }
+
+    if let Err(e) = msg
+        .channel_id
+        .send_message(&ctx, |m| {
+            m.embed(|e| {
+                *e = embed;
+                e
+            })
+        })
+        .await
+    {
+        handle_serenity_error!(e);
+    }
+    Ok(())
+}
@@ -1,4 +1,4 @@
-use scripty_audio::bind;
+use scripty_audio::{bind, log_consent, ConsentKind, PRIVACY_POLICY_URL};
 use scripty_db::PgPoolKey;
 use scripty_macros::handle_serenity_error;
 use serenity::builder::CreateSelectMenuOption;
@@ -46,10 +46,14 @@ async fn cmd_setup(ctx: &Context, msg: &Message) -> CommandResult {
     //////////////////////////////////////////////////////
     // make user agree to ToS + Privacy Policy as a CYA //
     //////////////////////////////////////////////////////
-    let mut m = match msg.channel_id.send_message(ctx, |f| {
-        f
-            .content("By using Scripty you agree to the privacy policy, found here: https://scripty.imaskeleton.me/privacy_policy . \
-    Type `ok` within 5 minutes to continue.")
+    let mut m = match msg
+        .channel_id
+        .send_message(ctx, |f| {
+            f.content(format!(
+                "By using Scripty you agree to the privacy policy, found here: {} . \
+                Type `ok` within 5 minutes to continue.",
+                PRIVACY_POLICY_URL
+            ))
             .components(|c| {
                 c.create_action_row(|r| {
                     r.create_button(|b| {
@@ -59,14 +63,16 @@ async fn cmd_setup(ctx: &Context, msg: &Message) -> CommandResult {
                     })
                 })
             })
-    }).await {
+        })
+        .await
+    {
         Ok(m) => m,
         Err(e) => {
             handle_serenity_error!(e);
             return Ok(());
         }
     };
-    if CollectComponentInteraction::new(&ctx)
+    let agreement = match CollectComponentInteraction::new(&ctx)
         .author_id(msg.author.id)
         .channel_id(msg.channel_id)
         .guild_id(unsafe { msg.guild_id.unwrap_unchecked() })
@@ -77,14 +83,19 @@ async fn cmd_setup(ctx: &Context, msg: &Message) -> CommandResult {
         })
         .timeout(Duration::from_secs(300))
         .await
-        .is_none()
     {
-        let _ = m
-            .edit(&ctx, |m| m.content("Timed out. Rerun setup to try again."))
-            .await;
-        return Ok(());
-    }
+        Some(a) => a,
+        None => {
+            let _ = m
+                .edit(&ctx, |m| m.content("Timed out. Rerun setup to try again."))
+                .await;
+            return Ok(());
+        }
+    };
     drop(m);
+    if let Err(e) = log_consent(guild_id, agreement.user.id, ConsentKind::Setup).await {
+        tracing::error!("Couldn't log agreement to the privacy policy: {}", e);
+    }
 
     ////////////////////////////////////////////////////////
     // get the transcription result channel from the user //
@@ -1,3 +1,4 @@
+use scripty_audio::{guild_policy, send_session_transcript, take_all_session_transcripts};
 use scripty_macros::handle_serenity_error;
 use scripty_utils::ShardManagerWrapper;
 use serenity::{
@@ -20,6 +21,18 @@ async fn cmd_shutdown(ctx: &Context, msg: &Message) -> CommandResult {
         handle_serenity_error!(e);
         return Ok(());
     }
+
+    // leaving voice ends every session, so send out the transcripts of the ones still going
+    for (guild_id, transcript) in take_all_session_transcripts() {
+        if let Some(format) = guild_policy(guild_id).transcript_format {
+            if let Err(e) =
+                send_session_transcript(ctx, transcript.channel_id, &transcript, format).await
+            {
+                tracing::warn!("Failed to send transcript for {}: {}", guild_id, e);
+            }
+        }
+    }
+
     let data = ctx.data.write().await;
     let manager = data
         .get::<ShardManagerWrapper>()
@@ -0,0 +1,90 @@
+use scripty_audio::{guild_policy, send_session_transcript, session_transcript};
+use scripty_audio_utils::{ExportFormat, EXPORT_FORMATS};
+use scripty_macros::handle_serenity_error;
+use serenity::{
+    client::Context,
+    framework::standard::{macros::command, Args, CommandResult},
+    model::prelude::Message,
+};
+
+#[command("transcript")]
+#[only_in("guilds")]
+#[bucket = "expensive"]
+#[max_args(1)]
+#[description = "Send everything said in voice chat so far as one file, to the channel \
+transcripts go to.\nIt's in the format picked with `config export`, unless you give one: `txt`, \
+`md`, `srt` or `vtt`."]
+#[usage = "[format]"]
+#[example = "srt"]
+async fn cmd_transcript(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let guild_id = match msg.guild_id {
+        Some(g) => g,
+        None => return Ok(()),
+    };
+
+    let format = match args.single::<String>() {
+        Ok(f) => match f.to_lowercase().parse::<ExportFormat>() {
+            Ok(f) => f,
+            Err(()) => {
+                if let Err(e) = msg
+                    .channel_id
+                    .send_message(&ctx, |m| {
+                        m.embed(|e| {
+                            e.title("That doesn't look right").description(format!(
+                                "I can only export transcripts as {}.",
+                                EXPORT_FORMATS
+                                    .iter()
+                                    .map(|f| format!("`{}`", f))
+                                    .collect::<Vec<_>>()
+                                    .join(", ")
+                            ))
+                        })
+                    })
+                    .await
+                {
+                    handle_serenity_error!(e);
+                }
+                return Ok(());
+            }
+        },
+        Err(_) => guild_policy(guild_id)
+            .transcript_format
+            .unwrap_or(ExportFormat::Text),
+    };
+
+    let transcript = match session_transcript(guild_id) {
+        Some(t) => t,
+        None => {
+            if let Err(e) = msg
+                .channel_id
+                .send_message(&ctx, |m| {
+                    m.embed(|e| {
+                        e.title("Nothing to export")
+                            .description("Nobody's said anything in voice chat yet.")
+                    })
+                })
+                .await
+            {
+                handle_serenity_error!(e);
+            }
+            return Ok(());
+        }
+    };
+
+    if let Err(e) = send_session_transcript(ctx, transcript.channel_id, &transcript, format).await {
+        handle_serenity_error!(e);
+        return Ok(());
+    }
+    if transcript.channel_id != msg.channel_id {
+        if let Err(e) = msg
+            .channel_id
+            .send_message(&ctx, |m| {
+                m.content(format!("Sent it to <#{}>.", transcript.channel_id))
+            })
+            .await
+        {
+            handle_serenity_error!(e);
+        }
+    }
+    Ok(())
+}
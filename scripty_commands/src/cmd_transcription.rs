@@ -0,0 +1,161 @@
+use scripty_audio::{
+    guild_policy, load_guild_settings, log_consent, set_opted_out, ConsentKind, PRIVACY_POLICY_URL,
+    PRIVACY_POLICY_VERSION,
+};
+use scripty_db::PgPoolKey;
+use scripty_macros::handle_serenity_error;
+use serenity::{
+    builder::CreateEmbed,
+    client::Context,
+    framework::standard::{macros::command, Args, CommandResult},
+    model::prelude::Message,
+};
+use sqlx::query;
+
+#[command("transcription")]
+#[aliases("privacy")]
+#[bucket = "general"]
+#[max_args(2)]
+#[description = "Choose whether I transcribe you in voice chat.\n`off` stops me transcribing you \
+in this server, `off everywhere` in every server, and `on` undoes that. Without anything, I'll \
+tell you what you picked.\nIn servers that ask everyone to agree first, `on` also means you agree \
+to the privacy policy."]
+#[usage = "[on/off] [everywhere]"]
+#[example = "off everywhere"]
+async fn cmd_transcription(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let mut embed = CreateEmbed::default();
+
+    let transcribe = match args.single::<String>().map(|a| a.to_lowercase()).as_deref() {
+        Ok("on") | Ok("yes") | Ok("optin") => Ok(Some(true)),
+        Ok("off") | Ok("no") | Ok("optout") => Ok(Some(false)),
+        Ok(_) => Err(()),
+        Err(_) => Ok(None),
+    };
+    // everywhere is the only choice in DMs
+    let everywhere = msg.guild_id.is_none()
+        || args
+            .single::<String>()
+            .map_or(false, |a| a.eq_ignore_ascii_case("everywhere"));
+    let guild_id = if everywhere { None } else { msg.guild_id };
+
+    let data = ctx.data.read().await;
+    let db = unsafe { data.get::<PgPoolKey>().unwrap_unchecked() };
+    match transcribe {
+        Ok(Some(transcribe)) => {
+            match query!(
+                "INSERT INTO user_settings (user_id, guild_id, opted_out) VALUES ($1, $2, $3) \
+                ON CONFLICT (user_id, guild_id) DO UPDATE SET opted_out = $3",
+                msg.author.id.0 as i64,
+                guild_id.map_or(0, |g| g.0 as i64),
+                !transcribe
+            )
+            .execute(db)
+            .await
+            {
+                Ok(_) => {
+                    set_opted_out(guild_id, msg.author.id, !transcribe);
+                    embed.description(match (transcribe, guild_id) {
+                        (true, Some(_)) => "Got it! I'll transcribe you in this server again.",
+                        (true, None) => {
+                            "Got it! I'll transcribe you again, except in servers you turned me \
+                            off in."
+                        }
+                        (false, Some(_)) => "Got it! I won't transcribe you in this server.",
+                        (false, None) => {
+                            "Got it! I won't transcribe you anywhere, except in servers you \
+                            turn me back on in."
+                        }
+                    });
+
+                    // turning it on here counts as agreeing, in servers that ask for it
+                    if let Some(g) = guild_id {
+                        // the policy might not be cached if there's no session running here
+                        if transcribe
+                            && load_guild_settings(g).await.is_ok()
+                            && guild_policy(g).strict_consent
+                        {
+                            if let Err(err) =
+                                log_consent(g, msg.author.id, ConsentKind::Transcription).await
+                            {
+                                tracing::error!("Couldn't log consent: {}", err);
+                            }
+                        }
+                    }
+                }
+                Err(err) => {
+                    tracing::error!("Couldn't update user settings: {}", err);
+                    embed
+                        .title("Ugh, I couldn't write that down..")
+                        .description(
+                            "I just let my developer know, until then you could just try again",
+                        );
+                }
+            }
+        }
+        Ok(None) => {
+            match query!(
+                "SELECT guild_id, opted_out FROM user_settings \
+                WHERE user_id = $1 AND guild_id IN (0, $2)",
+                msg.author.id.0 as i64,
+                msg.guild_id.map_or(0, |g| g.0 as i64)
+            )
+            .fetch_all(db)
+            .await
+            {
+                Ok(rows) => {
+                    let global = rows.iter().find(|r| r.guild_id == 0).map(|r| r.opted_out);
+                    let here = rows.iter().find(|r| r.guild_id != 0).map(|r| r.opted_out);
+                    embed.title("Transcription").field(
+                        "Everywhere",
+                        if global.unwrap_or(false) { "Off" } else { "On" },
+                        false,
+                    );
+                    if msg.guild_id.is_some() {
+                        embed.field(
+                            "In this server",
+                            match here {
+                                Some(false) => "On",
+                                Some(true) => "Off",
+                                None => "Same as everywhere",
+                            },
+                            false,
+                        );
+                    }
+                    embed.field(
+                        "Privacy policy",
+                        format!(
+                            "{} (version {})",
+                            PRIVACY_POLICY_URL, PRIVACY_POLICY_VERSION
+                        ),
+                        false,
+                    );
+                }
+                Err(err) => {
+                    tracing::error!("Couldn't fetch user settings: {}", err);
+                    embed.title("Ugh, I couldn't look that up..").description(
+                        "I just let my developer know, until then you could just try again",
+                    );
+                }
+            }
+        }
+        Err(()) => {
+            embed
+                .title("That doesn't look right")
+                .description("Transcription can only be turned `on` or `off`.");
+        }
+    }
+
+    if let Err(e) = msg
+        .channel_id
+        .send_message(&ctx, |m| {
+            m.embed(|e| {
+                *e = embed;
+                e
+            })
+        })
+        .await
+    {
+        handle_serenity_error!(e);
+    }
+    Ok(())
+}
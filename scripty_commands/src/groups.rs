@@ -2,7 +2,7 @@ use super::*;
 use serenity::framework::standard::macros::group;
 
 #[group("General Stuff")]
-#[commands(cmd_info, cmd_prefix, cmd_donate)]
+#[commands(cmd_info, cmd_prefix, cmd_donate, cmd_transcription)]
 struct General;
 
 #[group("Bot Utils")]
@@ -10,13 +10,20 @@ struct General;
 struct Utils;
 
 #[group("Voice Commands")]
-#[commands(cmd_join)]
+#[commands(cmd_join, cmd_record, cmd_transcript, cmd_seats)]
 struct Voice;
 
 #[group("Config Commands")]
-#[commands(cmd_setup)]
+#[commands(cmd_setup, cmd_config)]
 struct Config;
 
 #[group("Bot Owner Commands")]
-#[commands(cmd_rejoin_all, cmd_shutdown, cmd_add_premium, cmd_eval)]
+#[commands(
+    cmd_rejoin_all,
+    cmd_reload_models,
+    cmd_review_scorer,
+    cmd_shutdown,
+    cmd_add_premium,
+    cmd_eval
+)]
 struct BotOwner;
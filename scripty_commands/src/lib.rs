@@ -3,6 +3,7 @@
 #![feature(once_cell)]
 
 mod cmd_addpremium;
+mod cmd_config;
 mod cmd_credits;
 mod cmd_donate;
 pub mod cmd_error;
@@ -13,14 +14,21 @@ mod cmd_info;
 mod cmd_join;
 mod cmd_ping;
 mod cmd_prefix;
+mod cmd_record;
 mod cmd_rejoinall;
+mod cmd_reloadmodels;
+mod cmd_reviewscorer;
+mod cmd_seats;
 mod cmd_setup;
 mod cmd_shutdown;
 mod cmd_stats;
 mod cmd_template;
+mod cmd_transcript;
+mod cmd_transcription;
 pub mod groups;
 
 pub use cmd_addpremium::*;
+pub use cmd_config::*;
 pub use cmd_credits::*;
 pub use cmd_donate::*;
 pub use cmd_error::*;
@@ -31,10 +39,16 @@ pub use cmd_info::*;
 pub use cmd_join::*;
 pub use cmd_ping::*;
 pub use cmd_prefix::*;
+pub use cmd_record::*;
 pub use cmd_rejoinall::*;
+pub use cmd_reloadmodels::*;
+pub use cmd_reviewscorer::*;
+pub use cmd_seats::*;
 pub use cmd_setup::*;
 pub use cmd_shutdown::*;
 pub use cmd_stats::*;
+pub use cmd_transcript::*;
+pub use cmd_transcription::*;
 pub use groups::*;
 // not a real command
 // pub use cmd_template::*;
@@ -1,6 +1,6 @@
-use crate::{DatabaseConnection, BOT_CONFIG};
+use crate::{DatabaseConnection, SttBackendKind, BOT_CONFIG};
 use serde::{Deserialize, Serialize};
-use std::{fs, io};
+use std::{collections::BTreeMap, fs, io};
 
 #[derive(Serialize, Deserialize)]
 pub struct BotConfig {
@@ -11,6 +11,32 @@ pub struct BotConfig {
     github: String,
     colour: u32,
     model_path: String,
+    /// Language code of the model in `model_path`. Defaults to `en`.
+    default_language: Option<String>,
+    #[serde(default)]
+    stt_backend: SttBackendKind,
+    /// Number of CPU threads each whisper decode may use. Defaults to every available core.
+    whisper_threads: Option<u16>,
+    /// Number of copies of the model to load, so that many decodes can run at once. Defaults to 1.
+    inference_instances: Option<u16>,
+    /// Max number of copies of DeepSpeech models with a server's hot words added to keep
+    /// loaded, across every model. Each takes up as much memory as its model does. Defaults to 8.
+    max_boosted_models: Option<u16>,
+    /// Max number of utterances to transcribe at once. Defaults to one per CPU core.
+    stt_workers: Option<u16>,
+    /// Directory with KenLM's `lmplz` and `build_binary`, and DeepSpeech's
+    /// `generate_scorer_package`. Custom scorers can't be built without it.
+    scorer_tools: Option<String>,
+    /// Directory custom scorers and the text they're built from are kept in.
+    /// Defaults to `scorers`.
+    scorer_dir: Option<String>,
+    /// Default confidence (from 0 to 1) transcripts need to be sent. Defaults to 0.
+    min_confidence: Option<f32>,
+    /// Directory session recordings are kept in. Defaults to `recordings`.
+    recording_dir: Option<String>,
+    /// Public URL the webserver's `/recordings` route is reachable at, used to link recordings
+    /// too big to upload to Discord. If unset, those are only kept on disk.
+    recording_url: Option<String>,
 
     // DB stuff
     user: String,
@@ -21,6 +47,11 @@ pub struct BotConfig {
     host: Option<String>,
     port: Option<u16>,
     unix_socket: Option<String>,
+
+    /// Models for other languages, keyed by language code.
+    /// This has to stay last: TOML tables can't be followed by plain values.
+    #[serde(default)]
+    models: BTreeMap<String, String>,
 }
 
 impl BotConfig {
@@ -36,12 +67,24 @@ impl BotConfig {
                         github: "https://github.com/tazz4843/scripty".to_string(),
                         colour: 11771355,
                         model_path: "/home/user/deepspeech".to_string(),
+                        default_language: None,
+                        stt_backend: SttBackendKind::DeepSpeech,
+                        whisper_threads: None,
+                        inference_instances: None,
+                        max_boosted_models: None,
+                        stt_workers: None,
+                        scorer_tools: None,
+                        scorer_dir: None,
+                        min_confidence: None,
+                        recording_dir: None,
+                        recording_url: None,
                         user: "scripty".to_string(),
                         password: "scripty".to_string(),
                         db: "scripty".to_string(),
                         host: None,
                         port: None,
                         unix_socket: Some("/var/run/postgresql/".to_string()),
+                        models: BTreeMap::new(),
                     };
                     let default_cfg_str =
                         toml::to_string_pretty(&default_cfg).expect("failed to serialize config");
@@ -88,6 +131,87 @@ impl BotConfig {
     pub fn model_path(&self) -> &String {
         &self.model_path
     }
+    /// Get the language code of the model in `model_path`.
+    pub fn default_language(&self) -> &str {
+        self.default_language.as_deref().unwrap_or("en")
+    }
+    /// Get the directory of the model for `language`, if there is one.
+    pub fn model_dir(&self, language: &str) -> Option<&String> {
+        if language == self.default_language() {
+            Some(&self.model_path)
+        } else {
+            self.models.get(language)
+        }
+    }
+    /// Get the code of every language there's a model for, starting with the default.
+    pub fn languages(&self) -> Vec<&str> {
+        let default = self.default_language();
+        std::iter::once(default)
+            .chain(
+                self.models
+                    .keys()
+                    .map(String::as_str)
+                    .filter(|l| *l != default),
+            )
+            .collect()
+    }
+    /// Get the speech-to-text engine models in `model_path` should be loaded with.
+    pub fn stt_backend(&self) -> SttBackendKind {
+        self.stt_backend
+    }
+    /// Get the number of CPU threads each whisper decode may use.
+    ///
+    /// If `None`, every available core should be used.
+    pub fn whisper_threads(&self) -> Option<u16> {
+        self.whisper_threads
+    }
+    /// Get the number of instances of each model to load.
+    ///
+    /// Every instance takes up as much memory as the model does, so this defaults to 1.
+    pub fn inference_instances(&self) -> u16 {
+        self.inference_instances.unwrap_or(1).max(1)
+    }
+    /// Get the max number of copies of DeepSpeech models with hot words added to keep loaded.
+    ///
+    /// Every copy takes up as much memory as its model does. If 0, a copy is loaded for every
+    /// decode that uses hot words instead, which saves the memory but is a lot slower.
+    pub fn max_boosted_models(&self) -> u16 {
+        self.max_boosted_models.unwrap_or(8)
+    }
+    /// Get the max number of utterances to transcribe at once.
+    ///
+    /// If `None`, one per CPU core should be used.
+    pub fn stt_workers(&self) -> Option<u16> {
+        self.stt_workers
+    }
+    /// Get the directory the tools to build custom scorers with are in.
+    ///
+    /// If `None`, custom scorers are disabled.
+    pub fn scorer_tools(&self) -> Option<&str> {
+        self.scorer_tools.as_deref()
+    }
+    /// Get the directory custom scorers are kept in.
+    pub fn scorer_dir(&self) -> &str {
+        self.scorer_dir.as_deref().unwrap_or("scorers")
+    }
+    /// Get the default confidence transcripts need to be sent, for guilds that didn't set one.
+    ///
+    /// Defaults to 0, which sends everything.
+    pub fn min_confidence(&self) -> f32 {
+        self.min_confidence.unwrap_or(0.0)
+    }
+    /// Get the directory session recordings are kept in.
+    pub fn recording_dir(&self) -> &str {
+        self.recording_dir.as_deref().unwrap_or("recordings")
+    }
+    /// Get the public URL recordings can be downloaded from, without a trailing slash.
+    ///
+    /// If `None`, recordings too big to upload are only kept on disk.
+    pub fn recording_url(&self) -> Option<&str> {
+        self.recording_url
+            .as_deref()
+            .map(|u| u.trim_end_matches('/'))
+    }
     /// Get the database login.
     ///
     /// Returned tuple is user, password, and database respectively.
@@ -2,9 +2,11 @@
 
 mod config;
 mod database;
+mod stt_backend;
 
 pub use config::*;
 pub use database::*;
 use std::lazy::SyncOnceCell as OnceCell;
+pub use stt_backend::*;
 
 pub static BOT_CONFIG: OnceCell<BotConfig> = OnceCell::new();
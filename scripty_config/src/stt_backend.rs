@@ -0,0 +1,24 @@
+use serde::{Deserialize, Serialize};
+
+/// Which speech-to-text engine the bot should load its models with.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SttBackendKind {
+    /// Mozilla DeepSpeech. `model_path` should contain a `.pb`/`.pbmm` graph and optionally a
+    /// `.scorer` file.
+    DeepSpeech,
+    /// whisper.cpp, running on the CPU. `model_path` should contain a ggml model file, other
+    /// formats aren't supported.
+    /// Requires the `whisper` feature.
+    Whisper,
+    /// Vosk (Kaldi). `model_path` should be a unpacked Vosk model directory.
+    /// The small models are a good fit for low-memory machines.
+    /// Requires the `vosk` feature.
+    Vosk,
+}
+
+impl Default for SttBackendKind {
+    fn default() -> Self {
+        Self::DeepSpeech
+    }
+}
@@ -1,4 +1,4 @@
-use scripty_audio::auto_join;
+use scripty_audio::{auto_join, handle_consent_interaction, handle_correction_interaction};
 use scripty_commands::load_prefixes;
 use scripty_metrics::spawn_updater_task;
 use scripty_utils::START_TIME;
@@ -92,6 +92,12 @@ impl EventHandler for Handler {
             }
             InteractionType::MessageComponent => {
                 let interaction = unsafe { interaction.message_component().unwrap_unchecked() };
+                // these answer on their own, so they can't be deferred
+                if handle_correction_interaction(&ctx, &interaction).await
+                    || handle_consent_interaction(&ctx, &interaction).await
+                {
+                    return;
+                }
                 let _ = interaction
                     .create_interaction_response(&ctx, |r| {
                         r.kind(InteractionResponseType::DeferredUpdateMessage)
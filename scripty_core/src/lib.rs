@@ -5,6 +5,7 @@ mod handlers;
 
 use crate::handlers::bot::Handler;
 use crate::handlers::raw::RawHandler;
+use scripty_audio_utils::reload_models;
 use scripty_commands::groups::*;
 use scripty_commands::{cmd_error, prefix_check, CMD_HELP};
 use scripty_config::BotConfig;
@@ -25,7 +26,10 @@ use std::{
     sync::{atomic::AtomicBool, Arc},
     time::SystemTime,
 };
-use tokio::sync::RwLock;
+use tokio::{
+    signal::unix::{signal, SignalKind},
+    sync::RwLock,
+};
 use tracing::{error, info};
 
 pub async fn entrypoint() {
@@ -177,6 +181,17 @@ pub async fn entrypoint() {
         shard_manager.lock().await.shutdown_all().await;
     });
 
+    tokio::spawn(async move {
+        let mut hangup = signal(SignalKind::hangup()).expect("Couldn't listen for SIGHUP");
+        while hangup.recv().await.is_some() {
+            info!("Got SIGHUP, reloading models...");
+            match reload_models().await {
+                Ok(n) => info!("Reloaded {} model(s)", n),
+                Err(e) => error!("Couldn't reload models, still using the old ones: {}", e),
+            }
+        }
+    });
+
     info!("Starting client...");
     if let Err(e) = client.start_autosharded().await {
         error!("Couldn't start the client: {}", e);
@@ -47,6 +47,57 @@ pub async fn set_db() -> Pool<Postgres> {
     .await
     .expect("Couldn't create the guild table.");
 
+    query!("ALTER TABLE guilds ADD COLUMN IF NOT EXISTS vad_threshold REAL NOT NULL DEFAULT -45")
+        .execute(&db)
+        .await
+        .expect("Couldn't add the VAD threshold column to the guild table.");
+
+    query!(
+        "ALTER TABLE guilds ADD COLUMN IF NOT EXISTS vad_silence_ms INTEGER NOT NULL DEFAULT 800"
+    )
+    .execute(&db)
+    .await
+    .expect("Couldn't add the VAD silence column to the guild table.");
+
+    query!("ALTER TABLE guilds ADD COLUMN IF NOT EXISTS denoise BOOLEAN NOT NULL DEFAULT false")
+        .execute(&db)
+        .await
+        .expect("Couldn't add the denoise column to the guild table.");
+
+    query!("ALTER TABLE guilds ADD COLUMN IF NOT EXISTS language TEXT")
+        .execute(&db)
+        .await
+        .expect("Couldn't add the language column to the guild table.");
+
+    query!("ALTER TABLE guilds ADD COLUMN IF NOT EXISTS min_confidence REAL")
+        .execute(&db)
+        .await
+        .expect("Couldn't add the confidence column to the guild table.");
+
+    query!(
+        "ALTER TABLE guilds ADD COLUMN IF NOT EXISTS mark_low_confidence BOOLEAN NOT NULL DEFAULT false"
+    )
+    .execute(&db)
+    .await
+    .expect("Couldn't add the low confidence column to the guild table.");
+
+    query!("ALTER TABLE guilds ADD COLUMN IF NOT EXISTS redact TEXT[] NOT NULL DEFAULT '{}'")
+        .execute(&db)
+        .await
+        .expect("Couldn't add the redaction column to the guild table.");
+
+    query!("ALTER TABLE guilds ADD COLUMN IF NOT EXISTS transcript_format TEXT")
+        .execute(&db)
+        .await
+        .expect("Couldn't add the transcript format column to the guild table.");
+
+    query!(
+        "ALTER TABLE guilds ADD COLUMN IF NOT EXISTS strict_consent BOOLEAN NOT NULL DEFAULT false"
+    )
+    .execute(&db)
+    .await
+    .expect("Couldn't add the strict consent column to the guild table.");
+
     query!(
         "CREATE TABLE IF NOT EXISTS users (
         user_id BIGINT PRIMARY KEY,
@@ -79,6 +130,117 @@ pub async fn set_db() -> Pool<Postgres> {
     .await
     .expect("Couldn't create the API keys table");
 
+    query!(
+        "CREATE TABLE IF NOT EXISTS hot_words (
+           guild_id BIGINT NOT NULL,
+           word TEXT NOT NULL,
+           boost REAL NOT NULL,
+           PRIMARY KEY (guild_id, word)
+         )"
+    )
+    .execute(&db)
+    .await
+    .expect("Couldn't create the hot words table");
+
+    query!(
+        "CREATE TABLE IF NOT EXISTS custom_scorers (
+           guild_id BIGINT PRIMARY KEY,
+           language TEXT,
+           scorer_path TEXT,
+           pending_language TEXT
+         )"
+    )
+    .execute(&db)
+    .await
+    .expect("Couldn't create the custom scorers table");
+
+    query!(
+        "CREATE TABLE IF NOT EXISTS redacted_words (
+           guild_id BIGINT NOT NULL,
+           word TEXT NOT NULL,
+           PRIMARY KEY (guild_id, word)
+         )"
+    )
+    .execute(&db)
+    .await
+    .expect("Couldn't create the redacted words table");
+
+    query!(
+        "CREATE TABLE IF NOT EXISTS recording_consent (
+           guild_id BIGINT NOT NULL,
+           user_id BIGINT NOT NULL,
+           PRIMARY KEY (guild_id, user_id)
+         )"
+    )
+    .execute(&db)
+    .await
+    .expect("Couldn't create the recording consent table");
+
+    query!(
+        "CREATE TABLE IF NOT EXISTS transcript_corrections (
+           id BIGSERIAL PRIMARY KEY,
+           guild_id BIGINT NOT NULL,
+           user_id BIGINT NOT NULL,
+           language TEXT NOT NULL,
+           original TEXT NOT NULL,
+           corrected TEXT NOT NULL,
+           candidate_rank SMALLINT NOT NULL,
+           created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+         )"
+    )
+    .execute(&db)
+    .await
+    .expect("Couldn't create the transcript corrections table");
+
+    query!(
+        "CREATE TABLE IF NOT EXISTS user_settings (
+           user_id BIGINT NOT NULL,
+           guild_id BIGINT NOT NULL,
+           opted_out BOOLEAN NOT NULL DEFAULT false,
+           PRIMARY KEY (user_id, guild_id)
+         )"
+    )
+    .execute(&db)
+    .await
+    .expect("Couldn't create the user settings table");
+
+    query!(
+        "CREATE TABLE IF NOT EXISTS consent_log (
+           id BIGSERIAL PRIMARY KEY,
+           user_id BIGINT NOT NULL,
+           guild_id BIGINT NOT NULL,
+           kind TEXT NOT NULL,
+           policy_version TEXT NOT NULL,
+           accepted_at TIMESTAMPTZ NOT NULL DEFAULT now()
+         )"
+    )
+    .execute(&db)
+    .await
+    .expect("Couldn't create the consent log table");
+
+    query!(
+        "CREATE TABLE IF NOT EXISTS speaker_roles (
+           guild_id BIGINT NOT NULL,
+           role_id BIGINT NOT NULL,
+           allowed BOOLEAN NOT NULL,
+           PRIMARY KEY (guild_id, role_id)
+         )"
+    )
+    .execute(&db)
+    .await
+    .expect("Couldn't create the speaker roles table");
+
+    query!(
+        "CREATE TABLE IF NOT EXISTS priority_roles (
+           guild_id BIGINT NOT NULL,
+           role_id BIGINT NOT NULL,
+           PRIMARY KEY (guild_id, role_id)
+         )"
+    )
+    .execute(&db)
+    .await
+    .expect("Couldn't create the priority roles table");
+
     PG_POOL
         .set(db.clone())
         .expect("pool was already set, don't call `set_db` more than once");
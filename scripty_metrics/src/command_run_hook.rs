@@ -22,9 +22,30 @@ pub async fn before_hook(_: &Context, _: &Message, cmd_name: &str) -> bool {
         "join" => metrics.commands.join.inc(),
         "setup" => metrics.commands.setup.inc(),
         "rejoin_all" => metrics.commands.rejoin_all.inc(),
+        "reload_models" => metrics.commands.reload_models.inc(),
         "shutdown" => metrics.commands.shutdown.inc(),
         "add_premium" => metrics.commands.add_premium.inc(),
         "eval" => metrics.commands.eval.inc(),
+        "config" => metrics.commands.config.inc(),
+        "vad" => metrics.commands.config_vad.inc(),
+        "denoise" => metrics.commands.config_denoise.inc(),
+        "language" => metrics.commands.config_language.inc(),
+        "hotwords" => metrics.commands.config_hotwords.inc(),
+        "scorer" => metrics.commands.config_scorer.inc(),
+        "confidence" => metrics.commands.config_confidence.inc(),
+        "redact" => metrics.commands.config_redact.inc(),
+        "export" => metrics.commands.config_export.inc(),
+        "consent" => metrics.commands.config_consent.inc(),
+        "roles" => metrics.commands.config_roles.inc(),
+        "review_scorer" => metrics.commands.review_scorer.inc(),
+        "record" => metrics.commands.record.inc(),
+        "start" => metrics.commands.record_start.inc(),
+        "stop" => metrics.commands.record_stop.inc(),
+        "optin" => metrics.commands.record_optin.inc(),
+        "optout" => metrics.commands.record_optout.inc(),
+        "transcript" => metrics.commands.transcript.inc(),
+        "transcription" => metrics.commands.transcription.inc(),
+        "seats" => metrics.commands.seats.inc(),
         x => warn!("unknown command found: {}", x),
     };
     metrics.total_commands.inc();
@@ -11,8 +11,8 @@ pub use command_run_hook::before_hook;
 /// https://raw.githubusercontent.com/sushiibot/sushii-2/888fbcdaecc0838e5c3735a5aac677a2d327ef10/src/model/metrics.rs
 use chrono::{naive::NaiveDateTime, offset::Utc};
 use prometheus::{
-    Encoder, Gauge, GaugeVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry,
-    TextEncoder,
+    Encoder, Gauge, GaugeVec, Histogram, HistogramOpts, IntCounter, IntCounterVec, IntGauge,
+    IntGaugeVec, Opts, Registry, TextEncoder,
 };
 use prometheus_static_metric::make_static_metric;
 use serde::{Deserialize, Serialize};
@@ -137,6 +137,17 @@ make_static_metric! {
         unknown,
     }
 
+    pub label_enum SttJobDropReason {
+        queue_full,
+        expired,
+        timed_out,
+    }
+
+    pub label_enum LowConfidenceAction {
+        dropped,
+        marked,
+    }
+
     pub label_enum CommandsUsed {
         info,
         prefix,
@@ -148,9 +159,30 @@ make_static_metric! {
         join,
         setup,
         rejoin_all,
+        reload_models,
         shutdown,
         add_premium,
-        eval
+        eval,
+        config,
+        config_vad,
+        config_denoise,
+        config_language,
+        config_hotwords,
+        config_scorer,
+        config_confidence,
+        config_redact,
+        config_export,
+        config_consent,
+        config_roles,
+        review_scorer,
+        record,
+        record_start,
+        record_stop,
+        record_optin,
+        record_optout,
+        transcript,
+        transcription,
+        seats,
     }
 
     pub struct MessageCounterVec: IntCounter {
@@ -188,6 +220,14 @@ make_static_metric! {
     pub struct CommandsUsedVec: IntCounter {
         "command_name" => CommandsUsed,
     }
+
+    pub struct SttJobsDroppedVec: IntCounter {
+        "reason" => SttJobDropReason,
+    }
+
+    pub struct SttLowConfidenceVec: IntCounter {
+        "action" => LowConfidenceAction,
+    }
 }
 
 pub static METRICS: OnceCell<Arc<Metrics>> = OnceCell::new();
@@ -235,6 +275,12 @@ pub struct Metrics {
     pub cpu_temp: Gauge,
     pub total_commands: IntCounter,
     pub commands: CommandsUsedVec,
+    pub stt_queue_depth: IntGauge,
+    pub stt_jobs_running: IntGauge,
+    pub stt_queue_wait: Histogram,
+    pub stt_jobs_dropped: SttJobsDroppedVec,
+    pub stt_confidence: Histogram,
+    pub stt_low_confidence: SttLowConfidenceVec,
 }
 
 #[allow(clippy::new_without_default)]
@@ -318,6 +364,64 @@ impl Metrics {
         let commands_used_static = CommandsUsedVec::from(&commands_used);
         registry.register(Box::new(commands_used.clone())).unwrap();
 
+        let stt_queue_depth =
+            IntGauge::new("stt_queue_depth", "Utterances waiting to be transcribed").unwrap();
+        registry
+            .register(Box::new(stt_queue_depth.clone()))
+            .unwrap();
+
+        let stt_jobs_running =
+            IntGauge::new("stt_jobs_running", "Utterances being transcribed right now").unwrap();
+        registry
+            .register(Box::new(stt_jobs_running.clone()))
+            .unwrap();
+
+        let stt_queue_wait = Histogram::with_opts(
+            HistogramOpts::new(
+                "stt_queue_wait_seconds",
+                "Time utterances spent waiting to be transcribed",
+            )
+            .buckets(vec![0.01, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0]),
+        )
+        .unwrap();
+        registry.register(Box::new(stt_queue_wait.clone())).unwrap();
+
+        let stt_jobs_dropped = IntCounterVec::new(
+            Opts::new(
+                "stt_jobs_dropped",
+                "Utterances dropped without a transcript",
+            ),
+            &["reason"],
+        )
+        .unwrap();
+        let stt_jobs_dropped_static = SttJobsDroppedVec::from(&stt_jobs_dropped);
+        registry
+            .register(Box::new(stt_jobs_dropped.clone()))
+            .unwrap();
+
+        let stt_confidence = Histogram::with_opts(
+            HistogramOpts::new(
+                "stt_confidence",
+                "Normalized confidence of each non-empty transcript",
+            )
+            .buckets(vec![0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8, 0.9, 1.0]),
+        )
+        .unwrap();
+        registry.register(Box::new(stt_confidence.clone())).unwrap();
+
+        let stt_low_confidence = IntCounterVec::new(
+            Opts::new(
+                "stt_low_confidence",
+                "Transcripts below their guild's confidence threshold",
+            ),
+            &["action"],
+        )
+        .unwrap();
+        let stt_low_confidence_static = SttLowConfidenceVec::from(&stt_low_confidence);
+        registry
+            .register(Box::new(stt_low_confidence.clone()))
+            .unwrap();
+
         Self {
             registry,
             start_time: Utc::now().naive_utc(),
@@ -337,6 +441,12 @@ impl Metrics {
             cpu_temp,
             total_commands: total_commands_used,
             commands: commands_used_static,
+            stt_queue_depth,
+            stt_jobs_running,
+            stt_queue_wait,
+            stt_jobs_dropped: stt_jobs_dropped_static,
+            stt_confidence,
+            stt_low_confidence: stt_low_confidence_static,
         }
     }
 
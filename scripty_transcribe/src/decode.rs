@@ -0,0 +1,150 @@
+use audiopus::{coder::Decoder, packet::Packet, Channels, MutSignals, SampleRate};
+use ogg::PacketReader;
+use std::{
+    convert::TryFrom,
+    fs::File,
+    io::{BufReader, ErrorKind, Read, Seek, SeekFrom},
+    path::Path,
+};
+use symphonia::core::{
+    audio::SampleBuffer, codecs::DecoderOptions, errors::Error as SymphoniaError,
+    formats::FormatOptions, io::MediaSourceStream, meta::MetadataOptions, probe::Hint,
+};
+use tracing::warn;
+
+/// Opus always decodes at 48KHz, whatever the input was recorded at.
+const OPUS_SAMPLE_RATE: u32 = 48_000;
+/// The longest a single Opus packet can be: 120ms at 48KHz.
+const MAX_OPUS_FRAME: usize = 5_760;
+
+/// Interleaved 16 bit audio decoded from a file.
+pub struct DecodedAudio {
+    pub samples: Vec<i16>,
+    pub channels: usize,
+    pub sample_rate: u32,
+}
+
+/// Decode a WAV, FLAC, MP3, Ogg Vorbis or Ogg Opus file.
+pub fn decode_file(path: &Path) -> Result<DecodedAudio, String> {
+    let mut file = File::open(path).map_err(|e| format!("can't open file: {}", e))?;
+    // symphonia can't decode Opus, so those go through libopus instead
+    if is_ogg_opus(&mut file).map_err(|e| format!("can't read file: {}", e))? {
+        return decode_opus(file);
+    }
+
+    let mut hint = Hint::new();
+    if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+        hint.with_extension(ext);
+    }
+    let stream = MediaSourceStream::new(Box::new(file), Default::default());
+    let probed = symphonia::default::get_probe()
+        .format(
+            &hint,
+            stream,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .map_err(|e| format!("unsupported format: {}", e))?;
+    let mut format = probed.format;
+    let track = format
+        .default_track()
+        .ok_or_else(|| "file has no audio tracks".to_string())?;
+    let track_id = track.id;
+    let mut decoder = symphonia::default::get_codecs()
+        .make(&track.codec_params, &DecoderOptions::default())
+        .map_err(|e| format!("unsupported codec: {}", e))?;
+
+    let mut audio = DecodedAudio {
+        samples: Vec::new(),
+        channels: track.codec_params.channels.map_or(0, |c| c.count()),
+        sample_rate: track.codec_params.sample_rate.unwrap_or(0),
+    };
+    let mut buf: Option<SampleBuffer<i16>> = None;
+    loop {
+        let packet = match format.next_packet() {
+            Ok(p) => p,
+            Err(SymphoniaError::IoError(e)) if e.kind() == ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(format!("can't read file: {}", e)),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+        let decoded = match decoder.decode(&packet) {
+            Ok(d) => d,
+            // a corrupt packet only loses a few milliseconds, so keep going
+            Err(SymphoniaError::DecodeError(e)) => {
+                warn!("skipping corrupt packet: {}", e);
+                continue;
+            }
+            Err(e) => return Err(format!("can't decode file: {}", e)),
+        };
+        let spec = *decoded.spec();
+        let buf = match &mut buf {
+            Some(b) if b.capacity() >= decoded.capacity() * spec.channels.count() => b,
+            b => b.insert(SampleBuffer::new(decoded.capacity() as u64, spec)),
+        };
+        buf.copy_interleaved_ref(decoded);
+        audio.samples.extend_from_slice(buf.samples());
+        audio.channels = spec.channels.count();
+        audio.sample_rate = spec.rate;
+    }
+    Ok(audio)
+}
+
+/// Check whether `file` is an Ogg file holding an Opus stream, and rewind it.
+fn is_ogg_opus(file: &mut File) -> std::io::Result<bool> {
+    // the first page always holds just the Opus header, which starts at byte 28
+    let mut header = [0; 36];
+    let is_opus = match file.read_exact(&mut header) {
+        Ok(()) => &header[..4] == b"OggS" && &header[28..] == b"OpusHead",
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => false,
+        Err(e) => return Err(e),
+    };
+    file.seek(SeekFrom::Start(0))?;
+    Ok(is_opus)
+}
+
+fn decode_opus(file: File) -> Result<DecodedAudio, String> {
+    let mut reader = PacketReader::new(BufReader::new(file));
+    let mut next_packet = || {
+        reader
+            .read_packet()
+            .map_err(|e| format!("can't read file: {}", e))
+    };
+
+    // https://datatracker.ietf.org/doc/html/rfc7845#section-5.1
+    let head = next_packet()?.ok_or_else(|| "file is empty".to_string())?;
+    if head.data.len() < 19 {
+        return Err("invalid Opus header".to_string());
+    }
+    let channel_count = head.data[9] as usize;
+    let channels = match channel_count {
+        1 => Channels::Mono,
+        2 => Channels::Stereo,
+        n => return Err(format!("Opus files with {} channels aren't supported", n)),
+    };
+    // samples the encoder added to the start to prime itself, which aren't part of the audio
+    let pre_skip = u16::from_le_bytes([head.data[10], head.data[11]]) as usize;
+    // the comment header comes next, which has nothing we need
+    next_packet()?;
+
+    let mut decoder = Decoder::new(SampleRate::Hz48000, channels)
+        .map_err(|e| format!("can't create Opus decoder: {}", e))?;
+    let mut buf = vec![0; MAX_OPUS_FRAME * channel_count];
+    let mut samples = Vec::new();
+    while let Some(packet) = next_packet()? {
+        let decoded = Packet::try_from(&packet.data[..])
+            .and_then(|p| decoder.decode(Some(p), MutSignals::try_from(&mut buf[..])?, false));
+        match decoded {
+            Ok(len) => samples.extend_from_slice(&buf[..len * channel_count]),
+            Err(e) => warn!("skipping corrupt packet: {}", e),
+        }
+    }
+    samples.drain(..(pre_skip * channel_count).min(samples.len()));
+
+    Ok(DecodedAudio {
+        samples,
+        channels: channel_count,
+        sample_rate: OPUS_SAMPLE_RATE,
+    })
+}
@@ -0,0 +1,279 @@
+//! Transcribe audio files with the same models and pipeline the bot uses, without connecting
+//! to Discord.
+
+mod decode;
+mod output;
+
+use crate::decode::{decode_file, DecodedAudio};
+use crate::output::{format_transcript, OutputFormat};
+use scripty_audio_utils::{
+    load_backend, prepare_audio, transcribe_chunked, AudioFormat, ChannelLayout, HotWord,
+    SttBackend,
+};
+use scripty_config::BotConfig;
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process,
+    time::Instant,
+};
+use tracing::info;
+
+const USAGE: &str = "\
+usage: scripty-transcribe [options] <file>...
+
+Transcribes WAV, FLAC, MP3, Ogg Vorbis and Ogg Opus files.
+
+options:
+  -c, --config <path>       config file to read the models from (default: config.toml)
+  -l, --language <code>     language to transcribe as (default: the config's default_language)
+  -m, --model <dir>         use the model in this directory instead of the config's
+  -f, --format <format>     txt, json (with token timings), srt or vtt (default: txt)
+  -o, --output <dir>        write each transcript to <dir>/<file name>.<format>
+                            instead of stdout
+  -w, --hot-word <word[:boost]>
+                            bias the model towards a word, can be repeated
+      --denoise             run audio through the denoiser first
+  -h, --help                print this message";
+
+/// Boost for hot words given without one, the same as `config hotwords` uses.
+const DEFAULT_BOOST: f32 = 5.0;
+
+struct Options {
+    config: String,
+    language: Option<String>,
+    model: Option<PathBuf>,
+    format: OutputFormat,
+    output: Option<PathBuf>,
+    hot_words: Vec<HotWord>,
+    denoise: bool,
+    files: Vec<PathBuf>,
+}
+
+fn main() {
+    tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .with_writer(std::io::stderr)
+        .init();
+
+    let options = match parse_args(env::args().skip(1)) {
+        Ok(o) => o,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            process::exit(2);
+        }
+    };
+    if let Err(e) = run(&options) {
+        eprintln!("error: {}", e);
+        process::exit(1);
+    }
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options {
+        config: "config.toml".to_string(),
+        language: None,
+        model: None,
+        format: OutputFormat::Text,
+        output: None,
+        hot_words: Vec::new(),
+        denoise: false,
+        files: Vec::new(),
+    };
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| format!("`{}` needs a value", arg))
+        };
+        match arg.as_str() {
+            "-c" | "--config" => options.config = value()?,
+            "-l" | "--language" => options.language = Some(value()?),
+            "-m" | "--model" => options.model = Some(value()?.into()),
+            "-f" | "--format" => options.format = value()?.parse()?,
+            "-o" | "--output" => options.output = Some(value()?.into()),
+            "-w" | "--hot-word" => options.hot_words.push(parse_hot_word(&value()?)?),
+            "--denoise" => options.denoise = true,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            }
+            "--" => options.files.extend(args.by_ref().map(PathBuf::from)),
+            _ if arg.starts_with('-') => return Err(format!("unknown option `{}`", arg)),
+            _ => options.files.push(arg.into()),
+        }
+    }
+    if options.files.is_empty() {
+        return Err("no files to transcribe".to_string());
+    }
+    Ok(options)
+}
+
+fn parse_hot_word(arg: &str) -> Result<HotWord, String> {
+    let (word, boost) = match arg.rfind(':') {
+        Some(i) => (
+            &arg[..i],
+            arg[i + 1..]
+                .parse()
+                .map_err(|_| format!("invalid boost in `{}`", arg))?,
+        ),
+        None => (arg, DEFAULT_BOOST),
+    };
+    if word.is_empty() {
+        return Err(format!("invalid hot word `{}`", arg));
+    }
+    Ok(HotWord {
+        word: word.to_lowercase(),
+        boost,
+    })
+}
+
+fn run(options: &Options) -> Result<(), String> {
+    // BotConfig::set writes out a default config when there isn't one, which isn't wanted here
+    if !Path::new(&options.config).is_file() {
+        return Err(format!("config file `{}` doesn't exist", options.config));
+    }
+    BotConfig::set(&options.config);
+    let config = BotConfig::get().expect("Couldn't access BOT_CONFIG");
+
+    let language = options
+        .language
+        .as_deref()
+        .unwrap_or_else(|| config.default_language());
+    let model_dir = match &options.model {
+        Some(dir) => dir.clone(),
+        None => config
+            .model_dir(language)
+            .ok_or_else(|| format!("there's no model for the language `{}`", language))?
+            .into(),
+    };
+    let output_paths = output_paths(options)?;
+    if let Some(dir) = &options.output {
+        fs::create_dir_all(dir).map_err(|e| format!("can't create {}: {}", dir.display(), e))?;
+    }
+
+    info!("loading model from {}", model_dir.display());
+    let model = load_backend(config, &model_dir, language, None).map_err(|e| e.to_string())?;
+
+    let mut failed = 0;
+    for (file, path) in options.files.iter().zip(&output_paths) {
+        let st = Instant::now();
+        // keep going, so one bad file doesn't throw away a whole batch
+        let result = transcribe_file(options, &*model, file).and_then(|out| {
+            info!(
+                "transcribed {} in {}ms",
+                file.display(),
+                st.elapsed().as_millis()
+            );
+            write_output(path.as_deref(), &out)
+        });
+        if let Err(e) = result {
+            eprintln!("{}: {}", file.display(), e);
+            failed += 1;
+        }
+    }
+    if failed > 0 {
+        return Err(format!(
+            "{} of {} files failed",
+            failed,
+            options.files.len()
+        ));
+    }
+    Ok(())
+}
+
+fn transcribe_file(
+    options: &Options,
+    model: &dyn SttBackend,
+    file: &Path,
+) -> Result<String, String> {
+    let audio = decode_file(file)?;
+    if audio.sample_rate == 0 || audio.channels == 0 {
+        return Err("file doesn't say what its sample rate or channel count is".to_string());
+    }
+    let duration_ms = (audio.samples.len() as u64 * 1000
+        / audio.channels as u64
+        / audio.sample_rate as u64) as u32;
+    let (samples, format) = to_supported_layout(audio);
+
+    let samples = prepare_audio(&samples, format, model.sample_rate(), options.denoise);
+    let transcript =
+        transcribe_chunked(model, &samples, &options.hot_words, 1).map_err(|e| e.to_string())?;
+    Ok(format_transcript(
+        options.format,
+        &transcript,
+        model,
+        &file.to_string_lossy(),
+        duration_ms,
+    ))
+}
+
+/// Mix audio with more than two channels down to mono, since that's all `prepare_audio`
+/// takes besides stereo.
+fn to_supported_layout(audio: DecodedAudio) -> (Vec<i16>, AudioFormat) {
+    let layout = match audio.channels {
+        1 => ChannelLayout::Mono,
+        2 => ChannelLayout::Stereo,
+        n => {
+            let samples = audio
+                .samples
+                .chunks_exact(n)
+                .map(|frame| (frame.iter().map(|s| *s as i32).sum::<i32>() / n as i32) as i16)
+                .collect();
+            return (
+                samples,
+                AudioFormat {
+                    layout: ChannelLayout::Mono,
+                    sample_rate: audio.sample_rate,
+                },
+            );
+        }
+    };
+    (
+        audio.samples,
+        AudioFormat {
+            layout,
+            sample_rate: audio.sample_rate,
+        },
+    )
+}
+
+/// Where to write each file's transcript, in the same order as `options.files`, or all `None`
+/// for stdout.
+///
+/// Transcripts are named after their file without its extension, so this refuses files that
+/// would be written over each other, like `a/x.wav` and `b/x.wav`, before any work is done.
+fn output_paths(options: &Options) -> Result<Vec<Option<PathBuf>>, String> {
+    let dir = match &options.output {
+        Some(dir) => dir,
+        None => return Ok(vec![None; options.files.len()]),
+    };
+    let mut paths: Vec<PathBuf> = Vec::with_capacity(options.files.len());
+    for (i, file) in options.files.iter().enumerate() {
+        let name = file
+            .file_stem()
+            .map_or_else(|| "transcript".into(), |s| s.to_string_lossy());
+        let path = dir.join(format!("{}.{}", name, options.format.extension()));
+        if let Some(j) = paths.iter().position(|p| *p == path) {
+            return Err(format!(
+                "{} and {} would both be written to {}, transcribe them separately",
+                options.files[j].display(),
+                options.files[i].display(),
+                path.display()
+            ));
+        }
+        paths.push(path);
+    }
+    Ok(paths.into_iter().map(Some).collect())
+}
+
+fn write_output(path: Option<&Path>, out: &str) -> Result<(), String> {
+    match path {
+        Some(path) => {
+            fs::write(path, out).map_err(|e| format!("can't write {}: {}", path.display(), e))
+        }
+        None => {
+            print!("{}", out);
+            Ok(())
+        }
+    }
+}
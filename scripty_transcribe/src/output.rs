@@ -0,0 +1,83 @@
+use scripty_audio_utils::{build_cues, to_srt, to_webvtt, Candidate, SttBackend, Transcript};
+use serde_json::json;
+use std::str::FromStr;
+
+/// What to write transcripts out as.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    Text,
+    Json,
+    Srt,
+    WebVtt,
+}
+
+impl OutputFormat {
+    /// The file extension for this format, without the dot.
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Text => "txt",
+            OutputFormat::Json => "json",
+            OutputFormat::Srt => "srt",
+            OutputFormat::WebVtt => "vtt",
+        }
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "txt" | "text" => OutputFormat::Text,
+            "json" => OutputFormat::Json,
+            "srt" => OutputFormat::Srt,
+            "vtt" | "webvtt" => OutputFormat::WebVtt,
+            _ => {
+                return Err(format!(
+                    "unknown format `{}`, expected txt, json, srt or vtt",
+                    s
+                ))
+            }
+        })
+    }
+}
+
+/// Format the transcript of the file at `source`, which was `duration_ms` long.
+pub fn format_transcript(
+    format: OutputFormat,
+    transcript: &Transcript,
+    model: &dyn SttBackend,
+    source: &str,
+    duration_ms: u32,
+) -> String {
+    let empty = Candidate::default();
+    let best = transcript.best().unwrap_or(&empty);
+    match format {
+        OutputFormat::Text => format!("{}\n", best.text),
+        OutputFormat::Json => {
+            let tokens: Vec<_> = best
+                .tokens
+                .iter()
+                .map(|t| json!({"text": t.text, "start_ms": t.start_ms, "end_ms": t.end_ms}))
+                .collect();
+            let words: Vec<_> = best
+                .words()
+                .into_iter()
+                .map(|w| json!({"text": w.text, "start_ms": w.start_ms, "end_ms": w.end_ms}))
+                .collect();
+            let mut out = serde_json::to_string_pretty(&json!({
+                "file": source,
+                "duration_ms": duration_ms,
+                "text": best.text,
+                "confidence": model.normalized_confidence(best),
+                "words": words,
+                "tokens": tokens,
+            }))
+            .expect("failed to serialize transcript");
+            out.push('\n');
+            out
+        }
+        OutputFormat::Srt => to_srt(&build_cues(&best.words())),
+        OutputFormat::WebVtt => to_webvtt(&build_cues(&best.words())),
+    }
+}
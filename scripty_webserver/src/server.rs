@@ -1,5 +1,7 @@
-use rocket::Shutdown;
+use rocket::{http::ContentType, Shutdown};
+use scripty_config::BotConfig;
 use scripty_metrics::serialize_metrics;
+use std::path::Path;
 use tokio::sync::oneshot::{self, Receiver};
 /*
 use rocket::http::Status;
@@ -13,6 +15,25 @@ async fn metrics() -> Vec<u8> {
     serialize_metrics()
 }
 
+/// Download a file from a recording session that was too big to upload to Discord.
+///
+/// Session IDs are random, so knowing the link is what gives access to it.
+#[rocket::get("/recordings/<guild_id>/<session>/<file>")]
+async fn recording(guild_id: u64, session: &str, file: &str) -> Option<(ContentType, Vec<u8>)> {
+    // only ever serve what recording sessions write, never anything outside of them
+    let is_session = session.len() == 32 && session.chars().all(|c| c.is_ascii_hexdigit());
+    let user_id = file.strip_suffix(".opus")?;
+    if !is_session || user_id.is_empty() || !user_id.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let path = Path::new(BotConfig::get()?.recording_dir())
+        .join(guild_id.to_string())
+        .join(session)
+        .join(file);
+    let data = tokio::fs::read(path).await.ok()?;
+    Some((ContentType::new("audio", "ogg"), data))
+}
+
 /*
 TODO: actually implement authorization and a speech to text API
 
@@ -56,6 +77,7 @@ async fn _start(tx: oneshot::Sender<Shutdown>) {
     let r = rocket::build()
         .mount("/", rocket::routes![metrics])
         .mount("/", rocket::routes![root])
+        .mount("/", rocket::routes![recording])
         .ignite()
         .await
         .expect("failed to ignite server");